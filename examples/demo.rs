@@ -1,4 +1,4 @@
-use kmbox_net::{keys, KmBoxNetClient, KmBoxNetMonitor};
+use kmbox_net::{Key, KmBoxNetClient, KmBoxNetMonitor};
 use std::thread;
 use std::time::Duration;
 
@@ -66,22 +66,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Helper closure for typing
     let type_key =
-        |client: &mut KmBoxNetClient, key: u8| -> Result<(), Box<dyn std::error::Error>> {
-            client.keypress(key as i32, 50)?; // Press for 50ms
+        |client: &mut KmBoxNetClient, key: Key| -> Result<(), Box<dyn std::error::Error>> {
+            client.keypress(key, 50)?; // Press for 50ms
             thread::sleep(Duration::from_millis(50)); // Wait between keys
             Ok(())
         };
 
     // Shift + h for 'H'
-    kmbox.keydown(keys::KEY_LEFTSHIFT as i32)?;
-    type_key(&mut kmbox, keys::KEY_H as u8)?;
-    kmbox.keyup(keys::KEY_LEFTSHIFT as i32)?;
+    kmbox.keydown(Key::LeftShift)?;
+    type_key(&mut kmbox, Key::H)?;
+    kmbox.keyup(Key::LeftShift)?;
 
     // 'e', 'l', 'l', 'o'
-    type_key(&mut kmbox, keys::KEY_E as u8)?;
-    type_key(&mut kmbox, keys::KEY_L as u8)?;
-    type_key(&mut kmbox, keys::KEY_L as u8)?;
-    type_key(&mut kmbox, keys::KEY_O as u8)?;
+    type_key(&mut kmbox, Key::E)?;
+    type_key(&mut kmbox, Key::L)?;
+    type_key(&mut kmbox, Key::L)?;
+    type_key(&mut kmbox, Key::O)?;
 
     // --- Monitor Demonstration ---
     println!("\nStarting Monitor...");
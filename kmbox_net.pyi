@@ -1,12 +1,71 @@
-from typing import Callable, List
+from enum import IntEnum
+from typing import Any, Callable, Dict, List, Optional
 
-def xxtea_encrypt(data: bytearray, key: bytes) -> None:
+class KmBoxError(Exception):
+    """
+    KmBoxNet 所有错误的基类。
+    """
+
+    code: Optional[int]
+    """C SDK 中对应的错误码 (例如接收超时为 -8997)，客户端被其他线程占用时为 None"""
+    cmd: Optional[int]
+    """出错指令的 CMD_* 值 (如有)"""
+    indexpts: Optional[int]
+    """出错指令的序号 (如有)"""
+
+class ConnectError(KmBoxError):
+    """创建套接字或与设备握手失败 (例如 IP 或 MAC 错误)。"""
+
+class TimeoutError(KmBoxError):
+    """设备未在超时时间内应答指令。"""
+
+class TransmitError(KmBoxError):
+    """指令发送失败。"""
+
+class CommandError(KmBoxError):
+    """参数不合法，或设备应答与指令不匹配。"""
+
+def xxtea_encrypt(data: bytearray, key: bytes, standard: bool = False) -> None:
     """
     使用 XXTEA 算法加密数据 (原地修改)。
 
     Args:
         data: 要加密的数据缓冲区 (必须是 bytearray，且长度至少为 128 字节)。
         key: 16 字节的加密密钥。
+        standard: True 时使用标准 XXTEA (整个缓冲区，轮数 6 + 52/n，
+                  长度须为 4 的倍数且至少 8 字节)；默认使用盒子的 128 字节 6 轮模式。
+
+    Raises:
+        ValueError: standard=True 且长度不合法。
+    """
+    ...
+
+def xxtea_decrypt(data: bytearray, key: bytes, standard: bool = False) -> None:
+    """
+    使用 XXTEA 算法解密数据 (原地修改)，是 xxtea_encrypt 的逆运算。
+
+    Args:
+        data: 要解密的数据缓冲区 (必须是 bytearray)。
+        key: 16 字节的加密密钥。
+        standard: 与加密时使用的模式一致。
+
+    Raises:
+        ValueError: standard=True 且长度不合法。
+    """
+    ...
+
+def enable_logging(level: str = "DEBUG") -> None:
+    """
+    把协议收发日志转发到 Python 的 logging 模块 (logger 名为 "kmbox_net")。
+    需要以 tracing feature 编译。
+
+    Args:
+        level: 转发的最详细级别："ERROR"、"WARN"、"INFO"、"DEBUG" (每条指令和应答)
+               或 "TRACE" (另含监听数据)。
+
+    Raises:
+        RuntimeError: 已经调用过一次。
+        ValueError: 未知的级别。
     """
     ...
 
@@ -34,12 +93,33 @@ class HardKeyboard:
     data: List[int]
     """当前按下的普通键码列表 (通常最多10个键)"""
 
+class Capture:
+    """
+    抓包输出，可同时传给客户端和监听器，记录到同一个文件。
+    写入失败会被忽略，不影响指令；调用 flush() 时报告。
+    """
+    @staticmethod
+    def pcap(path: str) -> "Capture":
+        """记录为 pcap 文件 (IPv4/UDP)，可用 Wireshark 打开。"""
+        ...
+    @staticmethod
+    def jsonl(path: str) -> "Capture":
+        """记录为 JSONL，每行一个数据包，含时间戳和解码后的 CmdHead 或监听数据。"""
+        ...
+    def flush(self) -> None:
+        """把缓冲的数据写入文件。"""
+        ...
+
 class KmBoxNetMonitor:
     """
     KmBoxNet 监听器，用于接收并处理来自硬件的物理键鼠事件
     """
     def __init__(
-        self, port: int, callback: Callable[[HardMouse, HardKeyboard], None]
+        self,
+        port: int,
+        callback: Callable[[HardMouse, HardKeyboard], None],
+        *,
+        capture: Optional[Capture] = None,
     ) -> None:
         """
         初始化监听器并开始后台监听线程。
@@ -47,17 +127,139 @@ class KmBoxNetMonitor:
         Args:
             port: 本地监听端口 (需与盒子端配置的推流端口一致)。
             callback: 回调函数，当收到数据时调用。签名: (mouse: HardMouse, keyboard: HardKeyboard) -> None
+            capture: 记录收到的每个数据包。
         """
         ...
     def shutdown(self) -> None:
-        """停止监听线程"""
+        """停止监听线程并等待其退出。监听器被回收时也会停止线程，但不等待。"""
+        ...
+
+class ConnectionState:
+    """
+    与盒子的连接状态，每条指令后更新。
+    """
+
+    Connected: "ConnectionState"
+    """最近一条指令收到了应答"""
+    Degraded: "ConnectionState"
+    """最近的指令超时，但未达到 disconnect_after"""
+    Disconnected: "ConnectionState"
+    """握手失败，或连续 disconnect_after 条指令超时"""
+
+class Layout:
+    """
+    目标主机上配置的键盘布局。盒子发送的是按键位置 (HID 键码)，
+    同一位置输入什么字符由主机布局决定。
+    """
+
+    US: "Layout"
+    """美式 QWERTY"""
+    UK: "Layout"
+    """英式 QWERTY"""
+    DE: "Layout"
+    """德式 QWERTZ"""
+    FR: "Layout"
+    """法式 AZERTY"""
+
+class InputSession:
+    """
+    `session()` 返回的上下文管理器。`with` / `async with` 块结束时
+    (无论是否抛出异常) 调用客户端的 release_all，不会吞掉块内的异常。
+    """
+
+    def __enter__(self) -> Any: ...
+    def __exit__(self, *exc: Any) -> bool: ...
+    async def __aenter__(self) -> Any: ...
+    async def __aexit__(self, *exc: Any) -> None: ...
+
+class LatencyStats:
+    """
+    心跳统计。延迟为最近 100 次成功 ping 的数据，在第一次成功前为 None。
+    """
+
+    sent: int
+    """已发送的 ping 数"""
+    lost: int
+    """没有收到应答的 ping 数"""
+    last_ms: Optional[float]
+    """最近一次往返延迟 (毫秒)"""
+    min_ms: Optional[float]
+    """最小延迟 (毫秒)"""
+    avg_ms: Optional[float]
+    """平均延迟 (毫秒)"""
+    p99_ms: Optional[float]
+    """99 分位延迟 (毫秒)"""
+
+class Heartbeat:
+    """
+    后台心跳线程，由 SharedKmBoxNetClient.start_heartbeat 创建。
+    """
+    def stats(self) -> LatencyStats:
+        """当前的延迟与丢包统计。"""
+        ...
+    def stop(self) -> None:
+        """停止心跳线程。"""
+        ...
+
+class ClientStats:
+    """
+    客户端创建以来的流量统计。每条指令只计一次；bytes_sent 和 timeouts 包含重发。
+    """
+
+    commands: Dict[str, int]
+    """按指令名 (如 "CMD_MOUSE_MOVE") 统计的发送次数，握手计为 CMD_CONNECT"""
+    bytes_sent: int
+    """发送的字节数"""
+    acks: int
+    """收到的应答数"""
+    timeouts: int
+    """等待应答超时的次数"""
+    retries: int
+    """重发次数"""
+    encrypted: int
+    """加密发送的指令数"""
+    plain: int
+    """明文发送的指令数"""
+    def total_commands(self) -> int:
+        """发送的指令总数。"""
+        ...
+    def to_prometheus(self) -> str:
+        """Prometheus 文本格式。需要以 prometheus feature 编译。"""
+        ...
+
+class MetricsServer:
+    """
+    Prometheus 指标 HTTP 服务，由 SharedKmBoxNetClient.serve_metrics 创建。
+    需要以 prometheus feature 编译。
+    """
+
+    port: int
+    """监听端口"""
+    def stop(self) -> None:
+        """停止服务。"""
         ...
 
 class KmBoxNetClient:
     """
     KmBoxNet 客户端，用于连接设备并发送控制指令。
+
+    同一时间只能被一个线程使用：一条指令等待应答时，其他线程 (包括监听回调)
+    调用该客户端会抛出 KmBoxError。多线程请使用 SharedKmBoxNetClient。
     """
-    def __init__(self, ip: str, port: int, mac: str) -> None:
+    def __init__(
+        self,
+        ip: str,
+        port: int,
+        mac: str,
+        *,
+        connect_timeout_ms: int = 3000,
+        timeout_ms: int = 3000,
+        retries: int = 0,
+        backoff_ms: int = 0,
+        auto_reconnect: bool = False,
+        disconnect_after: int = 3,
+        layout: Layout = Layout.US,
+    ) -> None:
         """
         创建客户端并连接设备。
 
@@ -65,6 +267,17 @@ class KmBoxNetClient:
             ip: KmBox 设备的 IP 地址。
             port: KmBox 设备的端口号 (默认通常是 8888)。
             mac: 设备的 MAC 地址 (格式如 "00-11-22-33-44-55")，用于通信加密握手。
+            connect_timeout_ms: 握手等待应答的超时 (毫秒)。
+            timeout_ms: 每条指令等待应答的超时 (毫秒)。
+            retries: 超时后以相同序号重发的次数。
+            backoff_ms: 第一次重发前的等待 (毫秒)，之后每次翻倍。
+            auto_reconnect: 连接断开后，在下一条指令前自动重新握手并恢复状态。
+            disconnect_after: 连续多少条指令超时后视为断开。
+            layout: 目标主机的键盘布局，供 type_text 使用 (默认 Layout.US)。
+
+        Raises:
+            ConnectError: 握手失败。
+            其余方法失败时抛出 KmBoxError 的子类。
         """
         ...
 
@@ -203,6 +416,9 @@ class KmBoxNetClient:
 
         Args:
             vkey: 键码 (建议使用模块提供的 KEY_* 常量)。
+
+        Raises:
+            CommandError: vkey 不是有效的 HID 键码 (所有接收键码的方法相同)。
         """
         ...
     def keyup(self, vkey: int) -> None:
@@ -222,6 +438,43 @@ class KmBoxNetClient:
             ms: 按住持续时间 (毫秒)。
         """
         ...
+    def chord(self, vkeys: list[int], ms: int) -> None:
+        """
+        组合键：在同一个报告中按下所有按键 (修饰键置位 ctrl)，保持 ms 毫秒后
+        在同一个报告中全部松开。
+
+        Args:
+            vkeys: 按键码列表，例如 [KEY_LEFTCONTROL, KEY_LEFTSHIFT, KEY_ESCAPE]。
+            ms: 按住时长 (毫秒)。
+        """
+        ...
+    def hotkey(self, hotkey: str, ms: int) -> None:
+        """
+        按名称发送组合键，参见 `chord`。
+
+        Args:
+            hotkey: 用 `+` 连接的键名 (不区分大小写)，例如 "ctrl+shift+esc"、
+                "alt+f4"、"win+r"。ctrl/shift/alt/win 为左侧修饰键，
+                rctrl/rshift/ralt(altgr)/rwin 为右侧。
+            ms: 按住时长 (毫秒)。
+
+        Raises:
+            CommandError: 包含未知键名，此时不会发送任何按键。
+        """
+        ...
+    def type_text(self, text: str, per_key_ms: int) -> None:
+        """
+        按构造时指定的主机键盘布局输入一段文本，需要时自动按住左 Shift
+        或 AltGr (右 Alt)。死键字符后会自动补一个空格。
+
+        Args:
+            text: 要输入的文本，`\\n` 为回车，`\\t` 为 Tab。
+            per_key_ms: 每个字符的按键时长 (毫秒)。
+
+        Raises:
+            CommandError: 文本包含无法输入的字符，此时不会发送任何按键。
+        """
+        ...
 
     # Encrypted Keyboard Functions
     def enc_keydown(self, vkey: int) -> None:
@@ -296,6 +549,69 @@ class KmBoxNetClient:
         解除所有键盘和鼠标的屏蔽状态 (恢复正常)。
         """
         ...
+    def release_all(self) -> None:
+        """
+        松开所有按键和鼠标按键：清空本地记录的按键状态，并发送空的键盘
+        报告和鼠标报告 (即使本地没有按住任何键，也能释放之前进程遗留的按键)。
+        """
+        ...
+    def pressed_keys(self) -> List[int]:
+        """
+        客户端认为当前按住的普通按键 (不含修饰键)，按按下顺序排列。
+        可用 `Key(code)` 转换为枚举。
+        """
+        ...
+    def pressed_modifiers(self) -> List[int]:
+        """客户端认为当前按住的修饰键 (KEY_LEFTCONTROL 到 KEY_RIGHT_GUI)。"""
+        ...
+    def pressed_buttons(self) -> int:
+        """
+        客户端认为当前按住的鼠标按键掩码，与 mouse_all 的 button 相同：
+        0x01 左键，0x02 右键，0x04 中键，0x08 侧键1，0x10 侧键2。
+        """
+        ...
+    def is_pressed(self, vkey: int) -> bool:
+        """指定按键 (含修饰键) 是否处于按下状态。"""
+        ...
+    def session(self) -> InputSession:
+        """
+        返回上下文管理器，离开 with 块时 (包括抛出异常) 调用 release_all。
+
+        示例:
+            with client.session():
+                client.keydown(KEY_LEFTSHIFT)
+                client.mouse_left(True)
+                ...  # 即使这里抛出异常，按键也会被松开
+        """
+        ...
+
+    # Connection Functions
+    def ping(self) -> float:
+        """
+        测量到盒子的往返延迟 (毫秒)。协议没有空指令，因此会向主机发送一个
+        真实的鼠标报告 (CMD_MOUSE_MOVE，位移为 0，保持当前按键)。不会重发。
+
+        Raises:
+            TimeoutError: 在 timeout_ms 内没有收到应答。
+        """
+        ...
+    def connection_state(self) -> ConnectionState:
+        """返回最近一条指令后的连接状态。"""
+        ...
+    def stats(self) -> ClientStats:
+        """返回流量统计的快照。"""
+        ...
+    def set_capture(self, capture: Optional[Capture]) -> None:
+        """记录之后收发的每个数据包，传入 None 停止记录。"""
+        ...
+    def reconnect(self) -> None:
+        """
+        重新握手 (例如盒子重启后)，并恢复屏蔽状态、按住的鼠标按键和键盘按键。
+
+        Raises:
+            ConnectError: 握手失败。
+        """
+        ...
 
     # System Configuration Functions
     def reboot(self) -> None:
@@ -353,7 +669,663 @@ class KmBoxNetClient:
         """
         ...
 
-# 键码常量定义
+class AsyncKmBoxNetClient:
+    """
+    KmBoxNet 的 asyncio 客户端。
+
+    指令在后台线程中按调用顺序执行，所有方法都返回可 await 的对象，
+    不会阻塞事件循环。方法与 KmBoxNetClient 相同。
+    """
+    @staticmethod
+    async def connect(
+        ip: str,
+        port: int,
+        mac: str,
+        *,
+        connect_timeout_ms: int = 3000,
+        timeout_ms: int = 3000,
+        retries: int = 0,
+        backoff_ms: int = 0,
+        auto_reconnect: bool = False,
+        disconnect_after: int = 3,
+        layout: Layout = Layout.US,
+    ) -> "AsyncKmBoxNetClient":
+        """
+        在后台连接设备，参数同 KmBoxNetClient。
+
+        Raises:
+            ConnectError: 握手失败。
+        """
+        ...
+    def close(self) -> None:
+        """已提交的指令执行完后停止后台线程。之后调用任何方法都会抛出 RuntimeError。"""
+        ...
+    async def mouse_move(self, x: int, y: int) -> None:
+        """鼠标相对移动。"""
+        ...
+    async def mouse_left(self, is_down: bool) -> None:
+        """鼠标左键控制。"""
+        ...
+    async def mouse_right(self, is_down: bool) -> None:
+        """鼠标右键控制。"""
+        ...
+    async def mouse_middle(self, is_down: bool) -> None:
+        """鼠标中键控制。"""
+        ...
+    async def mouse_side1(self, is_down: bool) -> None:
+        """鼠标侧键1控制。"""
+        ...
+    async def mouse_side2(self, is_down: bool) -> None:
+        """鼠标侧键2控制。"""
+        ...
+    async def mouse_wheel(self, wheel: int) -> None:
+        """鼠标滚轮控制。"""
+        ...
+    async def mouse_all(self, button: int, x: int, y: int, wheel: int) -> None:
+        """发送鼠标完整状态。"""
+        ...
+    async def mouse_move_auto(self, x: int, y: int, ms: int) -> None:
+        """自动平滑移动。"""
+        ...
+    async def mouse_move_beizer(
+        self, x: int, y: int, ms: int, x1: int, y1: int, x2: int, y2: int
+    ) -> None:
+        """贝塞尔曲线移动。"""
+        ...
+    async def enc_mouse_move(self, x: int, y: int) -> None:
+        """[加密] 鼠标相对移动。"""
+        ...
+    async def enc_mouse_left(self, is_down: bool) -> None:
+        """[加密] 鼠标左键控制。"""
+        ...
+    async def enc_mouse_right(self, is_down: bool) -> None:
+        """[加密] 鼠标右键控制。"""
+        ...
+    async def enc_mouse_middle(self, is_down: bool) -> None:
+        """[加密] 鼠标中键控制。"""
+        ...
+    async def enc_mouse_side1(self, is_down: bool) -> None:
+        """[加密] 鼠标侧键1控制。"""
+        ...
+    async def enc_mouse_side2(self, is_down: bool) -> None:
+        """[加密] 鼠标侧键2控制。"""
+        ...
+    async def enc_mouse_wheel(self, wheel: int) -> None:
+        """[加密] 鼠标滚轮控制。"""
+        ...
+    async def enc_mouse_all(self, button: int, x: int, y: int, wheel: int) -> None:
+        """[加密] 发送鼠标完整状态。"""
+        ...
+    async def enc_mouse_move_auto(self, x: int, y: int, ms: int) -> None:
+        """[加密] 自动平滑移动。"""
+        ...
+    async def enc_mouse_move_beizer(
+        self, x: int, y: int, ms: int, x1: int, y1: int, x2: int, y2: int
+    ) -> None:
+        """[加密] 贝塞尔曲线移动。"""
+        ...
+    async def keydown(self, vkey: int) -> None:
+        """按下指定按键。"""
+        ...
+    async def keyup(self, vkey: int) -> None:
+        """弹起指定按键。"""
+        ...
+    async def keypress(self, vkey: int, ms: int) -> None:
+        """点击按键，延时在后台线程中进行，不会阻塞事件循环。"""
+        ...
+    async def chord(self, vkeys: list[int], ms: int) -> None:
+        """组合键，参见 `KmBoxNetClient.chord`。"""
+        ...
+    async def hotkey(self, hotkey: str, ms: int) -> None:
+        """按名称发送组合键，参见 `KmBoxNetClient.hotkey`。"""
+        ...
+    async def type_text(self, text: str, per_key_ms: int) -> None:
+        """输入一段文本，参见 `KmBoxNetClient.type_text`。"""
+        ...
+    async def enc_keydown(self, vkey: int) -> None:
+        """[加密] 按下指定按键。"""
+        ...
+    async def enc_keyup(self, vkey: int) -> None:
+        """[加密] 弹起指定按键。"""
+        ...
+    async def enc_keypress(self, vkey: int, ms: int) -> None:
+        """[加密] 点击按键。"""
+        ...
+    async def monitor(self, port: int) -> None:
+        """开启/关闭物理键鼠监听推流。"""
+        ...
+    async def mask_mouse_left(self, enable: bool) -> None:
+        """屏蔽物理鼠标左键。"""
+        ...
+    async def mask_mouse_right(self, enable: bool) -> None:
+        """屏蔽物理鼠标右键。"""
+        ...
+    async def mask_mouse_middle(self, enable: bool) -> None:
+        """屏蔽物理鼠标中键。"""
+        ...
+    async def mask_mouse_side1(self, enable: bool) -> None:
+        """屏蔽物理鼠标侧键1。"""
+        ...
+    async def mask_mouse_side2(self, enable: bool) -> None:
+        """屏蔽物理鼠标侧键2。"""
+        ...
+    async def mask_mouse_x(self, enable: bool) -> None:
+        """屏蔽物理鼠标X轴。"""
+        ...
+    async def mask_mouse_y(self, enable: bool) -> None:
+        """屏蔽物理鼠标Y轴。"""
+        ...
+    async def mask_mouse_wheel(self, enable: bool) -> None:
+        """屏蔽物理鼠标滚轮。"""
+        ...
+    async def mask_keyboard(self, vkey: int) -> None:
+        """屏蔽物理键盘的指定按键。"""
+        ...
+    async def unmask_keyboard(self, vkey: int) -> None:
+        """解除屏蔽物理键盘的指定按键。"""
+        ...
+    async def unmask_all(self) -> None:
+        """解除所有屏蔽。"""
+        ...
+    async def release_all(self) -> None:
+        """松开所有按键和鼠标按键，参见 `KmBoxNetClient.release_all`。"""
+        ...
+    def session(self) -> InputSession:
+        """用于 `async with client.session():`，退出时 await release_all。"""
+        ...
+    async def pressed_keys(self) -> List[int]:
+        """之前排队的指令执行完后按住的普通按键，参见 `KmBoxNetClient.pressed_keys`。"""
+        ...
+    async def pressed_modifiers(self) -> List[int]:
+        """之前排队的指令执行完后按住的修饰键。"""
+        ...
+    async def pressed_buttons(self) -> int:
+        """之前排队的指令执行完后按住的鼠标按键掩码。"""
+        ...
+    async def is_pressed(self, vkey: int) -> bool:
+        """之前排队的指令执行完后指定按键是否按下。"""
+        ...
+    async def ping(self) -> float:
+        """测量往返延迟 (毫秒)。会发送一个空鼠标报告，见 KmBoxNetClient.ping。"""
+        ...
+    async def set_capture(self, capture: Optional[Capture]) -> None:
+        """从之后提交的指令开始记录收发的数据包，传入 None 停止记录。"""
+        ...
+    async def stats(self) -> ClientStats:
+        """之前提交的指令执行完后的流量统计。"""
+        ...
+    async def connection_state(self) -> ConnectionState:
+        """之前提交的指令执行完后的连接状态。"""
+        ...
+    async def reconnect(self) -> None:
+        """重新握手并恢复屏蔽和按键状态。"""
+        ...
+    async def reboot(self) -> None:
+        """重启盒子。"""
+        ...
+    async def set_config(self, ip: str, port: int) -> None:
+        """修改盒子的 IP 与端口。"""
+        ...
+    async def set_vid_pid(self, vid: int, pid: int) -> None:
+        """设置盒子 device 端的 VID/PID。"""
+        ...
+    async def lcd_color(self, rgb565: int) -> None:
+        """LCD 整屏填充颜色。"""
+        ...
+    async def lcd_picture_bottom(self, buff: bytes) -> None:
+        """在 LCD 底部显示 128x80 图片。"""
+        ...
+    async def lcd_picture(self, buff: bytes) -> None:
+        """LCD 整屏显示 128x160 图片。"""
+        ...
+    async def trace_enable(self, type_: int, value: int) -> None:
+        """开启/关闭硬件轨迹修正算法。"""
+        ...
+
+class SharedKmBoxNetClient:
+    """
+    可在多个线程间共享的 KmBoxNet 客户端。
+
+    指令在内部加锁串行执行，序号与按键/鼠标按键状态保持一致；
+    等待锁和应答时会释放 GIL。方法与 KmBoxNetClient 相同。
+    """
+    def __init__(
+        self,
+        ip: str,
+        port: int,
+        mac: str,
+        *,
+        connect_timeout_ms: int = 3000,
+        timeout_ms: int = 3000,
+        retries: int = 0,
+        backoff_ms: int = 0,
+        auto_reconnect: bool = False,
+        disconnect_after: int = 3,
+        layout: Layout = Layout.US,
+    ) -> None:
+        """
+        创建客户端并连接设备，参数同 KmBoxNetClient。
+
+        Raises:
+            ConnectError: 握手失败。
+        """
+        ...
+    def mouse_move(self, x: int, y: int) -> None:
+        """鼠标相对移动。"""
+        ...
+    def mouse_left(self, is_down: bool) -> None:
+        """鼠标左键控制。"""
+        ...
+    def mouse_right(self, is_down: bool) -> None:
+        """鼠标右键控制。"""
+        ...
+    def mouse_middle(self, is_down: bool) -> None:
+        """鼠标中键控制。"""
+        ...
+    def mouse_side1(self, is_down: bool) -> None:
+        """鼠标侧键1控制。"""
+        ...
+    def mouse_side2(self, is_down: bool) -> None:
+        """鼠标侧键2控制。"""
+        ...
+    def mouse_wheel(self, wheel: int) -> None:
+        """鼠标滚轮控制。"""
+        ...
+    def mouse_all(self, button: int, x: int, y: int, wheel: int) -> None:
+        """发送鼠标完整状态。"""
+        ...
+    def mouse_move_auto(self, x: int, y: int, ms: int) -> None:
+        """自动平滑移动。"""
+        ...
+    def mouse_move_beizer(
+        self, x: int, y: int, ms: int, x1: int, y1: int, x2: int, y2: int
+    ) -> None:
+        """贝塞尔曲线移动。"""
+        ...
+    def enc_mouse_move(self, x: int, y: int) -> None:
+        """[加密] 鼠标相对移动。"""
+        ...
+    def enc_mouse_left(self, is_down: bool) -> None:
+        """[加密] 鼠标左键控制。"""
+        ...
+    def enc_mouse_right(self, is_down: bool) -> None:
+        """[加密] 鼠标右键控制。"""
+        ...
+    def enc_mouse_middle(self, is_down: bool) -> None:
+        """[加密] 鼠标中键控制。"""
+        ...
+    def enc_mouse_side1(self, is_down: bool) -> None:
+        """[加密] 鼠标侧键1控制。"""
+        ...
+    def enc_mouse_side2(self, is_down: bool) -> None:
+        """[加密] 鼠标侧键2控制。"""
+        ...
+    def enc_mouse_wheel(self, wheel: int) -> None:
+        """[加密] 鼠标滚轮控制。"""
+        ...
+    def enc_mouse_all(self, button: int, x: int, y: int, wheel: int) -> None:
+        """[加密] 发送鼠标完整状态。"""
+        ...
+    def enc_mouse_move_auto(self, x: int, y: int, ms: int) -> None:
+        """[加密] 自动平滑移动。"""
+        ...
+    def enc_mouse_move_beizer(
+        self, x: int, y: int, ms: int, x1: int, y1: int, x2: int, y2: int
+    ) -> None:
+        """[加密] 贝塞尔曲线移动。"""
+        ...
+    def keydown(self, vkey: int) -> None:
+        """按下指定按键。"""
+        ...
+    def keyup(self, vkey: int) -> None:
+        """弹起指定按键。"""
+        ...
+    def keypress(self, vkey: int, ms: int) -> None:
+        """点击按键。"""
+        ...
+    def chord(self, vkeys: list[int], ms: int) -> None:
+        """组合键，参见 `KmBoxNetClient.chord`。"""
+        ...
+    def hotkey(self, hotkey: str, ms: int) -> None:
+        """按名称发送组合键，参见 `KmBoxNetClient.hotkey`。"""
+        ...
+    def type_text(self, text: str, per_key_ms: int) -> None:
+        """输入一段文本，输入期间其他线程的指令会等待。"""
+        ...
+    def enc_keydown(self, vkey: int) -> None:
+        """[加密] 按下指定按键。"""
+        ...
+    def enc_keyup(self, vkey: int) -> None:
+        """[加密] 弹起指定按键。"""
+        ...
+    def enc_keypress(self, vkey: int, ms: int) -> None:
+        """[加密] 点击按键。"""
+        ...
+    def monitor(self, port: int) -> None:
+        """开启/关闭物理键鼠监听推流。"""
+        ...
+    def mask_mouse_left(self, enable: bool) -> None:
+        """屏蔽物理鼠标左键。"""
+        ...
+    def mask_mouse_right(self, enable: bool) -> None:
+        """屏蔽物理鼠标右键。"""
+        ...
+    def mask_mouse_middle(self, enable: bool) -> None:
+        """屏蔽物理鼠标中键。"""
+        ...
+    def mask_mouse_side1(self, enable: bool) -> None:
+        """屏蔽物理鼠标侧键1。"""
+        ...
+    def mask_mouse_side2(self, enable: bool) -> None:
+        """屏蔽物理鼠标侧键2。"""
+        ...
+    def mask_mouse_x(self, enable: bool) -> None:
+        """屏蔽物理鼠标X轴。"""
+        ...
+    def mask_mouse_y(self, enable: bool) -> None:
+        """屏蔽物理鼠标Y轴。"""
+        ...
+    def mask_mouse_wheel(self, enable: bool) -> None:
+        """屏蔽物理鼠标滚轮。"""
+        ...
+    def mask_keyboard(self, vkey: int) -> None:
+        """屏蔽物理键盘的指定按键。"""
+        ...
+    def unmask_keyboard(self, vkey: int) -> None:
+        """解除屏蔽物理键盘的指定按键。"""
+        ...
+    def unmask_all(self) -> None:
+        """解除所有屏蔽。"""
+        ...
+    def release_all(self) -> None:
+        """松开所有按键和鼠标按键，参见 `KmBoxNetClient.release_all`。"""
+        ...
+    def session(self) -> InputSession:
+        """离开 with 块时调用 release_all，参见 `KmBoxNetClient.session`。"""
+        ...
+    def pressed_keys(self) -> List[int]:
+        """按住的普通按键，参见 `KmBoxNetClient.pressed_keys`。"""
+        ...
+    def pressed_modifiers(self) -> List[int]:
+        """按住的修饰键。"""
+        ...
+    def pressed_buttons(self) -> int:
+        """按住的鼠标按键掩码。"""
+        ...
+    def is_pressed(self, vkey: int) -> bool:
+        """指定按键是否按下。"""
+        ...
+    def ping(self) -> float:
+        """测量往返延迟 (毫秒)。会发送一个空鼠标报告，见 KmBoxNetClient.ping。"""
+        ...
+    def serve_metrics(self, addr: str = "0.0.0.0:9464") -> MetricsServer:
+        """
+        在 addr 上提供 Prometheus 指标 (stats().to_prometheus())。
+        需要以 prometheus feature 编译。
+        """
+        ...
+    def start_heartbeat(self, interval_ms: int = 1000) -> Heartbeat:
+        """
+        在后台每隔 interval_ms 毫秒 ping 一次，统计延迟与丢包。
+        开启 auto_reconnect 时，空闲期间断线也会自动重连。
+        """
+        ...
+    def connection_state(self) -> ConnectionState:
+        """返回最近一条指令后的连接状态。"""
+        ...
+    def stats(self) -> ClientStats:
+        """返回流量统计的快照。"""
+        ...
+    def set_capture(self, capture: Optional[Capture]) -> None:
+        """记录之后收发的每个数据包，传入 None 停止记录。"""
+        ...
+    def reconnect(self) -> None:
+        """重新握手并恢复屏蔽和按键状态。"""
+        ...
+    def reboot(self) -> None:
+        """重启盒子。"""
+        ...
+    def set_config(self, ip: str, port: int) -> None:
+        """修改盒子的 IP 与端口。"""
+        ...
+    def set_vid_pid(self, vid: int, pid: int) -> None:
+        """设置盒子 device 端的 VID/PID。"""
+        ...
+    def lcd_color(self, rgb565: int) -> None:
+        """LCD 整屏填充颜色。"""
+        ...
+    def lcd_picture_bottom(self, buff: bytes) -> None:
+        """在 LCD 底部显示 128x80 图片。"""
+        ...
+    def lcd_picture(self, buff: bytes) -> None:
+        """LCD 整屏显示 128x160 图片。"""
+        ...
+    def trace_enable(self, type_: int, value: int) -> None:
+        """开启/关闭硬件轨迹修正算法。"""
+        ...
+
+# 键码常量定义 (由 src/keys.rs 生成，请勿手动修改)
+# Generated from src/keys.rs: run `UPDATE_PYI=1 cargo test --test python_keys` after changing it.
+
+class Key(IntEnum):
+    """HID 键码，每个成员对应一个 KEY_* 常量。"""
+
+    A = 0x04
+    B = 0x05
+    C = 0x06
+    D = 0x07
+    E = 0x08
+    F = 0x09
+    G = 0x0a
+    H = 0x0b
+    I = 0x0c
+    J = 0x0d
+    K = 0x0e
+    L = 0x0f
+    M = 0x10
+    N = 0x11
+    O = 0x12
+    P = 0x13
+    Q = 0x14
+    R = 0x15
+    S = 0x16
+    T = 0x17
+    U = 0x18
+    V = 0x19
+    W = 0x1a
+    X = 0x1b
+    Y = 0x1c
+    Z = 0x1d
+    DIGIT1 = 0x1e
+    DIGIT2 = 0x1f
+    DIGIT3 = 0x20
+    DIGIT4 = 0x21
+    DIGIT5 = 0x22
+    DIGIT6 = 0x23
+    DIGIT7 = 0x24
+    DIGIT8 = 0x25
+    DIGIT9 = 0x26
+    DIGIT0 = 0x27
+    ENTER = 0x28
+    ESCAPE = 0x29
+    BACKSPACE = 0x2a
+    TAB = 0x2b
+    SPACE = 0x2c
+    MINUS = 0x2d
+    EQUAL = 0x2e
+    LEFT_BRACKET = 0x2f
+    RIGHT_BRACKET = 0x30
+    BACKSLASH = 0x31
+    NON_US_HASH = 0x32
+    SEMICOLON = 0x33
+    QUOTE = 0x34
+    GRAVE = 0x35
+    COMMA = 0x36
+    PERIOD = 0x37
+    SLASH = 0x38
+    CAPS_LOCK = 0x39
+    F1 = 0x3a
+    F2 = 0x3b
+    F3 = 0x3c
+    F4 = 0x3d
+    F5 = 0x3e
+    F6 = 0x3f
+    F7 = 0x40
+    F8 = 0x41
+    F9 = 0x42
+    F10 = 0x43
+    F11 = 0x44
+    F12 = 0x45
+    PRINT_SCREEN = 0x46
+    SCROLL_LOCK = 0x47
+    PAUSE = 0x48
+    INSERT = 0x49
+    HOME = 0x4a
+    PAGE_UP = 0x4b
+    DELETE = 0x4c
+    END = 0x4d
+    PAGE_DOWN = 0x4e
+    RIGHT_ARROW = 0x4f
+    LEFT_ARROW = 0x50
+    DOWN_ARROW = 0x51
+    UP_ARROW = 0x52
+    NUM_LOCK = 0x53
+    KEYPAD_SLASH = 0x54
+    KEYPAD_ASTERISK = 0x55
+    KEYPAD_MINUS = 0x56
+    KEYPAD_PLUS = 0x57
+    KEYPAD_ENTER = 0x58
+    KEYPAD1 = 0x59
+    KEYPAD2 = 0x5a
+    KEYPAD3 = 0x5b
+    KEYPAD4 = 0x5c
+    KEYPAD5 = 0x5d
+    KEYPAD6 = 0x5e
+    KEYPAD7 = 0x5f
+    KEYPAD8 = 0x60
+    KEYPAD9 = 0x61
+    KEYPAD0 = 0x62
+    KEYPAD_PERIOD = 0x63
+    NON_US_BACKSLASH = 0x64
+    APPLICATION = 0x65
+    POWER = 0x66
+    KEYPAD_EQUAL = 0x67
+    F13 = 0x68
+    F14 = 0x69
+    F15 = 0x6a
+    F16 = 0x6b
+    F17 = 0x6c
+    F18 = 0x6d
+    F19 = 0x6e
+    F20 = 0x6f
+    F21 = 0x70
+    F22 = 0x71
+    F23 = 0x72
+    F24 = 0x73
+    EXECUTE = 0x74
+    HELP = 0x75
+    MENU = 0x76
+    SELECT = 0x77
+    STOP = 0x78
+    AGAIN = 0x79
+    UNDO = 0x7a
+    CUT = 0x7b
+    COPY = 0x7c
+    PASTE = 0x7d
+    FIND = 0x7e
+    MUTE = 0x7f
+    VOLUME_UP = 0x80
+    VOLUME_DOWN = 0x81
+    LOCKING_CAPS_LOCK = 0x82
+    LOCKING_NUM_LOCK = 0x83
+    LOCKING_SCROLL_LOCK = 0x84
+    KEYPAD_COMMA = 0x85
+    KEYPAD_EQUAL_SIGN = 0x86
+    INTERNATIONAL1 = 0x87
+    INTERNATIONAL2 = 0x88
+    INTERNATIONAL3 = 0x89
+    INTERNATIONAL4 = 0x8a
+    INTERNATIONAL5 = 0x8b
+    INTERNATIONAL6 = 0x8c
+    INTERNATIONAL7 = 0x8d
+    INTERNATIONAL8 = 0x8e
+    INTERNATIONAL9 = 0x8f
+    LANG1 = 0x90
+    LANG2 = 0x91
+    LANG3 = 0x92
+    LANG4 = 0x93
+    LANG5 = 0x94
+    LANG6 = 0x95
+    LANG7 = 0x96
+    LANG8 = 0x97
+    LANG9 = 0x98
+    ALTERNATE_ERASE = 0x99
+    SYS_REQ = 0x9a
+    CANCEL = 0x9b
+    CLEAR = 0x9c
+    PRIOR = 0x9d
+    RETURN = 0x9e
+    SEPARATOR = 0x9f
+    OUT = 0xa0
+    OPER = 0xa1
+    CLEAR_AGAIN = 0xa2
+    CR_SEL = 0xa3
+    EX_SEL = 0xa4
+    KEYPAD00 = 0xb0
+    KEYPAD000 = 0xb1
+    THOUSANDS_SEPARATOR = 0xb2
+    DECIMAL_SEPARATOR = 0xb3
+    CURRENCY_UNIT = 0xb4
+    CURRENCY_SUB_UNIT = 0xb5
+    KEYPAD_LEFT_PAREN = 0xb6
+    KEYPAD_RIGHT_PAREN = 0xb7
+    KEYPAD_LEFT_BRACE = 0xb8
+    KEYPAD_RIGHT_BRACE = 0xb9
+    KEYPAD_TAB = 0xba
+    KEYPAD_BACKSPACE = 0xbb
+    KEYPAD_A = 0xbc
+    KEYPAD_B = 0xbd
+    KEYPAD_C = 0xbe
+    KEYPAD_D = 0xbf
+    KEYPAD_E = 0xc0
+    KEYPAD_F = 0xc1
+    KEYPAD_XOR = 0xc2
+    KEYPAD_CARET = 0xc3
+    KEYPAD_PERCENT = 0xc4
+    KEYPAD_LESS = 0xc5
+    KEYPAD_GREATER = 0xc6
+    KEYPAD_AMPERSAND = 0xc7
+    KEYPAD_LOGICAL_AND = 0xc8
+    KEYPAD_VERTICAL_BAR = 0xc9
+    KEYPAD_LOGICAL_OR = 0xca
+    KEYPAD_COLON = 0xcb
+    KEYPAD_NUMBER_SIGN = 0xcc
+    KEYPAD_SPACE = 0xcd
+    KEYPAD_AT = 0xce
+    KEYPAD_EXCLAMATION_MARK = 0xcf
+    KEYPAD_MEMORY_STORE = 0xd0
+    KEYPAD_MEMORY_RECALL = 0xd1
+    KEYPAD_MEMORY_CLEAR = 0xd2
+    KEYPAD_MEMORY_ADD = 0xd3
+    KEYPAD_MEMORY_SUBTRACT = 0xd4
+    KEYPAD_MEMORY_MULTIPLY = 0xd5
+    KEYPAD_MEMORY_DIVIDE = 0xd6
+    KEYPAD_PLUS_MINUS = 0xd7
+    KEYPAD_CLEAR = 0xd8
+    KEYPAD_CLEAR_ENTRY = 0xd9
+    KEYPAD_BINARY = 0xda
+    KEYPAD_OCTAL = 0xdb
+    KEYPAD_DECIMAL = 0xdc
+    KEYPAD_HEXADECIMAL = 0xdd
+    LEFT_CONTROL = 0xe0
+    LEFT_SHIFT = 0xe1
+    LEFT_ALT = 0xe2
+    LEFT_GUI = 0xe3
+    RIGHT_CONTROL = 0xe4
+    RIGHT_SHIFT = 0xe5
+    RIGHT_ALT = 0xe6
+    RIGHT_GUI = 0xe7
+
 KEY_A: int
 KEY_B: int
 KEY_C: int
@@ -380,21 +1352,33 @@ KEY_W: int
 KEY_X: int
 KEY_Y: int
 KEY_Z: int
-KEY_1: int
-KEY_2: int
-KEY_3: int
-KEY_4: int
-KEY_5: int
-KEY_6: int
-KEY_7: int
-KEY_8: int
-KEY_9: int
-KEY_0: int
+KEY_1_EXCLAMATION_MARK: int
+KEY_2_AT: int
+KEY_3_NUMBER_SIGN: int
+KEY_4_DOLLAR: int
+KEY_5_PERCENT: int
+KEY_6_CARET: int
+KEY_7_AMPERSAND: int
+KEY_8_ASTERISK: int
+KEY_9_OPARENTHESIS: int
+KEY_0_CPARENTHESIS: int
 KEY_ENTER: int
 KEY_ESCAPE: int
 KEY_BACKSPACE: int
 KEY_TAB: int
 KEY_SPACEBAR: int
+KEY_MINUS_UNDERSCORE: int
+KEY_EQUAL_PLUS: int
+KEY_OBRACKET_AND_OBRACE: int
+KEY_CBRACKET_AND_CBRACE: int
+KEY_BACKSLASH_VERTICAL_BAR: int
+KEY_NONUS_NUMBER_SIGN_TILDE: int
+KEY_SEMICOLON_COLON: int
+KEY_SINGLE_AND_DOUBLE_QUOTE: int
+KEY_GRAVE_ACCENT_AND_TILDE: int
+KEY_COMMA_AND_LESS: int
+KEY_DOT_GREATER: int
+KEY_SLASH_QUESTION: int
 KEY_CAPS_LOCK: int
 KEY_F1: int
 KEY_F2: int
@@ -408,6 +1392,147 @@ KEY_F9: int
 KEY_F10: int
 KEY_F11: int
 KEY_F12: int
+KEY_PRINTSCREEN: int
+KEY_SCROLL_LOCK: int
+KEY_PAUSE: int
+KEY_INSERT: int
+KEY_HOME: int
+KEY_PAGEUP: int
+KEY_DELETE: int
+KEY_END1: int
+KEY_PAGEDOWN: int
+KEY_RIGHTARROW: int
+KEY_LEFTARROW: int
+KEY_DOWNARROW: int
+KEY_UPARROW: int
+KEY_KEYPAD_NUM_LOCK_AND_CLEAR: int
+KEY_KEYPAD_SLASH: int
+KEY_KEYPAD_ASTERIKS: int
+KEY_KEYPAD_MINUS: int
+KEY_KEYPAD_PLUS: int
+KEY_KEYPAD_ENTER: int
+KEY_KEYPAD_1_END: int
+KEY_KEYPAD_2_DOWN_ARROW: int
+KEY_KEYPAD_3_PAGEDN: int
+KEY_KEYPAD_4_LEFT_ARROW: int
+KEY_KEYPAD_5: int
+KEY_KEYPAD_6_RIGHT_ARROW: int
+KEY_KEYPAD_7_HOME: int
+KEY_KEYPAD_8_UP_ARROW: int
+KEY_KEYPAD_9_PAGEUP: int
+KEY_KEYPAD_0_INSERT: int
+KEY_KEYPAD_DECIMAL_SEPARATOR_DELETE: int
+KEY_NONUS_BACK_SLASH_VERTICAL_BAR: int
+KEY_APPLICATION: int
+KEY_POWER: int
+KEY_KEYPAD_EQUAL: int
+KEY_F13: int
+KEY_F14: int
+KEY_F15: int
+KEY_F16: int
+KEY_F17: int
+KEY_F18: int
+KEY_F19: int
+KEY_F20: int
+KEY_F21: int
+KEY_F22: int
+KEY_F23: int
+KEY_F24: int
+KEY_EXECUTE: int
+KEY_HELP: int
+KEY_MENU: int
+KEY_SELECT: int
+KEY_STOP: int
+KEY_AGAIN: int
+KEY_UNDO: int
+KEY_CUT: int
+KEY_COPY: int
+KEY_PASTE: int
+KEY_FIND: int
+KEY_MUTE: int
+KEY_VOLUME_UP: int
+KEY_VOLUME_DOWN: int
+KEY_LOCKING_CAPS_LOCK: int
+KEY_LOCKING_NUM_LOCK: int
+KEY_LOCKING_SCROLL_LOCK: int
+KEY_KEYPAD_COMMA: int
+KEY_KEYPAD_EQUAL_SIGN: int
+KEY_INTERNATIONAL1: int
+KEY_INTERNATIONAL2: int
+KEY_INTERNATIONAL3: int
+KEY_INTERNATIONAL4: int
+KEY_INTERNATIONAL5: int
+KEY_INTERNATIONAL6: int
+KEY_INTERNATIONAL7: int
+KEY_INTERNATIONAL8: int
+KEY_INTERNATIONAL9: int
+KEY_LANG1: int
+KEY_LANG2: int
+KEY_LANG3: int
+KEY_LANG4: int
+KEY_LANG5: int
+KEY_LANG6: int
+KEY_LANG7: int
+KEY_LANG8: int
+KEY_LANG9: int
+KEY_ALTERNATE_ERASE: int
+KEY_SYSREQ: int
+KEY_CANCEL: int
+KEY_CLEAR: int
+KEY_PRIOR: int
+KEY_RETURN: int
+KEY_SEPARATOR: int
+KEY_OUT: int
+KEY_OPER: int
+KEY_CLEAR_AGAIN: int
+KEY_CRSEL: int
+KEY_EXSEL: int
+KEY_KEYPAD_00: int
+KEY_KEYPAD_000: int
+KEY_THOUSANDS_SEPARATOR: int
+KEY_DECIMAL_SEPARATOR: int
+KEY_CURRENCY_UNIT: int
+KEY_CURRENCY_SUB_UNIT: int
+KEY_KEYPAD_OPARENTHESIS: int
+KEY_KEYPAD_CPARENTHESIS: int
+KEY_KEYPAD_OBRACE: int
+KEY_KEYPAD_CBRACE: int
+KEY_KEYPAD_TAB: int
+KEY_KEYPAD_BACKSPACE: int
+KEY_KEYPAD_A: int
+KEY_KEYPAD_B: int
+KEY_KEYPAD_C: int
+KEY_KEYPAD_D: int
+KEY_KEYPAD_E: int
+KEY_KEYPAD_F: int
+KEY_KEYPAD_XOR: int
+KEY_KEYPAD_CARET: int
+KEY_KEYPAD_PERCENT: int
+KEY_KEYPAD_LESS: int
+KEY_KEYPAD_GREATER: int
+KEY_KEYPAD_AMPERSAND: int
+KEY_KEYPAD_LOGICAL_AND: int
+KEY_KEYPAD_VERTICAL_BAR: int
+KEY_KEYPAD_LOGIACL_OR: int
+KEY_KEYPAD_COLON: int
+KEY_KEYPAD_NUMBER_SIGN: int
+KEY_KEYPAD_SPACE: int
+KEY_KEYPAD_AT: int
+KEY_KEYPAD_EXCLAMATION_MARK: int
+KEY_KEYPAD_MEMORY_STORE: int
+KEY_KEYPAD_MEMORY_RECALL: int
+KEY_KEYPAD_MEMORY_CLEAR: int
+KEY_KEYPAD_MEMORY_ADD: int
+KEY_KEYPAD_MEMORY_SUBTRACT: int
+KEY_KEYPAD_MEMORY_MULTIPLY: int
+KEY_KEYPAD_MEMORY_DIVIDE: int
+KEY_KEYPAD_PLUSMINUS: int
+KEY_KEYPAD_CLEAR: int
+KEY_KEYPAD_CLEAR_ENTRY: int
+KEY_KEYPAD_BINARY: int
+KEY_KEYPAD_OCTAL: int
+KEY_KEYPAD_DECIMAL: int
+KEY_KEYPAD_HEXADECIMAL: int
 KEY_LEFTCONTROL: int
 KEY_LEFTSHIFT: int
 KEY_LEFTALT: int
@@ -416,3 +1541,13 @@ KEY_RIGHTCONTROL: int
 KEY_RIGHTSHIFT: int
 KEY_RIGHTALT: int
 KEY_RIGHT_GUI: int
+KEY_1: int
+KEY_2: int
+KEY_3: int
+KEY_4: int
+KEY_5: int
+KEY_6: int
+KEY_7: int
+KEY_8: int
+KEY_9: int
+KEY_0: int
@@ -0,0 +1,325 @@
+use kmbox_net::codec::{Decode, Encode, MonitorReport, Packet, HEAD_LEN};
+use kmbox_net::encryption::{key_for_mac, xxtea_decrypt};
+use kmbox_net::protocol::{cmd_name, CmdHead, SoftKeyboard, SoftMouse};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+const USAGE: &str = "Usage: kmbox-dissect [--mac MAC] [--port PORT] FILE
+
+Prints every KmBoxNet datagram in FILE decoded. FILE is a pcap capture
+(Ethernet, raw IP or Linux cooked) or a JSONL trace written by the crate.
+
+Options:
+  --mac MAC     Device MAC, used to decrypt encrypted commands
+  --port PORT   Only show UDP datagrams to or from PORT";
+
+/// Encrypted commands are always exactly one 128-byte block.
+const ENCRYPTED_LEN: usize = 128;
+
+/// One UDP datagram read from the input.
+struct Datagram {
+    ts: f64,
+    src: Option<SocketAddr>,
+    dst: Option<SocketAddr>,
+    /// `"sent"` or `"received"`, as recorded in a JSONL trace.
+    dir: Option<String>,
+    /// Known to be a monitor report.
+    monitor: bool,
+    data: Vec<u8>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut mac = None;
+    let mut port = None;
+    let mut path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mac" => mac = Some(args.next().ok_or(USAGE)?),
+            "--port" => port = Some(args.next().ok_or(USAGE)?.parse::<u16>()?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => return Err(USAGE.into()),
+        }
+    }
+    let path = path.ok_or(USAGE)?;
+    let key = mac.as_deref().map(key_for_mac);
+
+    let input = std::fs::read(&path)?;
+    let datagrams = if input.len() >= 4 && is_pcap_magic(&input[..4]) {
+        read_pcap(&input)?
+    } else {
+        read_jsonl(std::str::from_utf8(&input)?)?
+    };
+
+    let mut dissector = Dissector { key, last: None };
+    for datagram in datagrams {
+        let ports = [datagram.src, datagram.dst].map(|a| a.map(|a| a.port()));
+        if port.is_some() && !ports.contains(&port) {
+            continue;
+        }
+        println!("{}", dissector.describe(&datagram));
+    }
+    Ok(())
+}
+
+struct Dissector {
+    key: Option<[u8; 16]>,
+    /// Destination and plain header of the last command, to tell its ack
+    /// apart from header-only commands such as `CMD_MONITOR`.
+    last: Option<(Option<SocketAddr>, Vec<u8>)>,
+}
+
+impl Dissector {
+    fn describe(&mut self, datagram: &Datagram) -> String {
+        let mut line = format!("{:.6}", datagram.ts);
+        if let (Some(src), Some(dst)) = (datagram.src, datagram.dst) {
+            line += &format!(" {} > {}", src, dst);
+        }
+        if let Some(dir) = &datagram.dir {
+            line += &format!(" {}", dir);
+        }
+        line.push(' ');
+
+        let mut data = datagram.data.clone();
+        if datagram.monitor || data.len() == MonitorReport::SIZE {
+            if let Some(report) = MonitorReport::decode(&data) {
+                let mouse = report.mouse;
+                let keys: Vec<u8> = report
+                    .keyboard
+                    .data
+                    .into_iter()
+                    .filter(|&k| k != 0)
+                    .collect();
+                line += &format!(
+                    "MONITOR buttons=0x{:02x} x={} y={} wheel={} modifiers=0x{:02x} keys={:?}",
+                    mouse.buttons, mouse.x, mouse.y, mouse.wheel, report.keyboard.buttons, keys
+                );
+                return line;
+            }
+        }
+        if let Some(head) = CmdHead::decode(&data).filter(|_| data.len() == HEAD_LEN) {
+            // The box acks a command by echoing its header.
+            let is_ack = match datagram.dir.as_deref() {
+                Some(dir) => dir == "received",
+                None => self.last == Some((datagram.src, head.to_bytes())),
+            };
+            if is_ack {
+                line += &format!(
+                    "ack {} mac=0x{:08x} indexpts={}",
+                    cmd_name(head.cmd),
+                    head.mac,
+                    head.indexpts
+                );
+                return line;
+            }
+        }
+
+        let mut encrypted = false;
+        if data.len() == ENCRYPTED_LEN {
+            match &self.key {
+                Some(key) => {
+                    xxtea_decrypt(&mut data, key);
+                    encrypted = true;
+                }
+                None => {
+                    line += "encrypted command (pass --mac to decrypt)";
+                    return line;
+                }
+            }
+        }
+        let Some(packet) = Packet::parse(&data) else {
+            line += &format!("{} bytes, not a KmBoxNet packet", data.len());
+            return line;
+        };
+
+        let head = packet.head();
+        self.last = Some((datagram.dst, head.to_bytes()));
+        line += &format!(
+            "{:<18} mac=0x{:08x} indexpts={} rand=0x{:08x}",
+            cmd_name(head.cmd),
+            head.mac,
+            head.indexpts,
+            head.rand
+        );
+        match packet {
+            Packet::Connect { .. } | Packet::Reboot { .. } => {}
+            Packet::Mouse { mouse, .. } => line += &describe_mouse(&mouse),
+            Packet::Keyboard { keyboard, .. } => line += &describe_keyboard(&keyboard),
+            Packet::Monitor { port, .. } => match port {
+                Some(port) => line += &format!(" port={}", port),
+                None => line += " off",
+            },
+            Packet::MaskMouse { flags, vkey, .. } | Packet::Unmask { flags, vkey, .. } => {
+                line += &format!(" flags=0x{:02x} vkey={}", flags, vkey)
+            }
+            Packet::SetConfig { ip, port, .. } => line += &format!(" ip={} port={}", ip, port),
+            Packet::SetVidPid { vid, pid, .. } => {
+                line += &format!(" vid=0x{:04x} pid=0x{:04x}", vid, pid)
+            }
+            Packet::ShowPic { row, pixels, .. } => {
+                line += &format!(" row={} pixels={}B", row, pixels.len())
+            }
+            Packet::TraceEnable { type_, value, .. } => {
+                line += &format!(" type={} value={}", type_, value)
+            }
+            Packet::Unknown { payload, .. } => line += &format!(" payload={}B", payload.len()),
+        }
+        if encrypted {
+            line += " (encrypted)";
+        }
+        line
+    }
+}
+
+fn describe_mouse(mouse: &SoftMouse) -> String {
+    let mut out = format!(
+        " buttons=0x{:02x} x={} y={} wheel={}",
+        mouse.button, mouse.x, mouse.y, mouse.wheel
+    );
+    if mouse.point.iter().any(|&p| p != 0) {
+        out += &format!(" point={:?}", mouse.point);
+    }
+    out
+}
+
+fn describe_keyboard(keyboard: &SoftKeyboard) -> String {
+    let keys: Vec<u8> = keyboard
+        .button
+        .iter()
+        .copied()
+        .filter(|&k| k != 0)
+        .collect();
+    format!(" modifiers=0x{:02x} keys={:?}", keyboard.ctrl, keys)
+}
+
+// --- pcap ---
+
+fn is_pcap_magic(magic: &[u8]) -> bool {
+    let magic = u32::from_le_bytes(magic.try_into().unwrap());
+    [0xa1b2_c3d4, 0xa1b2_3c4d].contains(&magic)
+        || [0xa1b2_c3d4, 0xa1b2_3c4d].contains(&magic.swap_bytes())
+}
+
+fn read_pcap(input: &[u8]) -> Result<Vec<Datagram>, Box<dyn std::error::Error>> {
+    if input.len() < 24 {
+        return Err("truncated pcap header".into());
+    }
+    let magic = u32::from_le_bytes(input[..4].try_into()?);
+    let big_endian = !matches!(magic, 0xa1b2_c3d4 | 0xa1b2_3c4d);
+    let nanos = matches!(magic, 0xa1b2_3c4d | 0x4d3c_b2a1);
+    let u32_at = |offset: usize| -> u32 {
+        let bytes: [u8; 4] = input[offset..offset + 4].try_into().unwrap();
+        if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    };
+    let linktype = u32_at(20);
+
+    let mut datagrams = Vec::new();
+    let mut offset = 24;
+    while offset + 16 <= input.len() {
+        let secs = u32_at(offset) as f64;
+        let frac = u32_at(offset + 4) as f64;
+        let caplen = u32_at(offset + 8) as usize;
+        let frame = input
+            .get(offset + 16..offset + 16 + caplen)
+            .ok_or("truncated pcap record")?;
+        offset += 16 + caplen;
+
+        let ip = match linktype {
+            // BSD loopback: 4-byte address family
+            0 => frame.get(4..),
+            // Ethernet, IPv4 only
+            1 => (frame.get(12..14) == Some(&[0x08, 0x00])).then(|| &frame[14..]),
+            101 | 228 => Some(frame),
+            // Linux cooked capture
+            113 => (frame.get(14..16) == Some(&[0x08, 0x00])).then(|| &frame[16..]),
+            _ => return Err(format!("unsupported pcap link type {}", linktype).into()),
+        };
+        if let Some((src, dst, data)) = ip.and_then(parse_ipv4_udp) {
+            datagrams.push(Datagram {
+                ts: secs + frac / if nanos { 1e9 } else { 1e6 },
+                src: Some(src),
+                dst: Some(dst),
+                dir: None,
+                monitor: false,
+                data: data.to_vec(),
+            });
+        }
+    }
+    Ok(datagrams)
+}
+
+/// The addresses and payload of an IPv4 UDP packet.
+fn parse_ipv4_udp(ip: &[u8]) -> Option<(SocketAddr, SocketAddr, &[u8])> {
+    if ip.first()? >> 4 != 4 || *ip.get(9)? != 17 {
+        return None;
+    }
+    let header_len = ((ip[0] & 0x0f) as usize) * 4;
+    let src_ip = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(12..16)?).ok()?);
+    let dst_ip = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(16..20)?).ok()?);
+    let udp = ip.get(header_len..)?;
+    let src_port = u16::from_be_bytes([*udp.first()?, *udp.get(1)?]);
+    let dst_port = u16::from_be_bytes([*udp.get(2)?, *udp.get(3)?]);
+    let udp_len = u16::from_be_bytes([*udp.get(4)?, *udp.get(5)?]) as usize;
+    let data = udp.get(8..udp_len.max(8))?;
+    Some((
+        SocketAddrV4::new(src_ip, src_port).into(),
+        SocketAddrV4::new(dst_ip, dst_port).into(),
+        data,
+    ))
+}
+
+// --- JSONL trace ---
+
+fn read_jsonl(input: &str) -> Result<Vec<Datagram>, Box<dyn std::error::Error>> {
+    let mut datagrams = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let field =
+            |name: &str| json_field(line, name).ok_or(format!("line {}: no {:?}", i + 1, name));
+        let dir = field("dir")?.to_string();
+        let local = field("local")?.parse::<SocketAddr>()?;
+        let remote = field("remote")?.parse::<SocketAddr>()?;
+        let (src, dst) = if dir == "sent" {
+            (local, remote)
+        } else {
+            (remote, local)
+        };
+        datagrams.push(Datagram {
+            ts: field("ts")?.parse()?,
+            src: Some(src),
+            dst: Some(dst),
+            dir: Some(dir),
+            monitor: field("channel")? == "monitor",
+            data: hex_decode(field("data")?).ok_or(format!("line {}: bad data", i + 1))?,
+        });
+    }
+    Ok(datagrams)
+}
+
+/// The value of a top-level string or number field in one of the crate's
+/// JSONL lines, which never contain escaped quotes.
+fn json_field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let start = line.find(&format!("\"{}\":", name))? + name.len() + 3;
+    let rest = &line[start..];
+    match rest.strip_prefix('"') {
+        Some(rest) => rest.split('"').next(),
+        None => rest.split([',', '}']).next(),
+    }
+}
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
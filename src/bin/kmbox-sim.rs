@@ -0,0 +1,53 @@
+use kmbox_net::protocol::cmd_name;
+use kmbox_net::KmBoxNetSimulator;
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: kmbox-sim [--bind ADDR] [--mac MAC]
+
+Runs a simulated KmBoxNet device that acknowledges every command
+and prints what it receives.
+
+Options:
+  --bind ADDR   Local address to listen on (default 0.0.0.0:8888)
+  --mac MAC     Device MAC used for the handshake and encryption (default 0B50E466)";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut bind = String::from("0.0.0.0:8888");
+    let mut mac = String::from("0B50E466");
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => bind = args.next().ok_or(USAGE)?,
+            "--mac" => mac = args.next().ok_or(USAGE)?,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let sim = KmBoxNetSimulator::start(&bind, &mac)?;
+    println!("Simulating KmBoxNet {} on {}", mac, sim.local_addr());
+
+    loop {
+        thread::sleep(Duration::from_millis(50));
+        // Taking the commands keeps the history from growing while we run.
+        for command in sim.take_commands() {
+            println!(
+                "{:<18} indexpts={:<6} rand=0x{:08x} payload={}B{}",
+                cmd_name(command.head.cmd),
+                command.head.indexpts,
+                command.head.rand,
+                command.payload.len(),
+                if command.encrypted {
+                    " (encrypted)"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+}
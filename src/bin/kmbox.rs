@@ -0,0 +1,146 @@
+use kmbox_net::{ClientConfig, Key, KmBoxNetClient, KmBoxNetMonitor, Layout};
+use std::sync::mpsc;
+
+const USAGE: &str =
+    "Usage: kmbox [--ip IP] [--port PORT] [--mac MAC] [--layout LAYOUT] COMMAND [ARGS...]
+
+Runs one command against a KmBoxNet device. The connection defaults to
+the KMBOX_IP, KMBOX_PORT and KMBOX_MAC environment variables, and the
+host keyboard layout (us, uk, de or fr) to KMBOX_LAYOUT or us.
+
+Commands:
+  reboot
+  set_config IP PORT         Change the device address (takes effect after reboot)
+  set_vid_pid VID PID
+  lcd_color RGB565           Fill the LCD, e.g. 0xf800 for red
+  lcd_picture FILE           Show a raw 128x160 RGB565 image (40960 bytes)
+  mouse_move X Y
+  keypress KEY [MS]          KEY is a HID usage code or key name, e.g. 0x04, a or esc
+  hotkey KEYS [MS]           Press KEYS (e.g. ctrl+shift+esc) together for MS (default 50)
+  type_text TEXT [MS]        Type TEXT on the host layout, MS per character (default 10)
+  monitor PORT               Print physical mouse/keyboard reports until interrupted;
+                             0 turns monitoring off
+  trace_enable TYPE VALUE
+  mask_mouse_left|right|middle|side1|side2|x|y|wheel on|off
+  mask_keyboard KEY
+  unmask_keyboard KEY
+  unmask_all
+  release_all                Release every key and mouse button
+
+Dashes may be used instead of underscores, e.g. set-vid-pid.
+Numbers may be decimal or 0x-prefixed hex.";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ip = std::env::var("KMBOX_IP").ok();
+    let mut port = std::env::var("KMBOX_PORT").ok();
+    let mut mac = std::env::var("KMBOX_MAC").ok();
+    let mut layout = std::env::var("KMBOX_LAYOUT").ok();
+
+    let mut args = std::env::args().skip(1);
+    let command = loop {
+        let arg = args.next().ok_or(USAGE)?;
+        match arg.as_str() {
+            "--ip" => ip = Some(args.next().ok_or(USAGE)?),
+            "--port" => port = Some(args.next().ok_or(USAGE)?),
+            "--mac" => mac = Some(args.next().ok_or(USAGE)?),
+            "--layout" => layout = Some(args.next().ok_or(USAGE)?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if arg.starts_with('-') => return Err(USAGE.into()),
+            _ => break arg.replace('-', "_"),
+        }
+    };
+    let args: Vec<String> = args.collect();
+
+    let ip = ip.ok_or("missing --ip or KMBOX_IP")?;
+    let port: u16 = number(&port.ok_or("missing --port or KMBOX_PORT")?)?;
+    let mac = mac.ok_or("missing --mac or KMBOX_MAC")?;
+    let layout = match layout {
+        Some(layout) => layout.parse::<Layout>()?,
+        None => Layout::Us,
+    };
+    let config = ClientConfig::default().layout(layout);
+    let mut client = KmBoxNetClient::with_config(&ip, port, &mac, config)?;
+
+    match (command.as_str(), &args[..]) {
+        ("reboot", []) => client.reboot()?,
+        ("set_config", [ip, port]) => client.set_config(ip, number(port)?)?,
+        ("set_vid_pid", [vid, pid]) => client.set_vid_pid(number(vid)?, number(pid)?)?,
+        ("lcd_color", [rgb565]) => client.lcd_color(number(rgb565)?)?,
+        ("lcd_picture", [path]) => client.lcd_picture(&std::fs::read(path)?)?,
+        ("mouse_move", [x, y]) => client.mouse_move(number(x)?, number(y)?)?,
+        ("keypress", [key]) => client.keypress(parse_key(key)?, 10)?,
+        ("keypress", [key, ms]) => client.keypress(parse_key(key)?, number(ms)?)?,
+        ("hotkey", [keys]) => client.hotkey(keys, 50)?,
+        ("hotkey", [keys, ms]) => client.hotkey(keys, number(ms)?)?,
+        ("type_text", [text]) => client.type_text(text, 10)?,
+        ("type_text", [text, ms]) => client.type_text(text, number(ms)?)?,
+        ("monitor", [port]) => monitor(&mut client, number(port)?)?,
+        ("trace_enable", [type_, value]) => client.trace_enable(number(type_)?, number(value)?)?,
+        ("mask_keyboard", [key]) => client.mask_keyboard(parse_key(key)?)?,
+        ("unmask_keyboard", [key]) => client.unmask_keyboard(parse_key(key)?)?,
+        ("unmask_all", []) => client.unmask_all()?,
+        ("release_all", []) => client.release_all()?,
+        (mask, [state]) if mask.starts_with("mask_mouse_") => {
+            let enable = match state.as_str() {
+                "on" => true,
+                "off" => false,
+                _ => return Err(USAGE.into()),
+            };
+            match mask {
+                "mask_mouse_left" => client.mask_mouse_left(enable)?,
+                "mask_mouse_right" => client.mask_mouse_right(enable)?,
+                "mask_mouse_middle" => client.mask_mouse_middle(enable)?,
+                "mask_mouse_side1" => client.mask_mouse_side1(enable)?,
+                "mask_mouse_side2" => client.mask_mouse_side2(enable)?,
+                "mask_mouse_x" => client.mask_mouse_x(enable)?,
+                "mask_mouse_y" => client.mask_mouse_y(enable)?,
+                "mask_mouse_wheel" => client.mask_mouse_wheel(enable)?,
+                _ => return Err(USAGE.into()),
+            }
+        }
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+fn monitor(client: &mut KmBoxNetClient, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    if port == 0 {
+        client.monitor(0)?;
+        return Ok(());
+    }
+    let (tx, rx) = mpsc::channel();
+    let _monitor = KmBoxNetMonitor::start(port, move |mouse, keyboard| {
+        let _ = tx.send((mouse, keyboard));
+    })?;
+    client.monitor(port)?;
+    for (mouse, keyboard) in rx {
+        let keys: Vec<u8> = keyboard.data.into_iter().filter(|&k| k != 0).collect();
+        println!(
+            "buttons=0x{:02x} x={} y={} wheel={} modifiers=0x{:02x} keys={:?}",
+            mouse.buttons, mouse.x, mouse.y, mouse.wheel, keyboard.buttons, keys
+        );
+    }
+    Ok(())
+}
+
+/// Parse a HID usage code, or a key name such as `esc` or `LeftShift`.
+fn parse_key(arg: &str) -> Result<Key, Box<dyn std::error::Error>> {
+    match number::<u8>(arg) {
+        Ok(code) => Ok(Key::try_from(code)?),
+        Err(_) => Ok(arg.parse()?),
+    }
+}
+
+/// Parse a decimal or `0x`-prefixed hex number.
+fn number<T: TryFrom<i64>>(arg: &str) -> Result<T, Box<dyn std::error::Error>> {
+    let value = match arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => arg.parse().ok(),
+    };
+    value
+        .and_then(|v| T::try_from(v).ok())
+        .ok_or_else(|| format!("invalid number {:?}", arg).into())
+}
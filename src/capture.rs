@@ -0,0 +1,290 @@
+use crate::client_stats::cmd_label;
+use crate::codec::{Decode, MonitorReport};
+use crate::protocol::CmdHead;
+use pyo3::prelude::*;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Which socket a datagram went through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Commands and acks of `KmBoxNetClient`.
+    Command,
+    /// Reports received by `KmBoxNetMonitor`.
+    Monitor,
+}
+
+/// One datagram as seen by the client or the monitor.
+#[derive(Debug, Clone)]
+pub struct CapturedPacket<'a> {
+    pub time: SystemTime,
+    pub direction: Direction,
+    pub channel: Channel,
+    pub local: SocketAddr,
+    pub remote: SocketAddr,
+    /// The plain header. For encrypted commands this is the header before
+    /// encryption, which cannot be read back from `data`.
+    pub head: Option<CmdHead>,
+    pub encrypted: bool,
+    /// The bytes on the wire.
+    pub data: &'a [u8],
+}
+
+/// Destination for captured datagrams.
+pub trait CaptureSink: Send {
+    fn record(&mut self, packet: &CapturedPacket<'_>) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a pcap file that Wireshark and tcpdump can open.
+///
+/// Each datagram gets a synthetic IPv4 and UDP header (`LINKTYPE_RAW`).
+/// IPv6 peers are not supported.
+pub struct PcapSink<W: Write + Send> {
+    out: W,
+}
+
+const LINKTYPE_RAW: u32 = 101;
+
+impl PcapSink<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + Send> PcapSink<W> {
+    /// Write the pcap file header to `out`.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(&0xa1b2_c3d4u32.to_le_bytes())?; // magic, microseconds
+        out.write_all(&2u16.to_le_bytes())?; // version 2.4
+        out.write_all(&4u16.to_le_bytes())?;
+        out.write_all(&0i32.to_le_bytes())?; // GMT offset
+        out.write_all(&0u32.to_le_bytes())?; // timestamp accuracy
+        out.write_all(&65535u32.to_le_bytes())?; // snaplen
+        out.write_all(&LINKTYPE_RAW.to_le_bytes())?;
+        Ok(Self { out })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write + Send> CaptureSink for PcapSink<W> {
+    fn record(&mut self, packet: &CapturedPacket<'_>) -> io::Result<()> {
+        let (src, dst) = match packet.direction {
+            Direction::Sent => (packet.local, packet.remote),
+            Direction::Received => (packet.remote, packet.local),
+        };
+        let (SocketAddr::V4(src), SocketAddr::V4(dst)) = (src, dst) else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "pcap capture supports IPv4 only",
+            ));
+        };
+        let frame = ipv4_udp(*src.ip(), src.port(), *dst.ip(), dst.port(), packet.data);
+
+        let since_epoch = packet.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        self.out
+            .write_all(&(since_epoch.as_secs() as u32).to_le_bytes())?;
+        self.out
+            .write_all(&since_epoch.subsec_micros().to_le_bytes())?;
+        self.out.write_all(&(frame.len() as u32).to_le_bytes())?; // captured
+        self.out.write_all(&(frame.len() as u32).to_le_bytes())?; // original
+        self.out.write_all(&frame)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// IPv4 and UDP headers followed by `payload`. The UDP checksum is left out,
+/// which IPv4 allows.
+fn ipv4_udp(src: Ipv4Addr, sport: u16, dst: Ipv4Addr, dport: u16, payload: &[u8]) -> Vec<u8> {
+    let udp_len = 8 + payload.len() as u16;
+    let total_len = 20 + udp_len;
+    let mut frame = Vec::with_capacity(total_len as usize);
+    frame.extend_from_slice(&[0x45, 0]); // version 4, 20 byte header
+    frame.extend_from_slice(&total_len.to_be_bytes());
+    frame.extend_from_slice(&[0, 0, 0x40, 0]); // id, don't fragment
+    frame.extend_from_slice(&[64, 17, 0, 0]); // TTL, UDP, checksum
+    frame.extend_from_slice(&src.octets());
+    frame.extend_from_slice(&dst.octets());
+    let mut sum: u32 = frame
+        .chunks(2)
+        .map(|w| u16::from_be_bytes([w[0], w[1]]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    let checksum = !(sum as u16);
+    frame[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    frame.extend_from_slice(&sport.to_be_bytes());
+    frame.extend_from_slice(&dport.to_be_bytes());
+    frame.extend_from_slice(&udp_len.to_be_bytes());
+    frame.extend_from_slice(&[0, 0]);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Writes one JSON object per datagram, with the decoded `CmdHead` or
+/// monitor report:
+///
+/// ```text
+/// {"ts":1700000000.123456,"dir":"sent","channel":"command","local":"192.168.2.10:50000",
+///  "remote":"192.168.2.188:8888","len":72,"encrypted":false,"head":{"mac":"0x0b50e466",
+///  "rand":0,"indexpts":1,"cmd":"CMD_MOUSE_MOVE"},"data":"66e4500b..."}
+/// ```
+pub struct JsonlSink<W: Write + Send> {
+    out: W,
+}
+
+impl JsonlSink<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Send> JsonlSink<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write + Send> CaptureSink for JsonlSink<W> {
+    fn record(&mut self, packet: &CapturedPacket<'_>) -> io::Result<()> {
+        let since_epoch = packet.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut line = format!(
+            "{{\"ts\":{}.{:06},\"dir\":\"{}\",\"channel\":\"{}\",\"local\":\"{}\",\"remote\":\"{}\",\"len\":{},\"encrypted\":{}",
+            since_epoch.as_secs(),
+            since_epoch.subsec_micros(),
+            match packet.direction {
+                Direction::Sent => "sent",
+                Direction::Received => "received",
+            },
+            match packet.channel {
+                Channel::Command => "command",
+                Channel::Monitor => "monitor",
+            },
+            packet.local,
+            packet.remote,
+            packet.data.len(),
+            packet.encrypted,
+        );
+        if let Some(head) = packet.head {
+            let _ = write!(
+                line,
+                ",\"head\":{{\"mac\":\"{:#010x}\",\"rand\":{},\"indexpts\":{},\"cmd\":\"{}\"}}",
+                head.mac,
+                head.rand,
+                head.indexpts,
+                cmd_label(head.cmd)
+            );
+        }
+        if packet.channel == Channel::Monitor {
+            if let Some(report) = MonitorReport::decode(packet.data) {
+                let _ = write!(
+                    line,
+                    ",\"report\":{{\"buttons\":{},\"x\":{},\"y\":{},\"wheel\":{},\"modifiers\":{},\"keys\":{:?}}}",
+                    report.mouse.buttons,
+                    report.mouse.x,
+                    report.mouse.y,
+                    report.mouse.wheel,
+                    report.keyboard.buttons,
+                    report.keyboard.data
+                );
+            }
+        }
+        line.push_str(",\"data\":\"");
+        for b in packet.data {
+            let _ = write!(line, "{:02x}", b);
+        }
+        line.push_str("\"}\n");
+        self.out.write_all(line.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Shared handle to a `CaptureSink`.
+///
+/// Clone it to record the client and the monitor into the same file.
+/// Write errors are ignored so that a full disk never fails a command;
+/// `flush` reports them.
+#[pyclass(frozen)]
+#[derive(Clone)]
+pub struct Capture {
+    sink: Arc<Mutex<dyn CaptureSink>>,
+}
+
+impl Capture {
+    pub fn new(sink: impl CaptureSink + 'static) -> Self {
+        Self {
+            sink: Arc::new(Mutex::new(sink)),
+        }
+    }
+
+    /// Capture to a new pcap file at `path`.
+    pub fn pcap(path: impl AsRef<Path>) -> io::Result<Self> {
+        PcapSink::create(path).map(Self::new)
+    }
+
+    /// Capture to a new JSONL file at `path`.
+    pub fn jsonl(path: impl AsRef<Path>) -> io::Result<Self> {
+        JsonlSink::create(path).map(Self::new)
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.sink.lock().unwrap_or_else(|e| e.into_inner()).flush()
+    }
+
+    pub(crate) fn record(&self, packet: &CapturedPacket<'_>) {
+        let _ = self
+            .sink
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(packet);
+    }
+}
+
+#[pymethods]
+impl Capture {
+    #[staticmethod]
+    #[pyo3(name = "pcap")]
+    fn py_pcap(path: &str) -> PyResult<Self> {
+        Ok(Self::pcap(path)?)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "jsonl")]
+    fn py_jsonl(path: &str) -> PyResult<Self> {
+        Ok(Self::jsonl(path)?)
+    }
+
+    #[pyo3(name = "flush")]
+    fn py_flush(&self, py: Python<'_>) -> PyResult<()> {
+        Ok(py.allow_threads(|| self.flush())?)
+    }
+}
@@ -1,213 +1,513 @@
-use crate::encryption::xxtea_encrypt;
+use crate::capture::{Capture, CapturedPacket, Channel, Direction};
+use crate::client_core::{Batch, ClientCore, Command, Packet};
+use crate::client_stats::ClientStats;
+use crate::codec::{Decode, MonitorReport};
+use crate::error::{client_in_use, Error};
+use crate::layout::Layout;
 use crate::protocol::*;
 use pyo3::prelude::*;
-use rand::Rng;
-use std::mem;
-use std::net::{ToSocketAddrs, UdpSocket};
-use std::ptr;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Payload for commands that only send the `CmdHead`.
+pub(crate) const NO_PAYLOAD: &[u8] = &[];
+
+/// Calls `$callback!` with the signature of every `KmBoxNetClient` command
+/// whose arguments are all `Copy`, so the wrapper clients stay in sync.
+/// `set_config`, `lcd_picture_bottom` and `lcd_picture` take borrowed data
+/// and are wrapped by hand.
+macro_rules! with_client_methods {
+    ($callback:ident) => {
+        $callback! {
+            mouse_move(x: i32, y: i32);
+            mouse_left(is_down: bool);
+            mouse_right(is_down: bool);
+            mouse_middle(is_down: bool);
+            mouse_side1(is_down: bool);
+            mouse_side2(is_down: bool);
+            mouse_wheel(wheel: i32);
+            mouse_all(button: i32, x: i32, y: i32, wheel: i32);
+            mouse_move_auto(x: i32, y: i32, ms: u32);
+            mouse_move_beizer(x: i32, y: i32, ms: u32, x1: i32, y1: i32, x2: i32, y2: i32);
+            enc_mouse_move(x: i32, y: i32);
+            enc_mouse_left(is_down: bool);
+            enc_mouse_right(is_down: bool);
+            enc_mouse_middle(is_down: bool);
+            enc_mouse_side1(is_down: bool);
+            enc_mouse_side2(is_down: bool);
+            enc_mouse_wheel(wheel: i32);
+            enc_mouse_all(button: i32, x: i32, y: i32, wheel: i32);
+            enc_mouse_move_auto(x: i32, y: i32, ms: u32);
+            enc_mouse_move_beizer(x: i32, y: i32, ms: u32, x1: i32, y1: i32, x2: i32, y2: i32);
+            keydown(vkey: crate::keys::Key);
+            keyup(vkey: crate::keys::Key);
+            keypress(vkey: crate::keys::Key, ms: u64);
+            enc_keydown(vkey: crate::keys::Key);
+            enc_keyup(vkey: crate::keys::Key);
+            enc_keypress(vkey: crate::keys::Key, ms: u64);
+            monitor(port: u16);
+            mask_mouse_left(enable: bool);
+            mask_mouse_right(enable: bool);
+            mask_mouse_middle(enable: bool);
+            mask_mouse_side1(enable: bool);
+            mask_mouse_side2(enable: bool);
+            mask_mouse_x(enable: bool);
+            mask_mouse_y(enable: bool);
+            mask_mouse_wheel(enable: bool);
+            mask_keyboard(vkey: crate::keys::Key);
+            unmask_keyboard(vkey: crate::keys::Key);
+            unmask_all();
+            release_all();
+            reconnect();
+            reboot();
+            set_vid_pid(vid: u16, pid: u16);
+            lcd_color(rgb565: u16);
+            trace_enable(type_: i32, value: i32);
+        }
+    };
+}
+pub(crate) use with_client_methods;
+
+/// Timeouts, retry and reconnect policy for `KmBoxNetClient`.
+///
+/// A command whose ack does not arrive within `command_timeout` is resent
+/// with the same `indexpts`, up to `retries` times. Before each retry the
+/// client sleeps for `backoff`, doubling after every attempt.
+///
+/// After `disconnect_after` commands in a row fail this way the client is
+/// `Disconnected`. With `auto_reconnect` it then redoes the handshake before
+/// the next command (see `KmBoxNetClient::reconnect`).
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// How long to wait for the handshake ack.
+    pub connect_timeout: Duration,
+    /// How long to wait for each command ack.
+    pub command_timeout: Duration,
+    /// How many times to resend a command after a timeout.
+    pub retries: u32,
+    /// Delay before the first retry.
+    pub backoff: Duration,
+    /// Consecutive failed commands before the link counts as down.
+    pub disconnect_after: u32,
+    /// Reconnect automatically once the link is down.
+    pub auto_reconnect: bool,
+    /// Keyboard layout of the host, used by `type_text`.
+    pub layout: Layout,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_millis(3000),
+            command_timeout: Duration::from_millis(3000),
+            retries: 0,
+            backoff: Duration::ZERO,
+            disconnect_after: 3,
+            auto_reconnect: false,
+            layout: Layout::Us,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = timeout;
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn disconnect_after(mut self, failures: u32) -> Self {
+        self.disconnect_after = failures;
+        self
+    }
+
+    pub fn auto_reconnect(mut self, enable: bool) -> Self {
+        self.auto_reconnect = enable;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+/// Builds a `ClientConfig` from the keyword arguments shared by the Python
+/// client constructors.
+pub(crate) fn py_config(
+    connect_timeout_ms: u64,
+    timeout_ms: u64,
+    retries: u32,
+    backoff_ms: u64,
+    auto_reconnect: bool,
+    disconnect_after: u32,
+    layout: Layout,
+) -> ClientConfig {
+    ClientConfig {
+        connect_timeout: Duration::from_millis(connect_timeout_ms),
+        command_timeout: Duration::from_millis(timeout_ms),
+        retries,
+        backoff: Duration::from_millis(backoff_ms),
+        disconnect_after,
+        auto_reconnect,
+        layout,
+    }
+}
+
+/// Health of the link to the box, updated after every command.
+#[pyclass]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The last command was acknowledged.
+    Connected,
+    /// Recent commands timed out, but fewer than `disconnect_after`.
+    Degraded,
+    /// The handshake failed or `disconnect_after` commands in a row timed out.
+    Disconnected,
+}
 
 #[pyclass]
 pub struct KmBoxNetClient {
     pub(crate) socket: UdpSocket,
-    pub(crate) dest_addr: std::net::SocketAddr,
-    pub(crate) mac: u32,
-    pub(crate) indexpts: u32,
-    pub(crate) key: [u8; 16],
-    pub(crate) soft_mouse: SoftMouse,
-    pub(crate) soft_keyboard: SoftKeyboard,
-    pub(crate) mask_keyboard_mouse_flag: i32,
+    pub(crate) core: ClientCore,
 }
 
 impl KmBoxNetClient {
     /// Initialize the KmBoxNet client.
     /// Connects to the device and performs the handshake.
-    pub fn new(ip: &str, port: u16, mac_str: &str) -> Result<Self, KmError> {
-        let addr_str = format!("{}:{}", ip, port);
-        let dest_addr = addr_str
-            .to_socket_addrs()
-            .map_err(|_| KmError::CreatSocket)?
-            .next()
-            .ok_or(KmError::CreatSocket)?;
-
-        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|_| KmError::CreatSocket)?;
-        socket
-            .set_read_timeout(Some(Duration::from_millis(3000)))
-            .map_err(|_| KmError::CreatSocket)?;
-        socket
-            .set_write_timeout(Some(Duration::from_millis(1000)))
-            .map_err(|_| KmError::CreatSocket)?;
+    pub fn new(ip: &str, port: u16, mac_str: &str) -> Result<Self, Error> {
+        Self::with_config(ip, port, mac_str, ClientConfig::default())
+    }
 
-        let mac = Self::str_to_hex(mac_str);
+    /// Like `new`, with custom timeouts and retry policy.
+    pub fn with_config(
+        ip: &str,
+        port: u16,
+        mac_str: &str,
+        config: ClientConfig,
+    ) -> Result<Self, Error> {
+        let dest_addr = resolve_addr(ip, port)?;
 
-        // Setup encryption key
-        let mut key = [0u8; 16];
-        key[0] = (mac >> 24) as u8;
-        key[1] = (mac >> 16) as u8;
-        key[2] = (mac >> 8) as u8;
-        key[3] = (mac >> 0) as u8;
+        let socket =
+            UdpSocket::bind("0.0.0.0:0").map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        socket
+            .set_write_timeout(Some(Duration::from_millis(1000)))
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        let local_addr = socket
+            .local_addr()
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        let local_addr = routed_addr(local_addr, dest_addr);
 
         let mut client = Self {
             socket,
-            dest_addr,
-            mac,
-            indexpts: 0,
-            key,
-            soft_mouse: SoftMouse::default(),
-            soft_keyboard: SoftKeyboard::default(),
-            mask_keyboard_mouse_flag: 0,
+            core: ClientCore::new(local_addr, dest_addr, mac_str, config),
         };
 
         // Handshake
-        client.connect()?;
+        client.handshake()?;
 
         Ok(client)
     }
 
-    fn str_to_hex(s: &str) -> u32 {
-        let s = s.replace("-", "").replace(":", "");
-        u32::from_str_radix(&s, 16).unwrap_or(0)
+    fn handshake(&mut self) -> Result<(), Error> {
+        let batch = self.core.handshake();
+        self.send_batch(batch)
+    }
+
+    /// Redo the handshake, e.g. after the box rebooted, then restore the
+    /// mouse and keyboard masks, held mouse buttons and held keys on the box.
+    pub fn reconnect(&mut self) -> Result<(), Error> {
+        self.handshake()?;
+        for command in self.core.restore() {
+            self.send(command)?;
+        }
+        Ok(())
     }
 
-    fn connect(&mut self) -> Result<(), KmError> {
-        let mut tx = ClientTx::default();
-        tx.head.mac = self.mac;
-        tx.head.rand = rand::thread_rng().gen();
-        tx.head.indexpts = 0;
-        tx.head.cmd = CMD_CONNECT;
+    /// Measure the round trip to the box.
+    ///
+    /// The protocol has no no-op command, so this sends a real
+    /// `CMD_MOUSE_MOVE` report with zero movement and the currently held
+    /// buttons. The host receives that empty mouse report; held buttons stay
+    /// held. Unlike other commands it is never resent: a lost ack fails with
+    /// `NetRxTimeout` after `command_timeout`.
+    pub fn ping(&mut self) -> Result<Duration, Error> {
+        let command = self.core.ping();
+        if self.core.needs_reconnect(&command) {
+            self.reconnect()?;
+        }
+        let packet = self.core.packet(command);
+        let start = Instant::now();
+        let res = self.attempt(&packet);
+        let rtt = start.elapsed();
+        self.core.finish(&packet.head, &res, start);
+        res.map(|()| rtt)
+    }
 
-        // Reset local state
-        self.soft_mouse = SoftMouse::default();
-        self.soft_keyboard = SoftKeyboard::default();
+    /// The health of the link after the last command.
+    pub fn connection_state(&self) -> ConnectionState {
+        self.core.state
+    }
 
-        let head_size = mem::size_of::<CmdHead>();
-        let data =
-            unsafe { std::slice::from_raw_parts(&tx as *const ClientTx as *const u8, head_size) };
+    /// Snapshot of the traffic counters.
+    pub fn stats(&self) -> ClientStats {
+        self.core.stats.clone()
+    }
 
-        self.socket
-            .send_to(data, self.dest_addr)
-            .map_err(|_| KmError::NetTx)?;
+    /// Record every datagram sent and received to `capture`, or stop
+    /// recording with `None`.
+    pub fn set_capture(&mut self, capture: Option<Capture>) {
+        self.core.capture = capture;
+    }
 
-        // Wait for response (simple check)
-        let mut buf = [0u8; 1024];
-        match self.socket.recv_from(&mut buf) {
-            Ok((_size, _src)) => Ok(()),
-            Err(_) => Err(KmError::NetRxTimeout),
+    /// Send `command` and wait for its ack, reconnecting first if the link
+    /// is down and `auto_reconnect` is on.
+    pub(crate) fn send(&mut self, command: Command) -> Result<(), Error> {
+        if self.core.needs_reconnect(&command) {
+            self.reconnect()?;
         }
+        let packet = self.core.packet(command);
+        let start = Instant::now();
+        let res = self.exchange(&packet);
+        self.core.finish(&packet.head, &res, start);
+        res
     }
 
-    pub(crate) fn send_command<T>(&mut self, cmd: u32, payload: &T) -> Result<(), KmError> {
-        self.send_command_with_rand(cmd, payload, rand::thread_rng().gen())
-    }
-
-    pub(crate) fn send_command_with_rand<T>(
-        &mut self,
-        cmd: u32,
-        payload: &T,
-        rand_val: u32,
-    ) -> Result<(), KmError> {
-        self.indexpts = self.indexpts.wrapping_add(1);
-
-        let mut tx = ClientTx::default();
-        tx.head.mac = self.mac;
-        tx.head.rand = rand_val;
-        tx.head.indexpts = self.indexpts;
-        tx.head.cmd = cmd;
-
-        // Copy payload into data buffer
-        let payload_size = mem::size_of::<T>();
-        unsafe {
-            ptr::copy_nonoverlapping(
-                payload as *const T as *const u8,
-                tx.data.as_mut_ptr(),
-                payload_size,
-            );
+    /// Send the commands of `batch` in order, stopping at the first error.
+    pub(crate) fn send_batch(&mut self, batch: Batch) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = batch.span.entered();
+        for command in batch.commands {
+            self.send(command)?;
         }
+        Ok(())
+    }
 
-        let total_size = mem::size_of::<CmdHead>() + payload_size;
-        let data =
-            unsafe { std::slice::from_raw_parts(&tx as *const ClientTx as *const u8, total_size) };
-
-        self.socket
-            .send_to(data, self.dest_addr)
-            .map_err(|_| KmError::NetTx)?;
+    /// Send `packet` and wait for its ack, resending the same bytes on
+    /// timeout according to the retry policy.
+    fn exchange(&mut self, packet: &Packet) -> Result<(), Error> {
+        let mut attempt = 0;
+        loop {
+            let err = match self.attempt(packet) {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            match self.core.retry_delay(&packet.head, &err, attempt) {
+                Some(delay) => thread::sleep(delay),
+                None => return Err(err),
+            }
+            attempt += 1;
+        }
+    }
 
-        // Wait for ack
-        let mut buf = [0u8; 1024];
+    /// Send `packet` once and wait for its ack.
+    fn attempt(&mut self, packet: &Packet) -> Result<(), Error> {
         self.socket
-            .recv_from(&mut buf)
-            .map_err(|_| KmError::NetRxTimeout)?;
+            .send_to(&packet.data, self.core.dest_addr)
+            .map_err(|e| Error::io(KmError::NetTx, e).with_head(&packet.head))?;
+        self.core.sent(packet);
 
-        Ok(())
+        let res = self.wait_ack(&packet.head, self.core.timeout(&packet.head));
+        self.core.attempted(&res);
+        res
     }
 
-    pub(crate) fn send_command_encrypted<T>(
-        &mut self,
-        cmd: u32,
-        payload: &T,
-    ) -> Result<(), KmError> {
-        self.send_command_encrypted_with_rand(cmd, payload, rand::thread_rng().gen())
-    }
-
-    pub(crate) fn send_command_encrypted_with_rand<T>(
-        &mut self,
-        cmd: u32,
-        payload: &T,
-        rand_val: u32,
-    ) -> Result<(), KmError> {
-        self.indexpts = self.indexpts.wrapping_add(1);
-
-        let mut tx = ClientTx::default();
-        tx.head.mac = self.mac;
-        tx.head.rand = rand_val;
-        tx.head.indexpts = self.indexpts;
-        tx.head.cmd = cmd;
-
-        let payload_size = mem::size_of::<T>();
-        unsafe {
-            ptr::copy_nonoverlapping(
-                payload as *const T as *const u8,
-                tx.data.as_mut_ptr(),
-                payload_size,
-            );
+    /// Wait for the device to acknowledge `sent`. See `ClientCore::received`.
+    fn wait_ack(&self, sent: &CmdHead, timeout: Duration) -> Result<(), Error> {
+        let deadline = Instant::now() + timeout;
+        let mut buf = [0u8; 1024];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::from(KmError::NetRxTimeout).with_head(sent));
+            }
+            self.socket
+                .set_read_timeout(Some(remaining))
+                .map_err(|e| Error::io(KmError::NetRxTimeout, e).with_head(sent))?;
+
+            let (len, src) = self
+                .socket
+                .recv_from(&mut buf)
+                .map_err(|e| Error::io(KmError::NetRxTimeout, e).with_head(sent))?;
+            if let Some(res) = self.core.received(sent, src, &buf[..len]) {
+                return res;
+            }
         }
+    }
+}
 
-        let total_size = mem::size_of::<CmdHead>() + payload_size;
+// Helpers shared by `KmBoxNetClient` and `AsyncKmBoxNetClient`.
+
+pub(crate) fn resolve_addr(ip: &str, port: u16) -> Result<SocketAddr, Error> {
+    format!("{}:{}", ip, port)
+        .to_socket_addrs()
+        .map_err(|e| Error::io(KmError::CreatSocket, e))?
+        .next()
+        .ok_or_else(|| {
+            Error::io(
+                KmError::CreatSocket,
+                std::io::Error::new(std::io::ErrorKind::NotFound, "no address resolved"),
+            )
+        })
+}
 
-        // Prepare buffer for encryption
-        let mut enc_buffer = [0u8; 128]; // Fixed 128 bytes buffer as per C++
-        unsafe {
-            ptr::copy_nonoverlapping(
-                &tx as *const ClientTx as *const u8,
-                enc_buffer.as_mut_ptr(),
-                total_size.min(128),
-            );
-        }
+/// The address packets from a socket bound to `local` leave from on their
+/// way to `remote`, for captures. A socket bound to `0.0.0.0` reports that
+/// address, so ask the OS which interface it routes `remote` through, and
+/// fall back to 127.0.0.1.
+pub(crate) fn routed_addr(local: SocketAddr, remote: SocketAddr) -> SocketAddr {
+    if !local.ip().is_unspecified() {
+        return local;
+    }
+    let ip = UdpSocket::bind((local.ip(), 0))
+        .and_then(|probe| {
+            probe.connect(remote)?;
+            probe.local_addr()
+        })
+        .map(|addr| addr.ip())
+        .ok()
+        .filter(|ip| !ip.is_unspecified())
+        .unwrap_or(Ipv4Addr::LOCALHOST.into());
+    SocketAddr::new(ip, local.port())
+}
 
-        xxtea_encrypt(&mut enc_buffer, &self.key);
+pub(crate) fn str_to_hex(s: &str) -> u32 {
+    let s = s.replace("-", "").replace(":", "");
+    u32::from_str_radix(&s, 16).unwrap_or(0)
+}
 
-        self.socket
-            .send_to(&enc_buffer, self.dest_addr)
-            .map_err(|_| KmError::NetTx)?;
+/// The XXTEA key is the MAC in big-endian order followed by 12 zero bytes.
+pub(crate) fn key_from_mac(mac: u32) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key[..4].copy_from_slice(&mac.to_be_bytes());
+    key
+}
 
-        // Wait for ack
-        let mut buf = [0u8; 1024];
-        self.socket
-            .recv_from(&mut buf)
-            .map_err(|_| KmError::NetRxTimeout)?;
+/// Match a datagram from the device against the command it should acknowledge.
+///
+/// The box echoes the command head. Returns `None` for datagrams to skip:
+/// undecodable ones and acks for other `indexpts` (e.g. a late ack for a
+/// command that already timed out). An ack for this `indexpts` with a
+/// different `cmd` is an error.
+pub(crate) fn check_ack(sent: &CmdHead, reply: &[u8]) -> Option<Result<(), Error>> {
+    let reply = CmdHead::decode(reply).filter(|reply| reply.indexpts == sent.indexpts)?;
+    if reply.cmd != sent.cmd {
+        return Some(Err(Error::from(KmError::NetAckMismatch).with_head(sent)));
+    }
+    Some(Ok(()))
+}
 
-        Ok(())
+// A `KmBoxNetClient` belongs to one Python thread at a time. The bindings
+// release the GIL while waiting for the box, so another thread can call in
+// meanwhile; that call raises `KmBoxError` rather than pyo3's `RuntimeError`.
+// `SharedKmBoxNetClient` queues such calls instead.
+impl KmBoxNetClient {
+    pub(crate) fn py_borrow<'py>(slf: &Bound<'py, Self>) -> PyResult<PyRef<'py, Self>> {
+        slf.try_borrow().map_err(|_| client_in_use())
+    }
+
+    pub(crate) fn py_borrow_mut<'py>(slf: &Bound<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.try_borrow_mut().map_err(|_| client_in_use())
+    }
+
+    /// Run a command from Python with the GIL released.
+    pub(crate) fn py_blocking<T, F>(slf: &Bound<'_, Self>, f: F) -> PyResult<T>
+    where
+        T: Send,
+        F: FnOnce(&mut Self) -> Result<T, Error> + Send,
+    {
+        let mut client = Self::py_borrow_mut(slf)?;
+        let client = &mut *client;
+        slf.py().allow_threads(|| f(client)).map_err(Into::into)
     }
 }
 
 #[pymethods]
 impl KmBoxNetClient {
     #[new]
-    fn py_new(ip: &str, port: u16, mac: &str) -> PyResult<Self> {
-        Self::new(ip, port, mac).map_err(Into::into)
+    #[pyo3(signature = (
+        ip,
+        port,
+        mac,
+        *,
+        connect_timeout_ms = 3000,
+        timeout_ms = 3000,
+        retries = 0,
+        backoff_ms = 0,
+        auto_reconnect = false,
+        disconnect_after = 3,
+        layout = Layout::Us
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        py: Python<'_>,
+        ip: &str,
+        port: u16,
+        mac: &str,
+        connect_timeout_ms: u64,
+        timeout_ms: u64,
+        retries: u32,
+        backoff_ms: u64,
+        auto_reconnect: bool,
+        disconnect_after: u32,
+        layout: Layout,
+    ) -> PyResult<Self> {
+        let config = py_config(
+            connect_timeout_ms,
+            timeout_ms,
+            retries,
+            backoff_ms,
+            auto_reconnect,
+            disconnect_after,
+            layout,
+        );
+        py.allow_threads(|| Self::with_config(ip, port, mac, config))
+            .map_err(Into::into)
+    }
+
+    #[pyo3(name = "connection_state")]
+    fn py_connection_state(slf: &Bound<'_, Self>) -> PyResult<ConnectionState> {
+        Ok(Self::py_borrow(slf)?.connection_state())
+    }
+
+    #[pyo3(name = "stats")]
+    fn py_stats(slf: &Bound<'_, Self>) -> PyResult<ClientStats> {
+        Ok(Self::py_borrow(slf)?.stats())
+    }
+
+    /// Round trip time in milliseconds.
+    #[pyo3(name = "ping")]
+    fn py_ping(slf: &Bound<'_, Self>) -> PyResult<f64> {
+        Self::py_blocking(slf, |client| client.ping()).map(|rtt| rtt.as_secs_f64() * 1000.0)
+    }
+
+    /// Record traffic to `capture`, or stop with `None`.
+    #[pyo3(name = "set_capture")]
+    fn py_set_capture(slf: &Bound<'_, Self>, capture: Option<Capture>) -> PyResult<()> {
+        Self::py_borrow_mut(slf)?.set_capture(capture);
+        Ok(())
+    }
+
+    #[pyo3(name = "reconnect")]
+    fn py_reconnect(slf: &Bound<'_, Self>) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.reconnect())
     }
 }
 
@@ -226,43 +526,67 @@ impl KmBoxNetMonitor {
     ///
     /// * `port` - The UDP port to bind locally to receive monitor packets.
     /// * `callback` - A closure that processes the received `HardMouse` and `HardKeyboard` data.
-    pub fn start<F>(port: u16, mut callback: F) -> Result<Self, KmError>
+    pub fn start<F>(port: u16, callback: F) -> Result<Self, Error>
     where
         F: FnMut(HardMouse, HardKeyboard) + Send + 'static,
     {
-        let socket =
-            UdpSocket::bind(format!("0.0.0.0:{}", port)).map_err(|_| KmError::CreatSocket)?;
+        Self::start_with_capture(port, None, callback)
+    }
+
+    /// Like `start`, also recording every received datagram to `capture`.
+    pub fn start_with_capture<F>(
+        port: u16,
+        capture: Option<Capture>,
+        mut callback: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnMut(HardMouse, HardKeyboard) + Send + 'static,
+    {
+        let socket = UdpSocket::bind(format!("0.0.0.0:{}", port))
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
         socket
             .set_read_timeout(Some(Duration::from_millis(100)))
-            .map_err(|_| KmError::CreatSocket)?;
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
 
         let running = Arc::new(AtomicBool::new(true));
         let running_clone = running.clone();
 
         let handle = thread::spawn(move || {
             let mut buf = [0u8; 1024];
+            // The routed local address for the last sender, see `routed_addr`.
+            let mut route: Option<(SocketAddr, SocketAddr)> = None;
             while running_clone.load(Ordering::Relaxed) {
                 match socket.recv_from(&mut buf) {
-                    Ok((len, _)) => {
-                        if len >= 20 {
-                            // Parse Mouse (8 bytes)
-                            // struct { u8 report_id, u8 buttons, i16 x, i16 y, i16 wheel }
-                            let mut mouse = HardMouse::default();
-                            mouse.buttons = buf[1];
-                            mouse.x = i16::from_le_bytes([buf[2], buf[3]]);
-                            mouse.y = i16::from_le_bytes([buf[4], buf[5]]);
-                            mouse.wheel = i16::from_le_bytes([buf[6], buf[7]]);
-
-                            // Parse Keyboard (12 bytes)
-                            // struct { u8 report_id, u8 buttons, u8 data[10] }
-                            let mut keyboard = HardKeyboard::default();
-                            let kb_offset = 8;
-                            keyboard.buttons = buf[kb_offset + 1];
-                            keyboard
-                                .data
-                                .extend_from_slice(&buf[kb_offset + 2..kb_offset + 12]);
-
-                            callback(mouse, keyboard);
+                    Ok((len, src)) => {
+                        if let (Some(capture), Ok(local)) = (&capture, socket.local_addr()) {
+                            let local = match route {
+                                Some((remote, routed)) if remote == src => routed,
+                                _ => {
+                                    let routed = routed_addr(local, src);
+                                    route = Some((src, routed));
+                                    routed
+                                }
+                            };
+                            capture.record(&CapturedPacket {
+                                time: SystemTime::now(),
+                                direction: Direction::Received,
+                                channel: Channel::Monitor,
+                                local,
+                                remote: src,
+                                head: None,
+                                encrypted: false,
+                                data: &buf[..len],
+                            });
+                        }
+                        // Mouse (8 bytes) followed by keyboard (12 bytes)
+                        if let Some(report) = MonitorReport::decode(&buf[..len]) {
+                            #[cfg(feature = "tracing")]
+                            tracing::trace!(
+                                mouse = ?report.mouse,
+                                keyboard = ?report.keyboard,
+                                "monitor report"
+                            );
+                            callback(report.mouse, report.keyboard);
                         }
                     }
                     Err(_) => {
@@ -279,7 +603,8 @@ impl KmBoxNetMonitor {
         })
     }
 
-    /// Stop the monitor thread.
+    /// Stop the monitor thread and wait for it to exit. Dropping the monitor
+    /// also stops the thread, but does not wait.
     pub fn stop(&mut self) {
         if self.running.load(Ordering::Relaxed) {
             self.running.store(false, Ordering::Relaxed);
@@ -293,26 +618,35 @@ impl KmBoxNetMonitor {
 #[pymethods]
 impl KmBoxNetMonitor {
     #[new]
-    fn py_start(port: u16, callback: PyObject) -> PyResult<Self> {
+    #[pyo3(signature = (port, callback, *, capture = None))]
+    fn py_start(port: u16, callback: PyObject, capture: Option<Capture>) -> PyResult<Self> {
         let callback = Arc::new(callback);
-        Self::start(port, move |mouse: HardMouse, keyboard: HardKeyboard| {
-            Python::with_gil(|py| {
-                let args = (mouse, keyboard);
-                if let Err(e) = callback.bind(py).call1(args) {
-                    e.print(py);
-                }
-            });
-        })
+        Self::start_with_capture(
+            port,
+            capture,
+            move |mouse: HardMouse, keyboard: HardKeyboard| {
+                Python::with_gil(|py| {
+                    let args = (mouse, keyboard);
+                    if let Err(e) = callback.bind(py).call1(args) {
+                        e.print(py);
+                    }
+                });
+            },
+        )
         .map_err(Into::into)
     }
 
-    fn shutdown(&mut self) {
-        self.stop();
+    fn shutdown(&mut self, py: Python<'_>) {
+        // The monitor thread may be waiting for the GIL to run the callback.
+        py.allow_threads(|| self.stop());
     }
 }
 
 impl Drop for KmBoxNetMonitor {
     fn drop(&mut self) {
-        self.stop();
+        // Python may drop the monitor while holding the GIL that the callback
+        // is waiting for, so joining here could deadlock. The detached thread
+        // sees the flag within its 100 ms read timeout and exits.
+        self.running.store(false, Ordering::Relaxed);
     }
 }
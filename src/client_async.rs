@@ -0,0 +1,530 @@
+use crate::capture::Capture;
+use crate::client::{resolve_addr, routed_addr, ClientConfig, ConnectionState};
+use crate::client_core::{Batch, ClientCore, Command, Packet};
+use crate::client_keyboard::{chord_steps, hotkey_keys, keypress_steps, KeyStep};
+use crate::client_stats::ClientStats;
+use crate::error::Error;
+use crate::keys::{Key, Modifier};
+use crate::protocol::*;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::time;
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+
+/// Async counterpart of `KmBoxNetClient`, built on `tokio::net::UdpSocket`.
+///
+/// Offers the same mouse, keyboard, monitor and system methods as async
+/// fns. Packets, stats, capture, tracing, connection state and reconnects
+/// all come from the same `ClientCore` as the blocking client; only the
+/// socket differs. Requires the `tokio` feature.
+pub struct AsyncKmBoxNetClient {
+    socket: UdpSocket,
+    core: ClientCore,
+}
+
+impl AsyncKmBoxNetClient {
+    /// Connect to the device and perform the handshake.
+    pub async fn new(ip: &str, port: u16, mac_str: &str) -> Result<Self, Error> {
+        Self::with_config(ip, port, mac_str, ClientConfig::default()).await
+    }
+
+    /// Like `new`, with custom timeouts and retry policy.
+    pub async fn with_config(
+        ip: &str,
+        port: u16,
+        mac_str: &str,
+        config: ClientConfig,
+    ) -> Result<Self, Error> {
+        let dest_addr = resolve_addr(ip, port)?;
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .await
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        let local_addr = socket
+            .local_addr()
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        let local_addr = routed_addr(local_addr, dest_addr);
+
+        let mut client = Self {
+            socket,
+            core: ClientCore::new(local_addr, dest_addr, mac_str, config),
+        };
+        client.handshake().await?;
+        Ok(client)
+    }
+
+    async fn handshake(&mut self) -> Result<(), Error> {
+        let batch = self.core.handshake();
+        self.send_batch(batch).await
+    }
+
+    /// Redo the handshake and restore masks, held buttons and held keys.
+    /// See `KmBoxNetClient::reconnect`.
+    pub async fn reconnect(&mut self) -> Result<(), Error> {
+        self.handshake().await?;
+        for command in self.core.restore() {
+            self.send(command).await?;
+        }
+        Ok(())
+    }
+
+    /// Measure the round trip to the box. See `KmBoxNetClient::ping`.
+    pub async fn ping(&mut self) -> Result<Duration, Error> {
+        let command = self.core.ping();
+        if self.core.needs_reconnect(&command) {
+            self.reconnect().await?;
+        }
+        let packet = self.core.packet(command);
+        let start = Instant::now();
+        let res = self.attempt(&packet).await;
+        let rtt = start.elapsed();
+        self.core.finish(&packet.head, &res, start);
+        res.map(|()| rtt)
+    }
+
+    /// The health of the link after the last command.
+    pub fn connection_state(&self) -> ConnectionState {
+        self.core.state
+    }
+
+    /// Snapshot of the traffic counters.
+    pub fn stats(&self) -> ClientStats {
+        self.core.stats.clone()
+    }
+
+    /// Record every datagram sent and received to `capture`, or stop
+    /// recording with `None`.
+    pub fn set_capture(&mut self, capture: Option<Capture>) {
+        self.core.capture = capture;
+    }
+
+    /// Send `command` and wait for its ack, reconnecting first if the link
+    /// is down and `auto_reconnect` is on.
+    async fn send(&mut self, command: Command) -> Result<(), Error> {
+        if self.core.needs_reconnect(&command) {
+            // `reconnect` sends commands itself, so the future is boxed.
+            Box::pin(self.reconnect()).await?;
+        }
+        let packet = self.core.packet(command);
+        let start = Instant::now();
+        let res = self.exchange(&packet).await;
+        self.core.finish(&packet.head, &res, start);
+        res
+    }
+
+    /// Send the commands of `batch` in order, stopping at the first error.
+    async fn send_batch(&mut self, batch: Batch) -> Result<(), Error> {
+        let commands = batch.commands;
+        let send_all = async {
+            for command in commands {
+                self.send(command).await?;
+            }
+            Ok(())
+        };
+        #[cfg(feature = "tracing")]
+        let send_all = send_all.instrument(batch.span);
+        send_all.await
+    }
+
+    /// Send `packet` and wait for its ack, resending the same bytes on
+    /// timeout according to the retry policy.
+    async fn exchange(&mut self, packet: &Packet) -> Result<(), Error> {
+        let mut attempt = 0;
+        loop {
+            let err = match self.attempt(packet).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            match self.core.retry_delay(&packet.head, &err, attempt) {
+                Some(delay) => time::sleep(delay).await,
+                None => return Err(err),
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Send `packet` once and wait for its ack.
+    async fn attempt(&mut self, packet: &Packet) -> Result<(), Error> {
+        self.socket
+            .send_to(&packet.data, self.core.dest_addr)
+            .await
+            .map_err(|e| Error::io(KmError::NetTx, e).with_head(&packet.head))?;
+        self.core.sent(packet);
+
+        let timeout = self.core.timeout(&packet.head);
+        let res = match time::timeout(timeout, self.wait_ack(&packet.head)).await {
+            Ok(res) => res,
+            Err(_) => Err(Error::from(KmError::NetRxTimeout).with_head(&packet.head)),
+        };
+        self.core.attempted(&res);
+        res
+    }
+
+    /// Wait for the device to acknowledge `sent`. See `ClientCore::received`.
+    async fn wait_ack(&self, sent: &CmdHead) -> Result<(), Error> {
+        let mut buf = [0u8; 1024];
+        loop {
+            let (len, src) = self
+                .socket
+                .recv_from(&mut buf)
+                .await
+                .map_err(|e| Error::io(KmError::NetRxTimeout, e).with_head(sent))?;
+            if let Some(res) = self.core.received(sent, src, &buf[..len]) {
+                return res;
+            }
+        }
+    }
+
+    // --- Mouse Functions ---
+
+    /// Move the mouse relative to current position.
+    pub async fn mouse_move(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        let command = self.core.mouse_move(x, y);
+        self.send(command).await
+    }
+
+    /// Set mouse left button state.
+    pub async fn mouse_left(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_left(is_down);
+        self.send(command).await
+    }
+
+    /// Set mouse right button state.
+    pub async fn mouse_right(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_right(is_down);
+        self.send(command).await
+    }
+
+    /// Set mouse middle button state.
+    pub async fn mouse_middle(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_middle(is_down);
+        self.send(command).await
+    }
+
+    /// Set mouse side button 1 (Back) state.
+    pub async fn mouse_side1(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_side1(is_down);
+        self.send(command).await
+    }
+
+    /// Set mouse side button 2 (Forward) state.
+    pub async fn mouse_side2(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_side2(is_down);
+        self.send(command).await
+    }
+
+    /// Scroll mouse wheel.
+    pub async fn mouse_wheel(&mut self, wheel: i32) -> Result<(), Error> {
+        let command = self.core.mouse_wheel(wheel);
+        self.send(command).await
+    }
+
+    /// Control all mouse parameters at once.
+    pub async fn mouse_all(
+        &mut self,
+        button: i32,
+        x: i32,
+        y: i32,
+        wheel: i32,
+    ) -> Result<(), Error> {
+        let command = self.core.mouse_all(button, x, y, wheel);
+        self.send(command).await
+    }
+
+    /// Move mouse with automatic duration control (firmware side).
+    pub async fn mouse_move_auto(&mut self, x: i32, y: i32, ms: u32) -> Result<(), Error> {
+        let command = self.core.mouse_move_auto(x, y, ms);
+        self.send(command).await
+    }
+
+    /// Move mouse using Bezier curve (firmware side).
+    #[allow(clippy::too_many_arguments)]
+    pub async fn mouse_move_beizer(
+        &mut self,
+        x: i32,
+        y: i32,
+        ms: u32,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+    ) -> Result<(), Error> {
+        let command = self.core.mouse_move_beizer(x, y, ms, x1, y1, x2, y2);
+        self.send(command).await
+    }
+
+    // --- Encrypted Mouse Functions ---
+
+    pub async fn enc_mouse_move(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        let command = self.core.mouse_move(x, y).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_mouse_left(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_left(is_down).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_mouse_right(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_right(is_down).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_mouse_middle(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_middle(is_down).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_mouse_side1(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_side1(is_down).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_mouse_side2(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_side2(is_down).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_mouse_wheel(&mut self, wheel: i32) -> Result<(), Error> {
+        let command = self.core.mouse_wheel(wheel).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_mouse_all(
+        &mut self,
+        button: i32,
+        x: i32,
+        y: i32,
+        wheel: i32,
+    ) -> Result<(), Error> {
+        let command = self.core.mouse_all(button, x, y, wheel).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_mouse_move_auto(&mut self, x: i32, y: i32, ms: u32) -> Result<(), Error> {
+        let command = self.core.mouse_move_auto(x, y, ms).encrypted();
+        self.send(command).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn enc_mouse_move_beizer(
+        &mut self,
+        x: i32,
+        y: i32,
+        ms: u32,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+    ) -> Result<(), Error> {
+        let command = self
+            .core
+            .mouse_move_beizer(x, y, ms, x1, y1, x2, y2)
+            .encrypted();
+        self.send(command).await
+    }
+
+    // --- Keyboard Functions ---
+
+    /// Press a key (keydown).
+    pub async fn keydown(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.press(&[vkey]);
+        self.send(command).await
+    }
+
+    /// Release a key (keyup).
+    pub async fn keyup(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.release(&[vkey]);
+        self.send(command).await
+    }
+
+    /// Press and release a key with a delay.
+    pub async fn keypress(&mut self, vkey: Key, ms: u64) -> Result<(), Error> {
+        self.run_keys(keypress_steps(vkey, ms), false).await
+    }
+
+    /// Press `vkeys` in one report, hold, release in one report. See
+    /// `KmBoxNetClient::chord`.
+    pub async fn chord(&mut self, vkeys: &[Key], ms: u64) -> Result<(), Error> {
+        self.run_keys(chord_steps(vkeys, ms), false).await
+    }
+
+    /// Send a `"ctrl+shift+esc"` style chord. See `KmBoxNetClient::hotkey`.
+    pub async fn hotkey(&mut self, hotkey: &str, ms: u64) -> Result<(), Error> {
+        let steps = chord_steps(&hotkey_keys(hotkey)?, ms);
+        self.run_keys(steps, false).await
+    }
+
+    /// Type `text` on the host layout. See `KmBoxNetClient::type_text`.
+    pub async fn type_text(&mut self, text: &str, per_key_ms: u64) -> Result<(), Error> {
+        let steps = self.core.type_text_steps(text, per_key_ms)?;
+        self.run_keys(steps, false).await
+    }
+
+    /// Send the reports of `steps`, sleeping in between as they say.
+    async fn run_keys(&mut self, steps: Vec<KeyStep>, encrypted: bool) -> Result<(), Error> {
+        for step in steps {
+            match step {
+                KeyStep::Report(change) => {
+                    let command = self.core.key_report(change, encrypted);
+                    self.send(command).await?;
+                }
+                KeyStep::Wait(delay) => time::sleep(delay).await,
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn enc_keydown(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.press(&[vkey]).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_keyup(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.release(&[vkey]).encrypted();
+        self.send(command).await
+    }
+
+    pub async fn enc_keypress(&mut self, vkey: Key, ms: u64) -> Result<(), Error> {
+        self.run_keys(keypress_steps(vkey, ms), true).await
+    }
+
+    // --- Monitor Functions ---
+
+    /// Enable/Disable monitoring of physical keyboard and mouse on the device.
+    /// port: UDP port to listen on. If 0, monitoring is disabled on the device.
+    pub async fn monitor(&mut self, port: u16) -> Result<(), Error> {
+        let command = self.core.monitor(port);
+        self.send(command).await
+    }
+
+    // --- Masking Functions ---
+
+    pub async fn mask_mouse_left(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x01, enable);
+        self.send(command).await
+    }
+
+    pub async fn mask_mouse_right(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x02, enable);
+        self.send(command).await
+    }
+
+    pub async fn mask_mouse_middle(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x04, enable);
+        self.send(command).await
+    }
+
+    pub async fn mask_mouse_side1(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x08, enable);
+        self.send(command).await
+    }
+
+    pub async fn mask_mouse_side2(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x10, enable);
+        self.send(command).await
+    }
+
+    pub async fn mask_mouse_x(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x20, enable);
+        self.send(command).await
+    }
+
+    pub async fn mask_mouse_y(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x40, enable);
+        self.send(command).await
+    }
+
+    pub async fn mask_mouse_wheel(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x80, enable);
+        self.send(command).await
+    }
+
+    pub async fn mask_keyboard(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.mask_keyboard(vkey);
+        self.send(command).await
+    }
+
+    pub async fn unmask_keyboard(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.unmask_keyboard(vkey);
+        self.send(command).await
+    }
+
+    pub async fn unmask_all(&mut self) -> Result<(), Error> {
+        let command = self.core.unmask_all();
+        self.send(command).await
+    }
+
+    /// Keys held, in press order. See `KmBoxNetClient::pressed_keys`.
+    pub fn pressed_keys(&self) -> Vec<Key> {
+        self.core.soft_keyboard.pressed_keys()
+    }
+
+    /// Modifiers held. See `KmBoxNetClient::pressed_modifiers`.
+    pub fn pressed_modifiers(&self) -> Vec<Modifier> {
+        self.core.soft_keyboard.pressed_modifiers()
+    }
+
+    /// Mouse buttons held. See `KmBoxNetClient::pressed_buttons`.
+    pub fn pressed_buttons(&self) -> i32 {
+        self.core.soft_mouse.button
+    }
+
+    /// Whether `vkey` is held. See `KmBoxNetClient::is_pressed`.
+    pub fn is_pressed(&self, vkey: Key) -> bool {
+        self.core.soft_keyboard.is_pressed(vkey)
+    }
+
+    /// Release every key and mouse button. See `KmBoxNetClient::release_all`.
+    pub async fn release_all(&mut self) -> Result<(), Error> {
+        let [keys, buttons] = self.core.release_all();
+        let keys = self.send(keys).await;
+        let buttons = self.send(buttons).await;
+        keys.and(buttons)
+    }
+
+    // --- System Configuration Functions ---
+
+    /// Reboot the device.
+    pub async fn reboot(&mut self) -> Result<(), Error> {
+        let command = self.core.reboot();
+        self.send(command).await
+    }
+
+    /// Set IP configuration (IP and port).
+    pub async fn set_config(&mut self, ip_str: &str, port: u16) -> Result<(), Error> {
+        let command = self.core.set_config(ip_str, port)?;
+        self.send(command).await
+    }
+
+    /// Set device VID and PID. Requires reboot to take effect.
+    pub async fn set_vid_pid(&mut self, vid: u16, pid: u16) -> Result<(), Error> {
+        let command = self.core.set_vid_pid(vid, pid);
+        self.send(command).await
+    }
+
+    /// Fill the LCD screen with a specific color (RGB565).
+    pub async fn lcd_color(&mut self, rgb565: u16) -> Result<(), Error> {
+        let batch = self.core.lcd_color(rgb565);
+        self.send_batch(batch).await
+    }
+
+    /// Display a 128x80 image at the bottom of the LCD.
+    pub async fn lcd_picture_bottom(&mut self, buff: &[u8]) -> Result<(), Error> {
+        let batch = self.core.lcd_picture_bottom(buff)?;
+        self.send_batch(batch).await
+    }
+
+    /// Display a 128x160 image on the LCD.
+    pub async fn lcd_picture(&mut self, buff: &[u8]) -> Result<(), Error> {
+        let batch = self.core.lcd_picture(buff)?;
+        self.send_batch(batch).await
+    }
+
+    /// Enable hardware curve correction.
+    /// type_: 0: Bezier, 1: Missile tracking, 2: Bezier real-time, 3: RM-RT
+    pub async fn trace_enable(&mut self, type_: i32, value: i32) -> Result<(), Error> {
+        let command = self.core.trace_enable(type_, value);
+        self.send(command).await
+    }
+}
@@ -0,0 +1,271 @@
+use crate::capture::Capture;
+use crate::client::{py_config, with_client_methods};
+use crate::error::Error;
+use crate::keys::Key;
+use crate::layout::Layout;
+use crate::KmBoxNetClient;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use std::sync::mpsc;
+use std::thread;
+
+type Job = Box<dyn FnOnce(&mut KmBoxNetClient) + Send>;
+
+/// asyncio front end for `KmBoxNetClient`.
+///
+/// The blocking client lives on a worker thread that runs commands in the
+/// order they were awaited. Each method returns an `asyncio.Future` that the
+/// worker resolves through `loop.call_soon_threadsafe`, so the event loop is
+/// never blocked by network I/O or `keypress` delays.
+#[pyclass(name = "AsyncKmBoxNetClient")]
+pub struct AsyncioKmBoxNetClient {
+    jobs: Option<mpsc::Sender<Job>>,
+}
+
+/// The future of one call and the loop it belongs to.
+struct Waiter {
+    event_loop: PyObject,
+    future: PyObject,
+}
+
+impl Waiter {
+    fn new(py: Python<'_>) -> PyResult<Self> {
+        let event_loop = py
+            .import_bound("asyncio")?
+            .call_method0("get_running_loop")?;
+        let future = event_loop.call_method0("create_future")?;
+        Ok(Self {
+            event_loop: event_loop.unbind(),
+            future: future.unbind(),
+        })
+    }
+
+    fn future<'py>(&self, py: Python<'py>) -> Bound<'py, PyAny> {
+        self.future.bind(py).clone()
+    }
+
+    /// Hand the outcome to the event loop thread.
+    fn resolve<T: IntoPy<PyObject>>(self, res: Result<T, Error>) {
+        Python::with_gil(|py| {
+            let (value, exc) = match res {
+                Ok(value) => (value.into_py(py), None),
+                Err(e) => (py.None(), Some(PyErr::from(e).into_value(py))),
+            };
+            let callback = match wrap_pyfunction_bound!(set_future_result, py) {
+                Ok(callback) => callback,
+                Err(e) => return e.print(py),
+            };
+            // Fails only if the loop was closed, and then nobody is waiting.
+            let _ = self.event_loop.call_method1(
+                py,
+                "call_soon_threadsafe",
+                (callback, self.future, value, exc),
+            );
+        });
+    }
+}
+
+/// Runs on the event loop thread. The caller may have cancelled the future.
+#[pyfunction]
+fn set_future_result(
+    future: &Bound<'_, PyAny>,
+    value: PyObject,
+    exc: Option<PyObject>,
+) -> PyResult<()> {
+    if future.call_method0("done")?.is_truthy()? {
+        return Ok(());
+    }
+    match exc {
+        Some(exc) => future.call_method1("set_exception", (exc,))?,
+        None => future.call_method1("set_result", (value,))?,
+    };
+    Ok(())
+}
+
+impl AsyncioKmBoxNetClient {
+    /// Queue `f` on the worker thread and return a future for its result.
+    fn submit<'py, T, F>(&self, py: Python<'py>, f: F) -> PyResult<Bound<'py, PyAny>>
+    where
+        T: IntoPy<PyObject>,
+        F: FnOnce(&mut KmBoxNetClient) -> Result<T, Error> + Send + 'static,
+    {
+        let jobs = self
+            .jobs
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("client is closed"))?;
+        let waiter = Waiter::new(py)?;
+        let future = waiter.future(py);
+        jobs.send(Box::new(move |client| waiter.resolve(f(client))))
+            .map_err(|_| PyRuntimeError::new_err("client is closed"))?;
+        Ok(future)
+    }
+}
+
+/// Generates awaitable wrappers that forward their arguments unchanged.
+macro_rules! asyncio_methods {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        #[pymethods]
+        impl AsyncioKmBoxNetClient {
+            $(
+                #[allow(clippy::too_many_arguments)]
+                fn $name<'py>(&self, py: Python<'py>, $($arg: $ty),*) -> PyResult<Bound<'py, PyAny>> {
+                    self.submit(py, move |client| client.$name($($arg),*))
+                }
+            )*
+        }
+    };
+}
+
+with_client_methods!(asyncio_methods);
+
+#[pymethods]
+impl AsyncioKmBoxNetClient {
+    /// Connect in the background. Awaiting the result gives the client.
+    #[staticmethod]
+    #[pyo3(signature = (
+        ip,
+        port,
+        mac,
+        *,
+        connect_timeout_ms = 3000,
+        timeout_ms = 3000,
+        retries = 0,
+        backoff_ms = 0,
+        auto_reconnect = false,
+        disconnect_after = 3,
+        layout = Layout::Us
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn connect<'py>(
+        py: Python<'py>,
+        ip: String,
+        port: u16,
+        mac: String,
+        connect_timeout_ms: u64,
+        timeout_ms: u64,
+        retries: u32,
+        backoff_ms: u64,
+        auto_reconnect: bool,
+        disconnect_after: u32,
+        layout: Layout,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let config = py_config(
+            connect_timeout_ms,
+            timeout_ms,
+            retries,
+            backoff_ms,
+            auto_reconnect,
+            disconnect_after,
+            layout,
+        );
+        let (tx, rx) = mpsc::channel::<Job>();
+        let this = Py::new(py, Self { jobs: Some(tx) })?;
+        let waiter = Waiter::new(py)?;
+        let future = waiter.future(py);
+
+        thread::Builder::new()
+            .name("kmbox-asyncio".into())
+            .spawn(move || {
+                let mut client = match KmBoxNetClient::with_config(&ip, port, &mac, config) {
+                    Ok(client) => client,
+                    Err(e) => return waiter.resolve::<()>(Err(e)),
+                };
+                waiter.resolve(Ok(this));
+                // Runs until `close()` or the Python object is dropped.
+                for job in rx {
+                    job(&mut client);
+                }
+            })
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        Ok(future)
+    }
+
+    fn set_config<'py>(
+        &self,
+        py: Python<'py>,
+        ip: String,
+        port: u16,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, move |client| client.set_config(&ip, port))
+    }
+
+    fn lcd_picture_bottom<'py>(
+        &self,
+        py: Python<'py>,
+        buff: Vec<u8>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, move |client| client.lcd_picture_bottom(&buff))
+    }
+
+    fn lcd_picture<'py>(&self, py: Python<'py>, buff: Vec<u8>) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, move |client| client.lcd_picture(&buff))
+    }
+
+    fn chord<'py>(&self, py: Python<'py>, vkeys: Vec<Key>, ms: u64) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, move |client| client.chord(&vkeys, ms))
+    }
+
+    fn hotkey<'py>(&self, py: Python<'py>, hotkey: String, ms: u64) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, move |client| client.hotkey(&hotkey, ms))
+    }
+
+    fn type_text<'py>(
+        &self,
+        py: Python<'py>,
+        text: String,
+        per_key_ms: u64,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, move |client| client.type_text(&text, per_key_ms))
+    }
+
+    /// Round trip time in milliseconds, measured on the worker thread.
+    fn ping<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, |client| {
+            client.ping().map(|rtt| rtt.as_secs_f64() * 1000.0)
+        })
+    }
+
+    /// Record traffic to `capture` from the next queued command on.
+    fn set_capture<'py>(
+        &self,
+        py: Python<'py>,
+        capture: Option<Capture>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, move |client| {
+            client.set_capture(capture);
+            Ok(())
+        })
+    }
+
+    /// The counters once the commands queued before this call have run.
+    fn stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, |client| Ok(client.stats()))
+    }
+
+    /// The state once the commands queued before this call have run.
+    fn connection_state<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, |client| Ok(client.connection_state()))
+    }
+
+    /// The held keys once the commands queued before this call have run.
+    fn pressed_keys<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, |client| Ok(client.pressed_keys()))
+    }
+
+    fn pressed_modifiers<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, |client| Ok(client.pressed_modifiers()))
+    }
+
+    fn pressed_buttons<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, |client| Ok(client.pressed_buttons()))
+    }
+
+    fn is_pressed<'py>(&self, py: Python<'py>, vkey: Key) -> PyResult<Bound<'py, PyAny>> {
+        self.submit(py, move |client| Ok(client.is_pressed(vkey)))
+    }
+
+    /// Stop the worker once already queued commands have run.
+    fn close(&mut self) {
+        self.jobs = None;
+    }
+}
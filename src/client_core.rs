@@ -0,0 +1,338 @@
+use crate::capture::{Capture, CapturedPacket, Channel, Direction};
+use crate::client::{check_ack, key_from_mac, str_to_hex, ClientConfig, ConnectionState};
+use crate::client_stats::ClientStats;
+use crate::codec::{Decode, Encode};
+use crate::encryption::xxtea_encrypt;
+use crate::error::Error;
+use crate::keys::Key;
+use crate::protocol::*;
+use rand::Rng;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A command to send, without the `indexpts` it gets when it is sent.
+#[derive(Debug, Clone)]
+pub(crate) struct Command {
+    pub(crate) cmd: u32,
+    pub(crate) rand: u32,
+    pub(crate) payload: Vec<u8>,
+    pub(crate) encrypted: bool,
+}
+
+impl Command {
+    /// `cmd` with `payload` and a random `rand`.
+    pub(crate) fn new<T: Encode + ?Sized>(cmd: u32, payload: &T) -> Self {
+        Self::with_rand(cmd, payload, rand::thread_rng().gen())
+    }
+
+    pub(crate) fn with_rand<T: Encode + ?Sized>(cmd: u32, payload: &T, rand: u32) -> Self {
+        Self {
+            cmd,
+            rand,
+            payload: payload.to_bytes(),
+            encrypted: false,
+        }
+    }
+
+    /// Send this command as an encrypted 128-byte block.
+    pub(crate) fn encrypted(mut self) -> Self {
+        self.encrypted = true;
+        self
+    }
+}
+
+/// A command with its header, encoded and encrypted for the wire.
+pub(crate) struct Packet {
+    pub(crate) head: CmdHead,
+    pub(crate) data: Vec<u8>,
+    pub(crate) encrypted: bool,
+}
+
+/// Commands sent back to back, e.g. the chunks of an LCD picture, traced
+/// under one span.
+pub(crate) struct Batch {
+    pub(crate) commands: Vec<Command>,
+    #[cfg(feature = "tracing")]
+    pub(crate) span: tracing::Span,
+}
+
+/// Builds a `Batch` of `$commands` whose span is
+/// `tracing::info_span!($span...)`. Without the `tracing` feature the span
+/// arguments are dropped.
+macro_rules! batch {
+    ($commands:expr, $($span:tt)*) => {
+        $crate::client_core::Batch {
+            commands: $commands,
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!($($span)*),
+        }
+    };
+}
+pub(crate) use batch;
+
+/// The protocol side of a client, without any I/O.
+///
+/// `ClientCore` builds every packet and owns the state around them: the
+/// reports the box should hold, the masks, `indexpts`, the connection state,
+/// the stats and the capture. `KmBoxNetClient` and `AsyncKmBoxNetClient`
+/// only move its packets over their sockets and report back through the
+/// `sent`, `received`, `attempted`, `retry_delay` and `finish` hooks.
+pub(crate) struct ClientCore {
+    pub(crate) local_addr: SocketAddr,
+    pub(crate) dest_addr: SocketAddr,
+    pub(crate) mac: u32,
+    pub(crate) indexpts: u32,
+    pub(crate) key: [u8; 16],
+    pub(crate) soft_mouse: SoftMouse,
+    pub(crate) soft_keyboard: SoftKeyboard,
+    pub(crate) mask_keyboard_mouse_flag: i32,
+    pub(crate) masked_keys: Vec<Key>,
+    pub(crate) config: ClientConfig,
+    pub(crate) state: ConnectionState,
+    pub(crate) failures: u32,
+    pub(crate) stats: ClientStats,
+    pub(crate) capture: Option<Capture>,
+}
+
+impl ClientCore {
+    pub(crate) fn new(
+        local_addr: SocketAddr,
+        dest_addr: SocketAddr,
+        mac_str: &str,
+        config: ClientConfig,
+    ) -> Self {
+        let mac = str_to_hex(mac_str);
+        Self {
+            local_addr,
+            dest_addr,
+            mac,
+            indexpts: 0,
+            key: key_from_mac(mac),
+            soft_mouse: SoftMouse::default(),
+            soft_keyboard: SoftKeyboard::default(),
+            mask_keyboard_mouse_flag: 0,
+            masked_keys: Vec::new(),
+            config,
+            state: ConnectionState::Disconnected,
+            failures: 0,
+            stats: ClientStats::default(),
+            capture: None,
+        }
+    }
+
+    /// The handshake. It always uses `indexpts` 0.
+    pub(crate) fn handshake(&self) -> Batch {
+        let command = Command::new(CMD_CONNECT, crate::client::NO_PAYLOAD);
+        batch!(vec![command], "handshake", addr = %self.dest_addr)
+    }
+
+    /// The commands that restore the masks, held mouse buttons and held keys
+    /// on a box that forgot them, e.g. after a reboot.
+    pub(crate) fn restore(&self) -> Vec<Command> {
+        let mut commands = self.restore_masks();
+        if self.soft_mouse.button != 0 {
+            commands.push(Command::new(CMD_MOUSE_LEFT, &self.soft_mouse));
+        }
+        if self.soft_keyboard.ctrl != 0 || self.soft_keyboard.button.iter().any(|&k| k != 0) {
+            commands.push(Command::new(CMD_KEYBOARD_ALL, &self.soft_keyboard));
+        }
+        commands
+    }
+
+    /// The ping: a mouse report without movement that keeps the held buttons.
+    pub(crate) fn ping(&self) -> Command {
+        let mouse = SoftMouse {
+            button: self.soft_mouse.button,
+            ..SoftMouse::default()
+        };
+        Command::with_rand(CMD_MOUSE_MOVE, &mouse, 0)
+    }
+
+    /// Whether to reconnect before sending `command`: the link is down and
+    /// `auto_reconnect` is on.
+    pub(crate) fn needs_reconnect(&self, command: &Command) -> bool {
+        command.cmd != CMD_CONNECT
+            && self.config.auto_reconnect
+            && self.state == ConnectionState::Disconnected
+    }
+
+    /// Assign the next `indexpts` (0 for the handshake) and encode the
+    /// header followed by the payload, encrypted if asked to.
+    pub(crate) fn packet(&mut self, command: Command) -> Packet {
+        let indexpts = if command.cmd == CMD_CONNECT {
+            0
+        } else {
+            self.indexpts = self.indexpts.wrapping_add(1);
+            self.indexpts
+        };
+        self.stats.record_command(command.cmd, command.encrypted);
+        let head = CmdHead {
+            mac: self.mac,
+            rand: command.rand,
+            indexpts,
+            cmd: command.cmd,
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            cmd = cmd_name(head.cmd),
+            indexpts = head.indexpts,
+            rand = head.rand,
+            encrypted = command.encrypted,
+            payload = %crate::trace::payload_summary(&command.payload),
+            "send"
+        );
+        let mut data = head.to_bytes();
+        data.extend_from_slice(&command.payload);
+        if command.encrypted {
+            data = encrypt_packet(&data, &self.key);
+        }
+        Packet {
+            head,
+            data,
+            encrypted: command.encrypted,
+        }
+    }
+
+    /// How long to wait for the ack of `head`.
+    pub(crate) fn timeout(&self, head: &CmdHead) -> Duration {
+        if head.cmd == CMD_CONNECT {
+            self.config.connect_timeout
+        } else {
+            self.config.command_timeout
+        }
+    }
+
+    /// `packet` went out.
+    pub(crate) fn sent(&mut self, packet: &Packet) {
+        self.stats.bytes_sent += packet.data.len() as u64;
+        self.capture_packet(
+            Direction::Sent,
+            self.dest_addr,
+            Some(packet.head),
+            packet.encrypted,
+            &packet.data,
+        );
+    }
+
+    /// A datagram from `src` arrived while waiting for the ack of `sent`.
+    /// Returns `None` if it is not that ack; see `check_ack`.
+    pub(crate) fn received(
+        &self,
+        sent: &CmdHead,
+        src: SocketAddr,
+        data: &[u8],
+    ) -> Option<Result<(), Error>> {
+        self.capture_packet(Direction::Received, src, CmdHead::decode(data), false, data);
+        if src != self.dest_addr {
+            return None;
+        }
+        check_ack(sent, data)
+    }
+
+    /// One send of a packet ended with `res`.
+    pub(crate) fn attempted(&mut self, res: &Result<(), Error>) {
+        match res {
+            Ok(()) => self.stats.acks += 1,
+            Err(e) if e.kind() == KmError::NetRxTimeout => self.stats.timeouts += 1,
+            Err(_) => {}
+        }
+    }
+
+    /// How long to wait before resending `head` after its `attempt`-th send
+    /// (counting from 0) failed with `err`, or `None` to give up. Only
+    /// timeouts are retried, `retries` times, with the delay doubling from
+    /// `backoff`.
+    pub(crate) fn retry_delay(
+        &mut self,
+        head: &CmdHead,
+        err: &Error,
+        attempt: u32,
+    ) -> Option<Duration> {
+        if err.kind() != KmError::NetRxTimeout || attempt >= self.config.retries {
+            return None;
+        }
+        self.stats.retries += 1;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            cmd = cmd_name(head.cmd),
+            indexpts = head.indexpts,
+            attempt = attempt + 1,
+            "retry"
+        );
+        #[cfg(not(feature = "tracing"))]
+        let _ = head;
+        Some(self.config.backoff.saturating_mul(1 << attempt.min(31)))
+    }
+
+    /// The command `head`, sent at `start`, is done. Updates the connection
+    /// state.
+    pub(crate) fn finish(&mut self, head: &CmdHead, res: &Result<(), Error>, start: Instant) {
+        #[cfg(feature = "tracing")]
+        match res {
+            Ok(()) => tracing::debug!(
+                cmd = cmd_name(head.cmd),
+                indexpts = head.indexpts,
+                elapsed_us = start.elapsed().as_micros() as u64,
+                "ack"
+            ),
+            Err(e) => tracing::warn!(
+                cmd = cmd_name(head.cmd),
+                indexpts = head.indexpts,
+                elapsed_us = start.elapsed().as_micros() as u64,
+                error = %e,
+                "failed"
+            ),
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = start;
+        match res {
+            Ok(()) => {
+                self.failures = 0;
+                self.state = ConnectionState::Connected;
+            }
+            Err(e) if matches!(e.kind(), KmError::NetRxTimeout | KmError::NetTx) => {
+                self.failures += 1;
+                self.state =
+                    if head.cmd == CMD_CONNECT || self.failures >= self.config.disconnect_after {
+                        ConnectionState::Disconnected
+                    } else {
+                        ConnectionState::Degraded
+                    };
+            }
+            // The box answered, so the link itself is fine.
+            Err(_) => {}
+        }
+    }
+
+    fn capture_packet(
+        &self,
+        direction: Direction,
+        remote: SocketAddr,
+        head: Option<CmdHead>,
+        encrypted: bool,
+        data: &[u8],
+    ) {
+        let Some(capture) = &self.capture else {
+            return;
+        };
+        capture.record(&CapturedPacket {
+            time: SystemTime::now(),
+            direction,
+            channel: Channel::Command,
+            local: self.local_addr,
+            remote,
+            head,
+            encrypted,
+            data,
+        });
+    }
+}
+
+/// Pad or truncate a plain packet to the fixed 128-byte block and encrypt it.
+fn encrypt_packet(data: &[u8], key: &[u8; 16]) -> Vec<u8> {
+    let mut enc_buffer = vec![0u8; 128]; // Fixed 128 bytes buffer as per C++
+    let length = data.len().min(128);
+    enc_buffer[..length].copy_from_slice(&data[..length]);
+    xxtea_encrypt(&mut enc_buffer, key);
+    enc_buffer
+}
@@ -0,0 +1,195 @@
+use crate::client_shared::{PySharedKmBoxNetClient, SharedKmBoxNetClient};
+use crate::error::Error;
+use pyo3::prelude::*;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How many recent round trips `min`, `avg` and `p99` are computed over.
+const WINDOW: usize = 100;
+
+/// Snapshot of the latency seen by a `Heartbeat`.
+///
+/// `sent` and `lost` count every ping since the heartbeat started. The
+/// latency figures cover the last 100 successful pings and are `None` until
+/// the first one succeeds.
+#[pyclass]
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    #[pyo3(get)]
+    pub sent: u64,
+    #[pyo3(get)]
+    pub lost: u64,
+    pub last: Option<Duration>,
+    pub min: Option<Duration>,
+    pub avg: Option<Duration>,
+    pub p99: Option<Duration>,
+}
+
+#[pymethods]
+impl LatencyStats {
+    #[getter]
+    fn last_ms(&self) -> Option<f64> {
+        self.last.map(as_ms)
+    }
+
+    #[getter]
+    fn min_ms(&self) -> Option<f64> {
+        self.min.map(as_ms)
+    }
+
+    #[getter]
+    fn avg_ms(&self) -> Option<f64> {
+        self.avg.map(as_ms)
+    }
+
+    #[getter]
+    fn p99_ms(&self) -> Option<f64> {
+        self.p99.map(as_ms)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+fn as_ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+#[derive(Default)]
+struct Samples {
+    sent: u64,
+    lost: u64,
+    last: Option<Duration>,
+    window: VecDeque<Duration>,
+}
+
+impl Samples {
+    fn add(&mut self, res: &Result<Duration, Error>) {
+        self.sent += 1;
+        match res {
+            Ok(rtt) => {
+                self.last = Some(*rtt);
+                if self.window.len() == WINDOW {
+                    self.window.pop_front();
+                }
+                self.window.push_back(*rtt);
+            }
+            Err(_) => self.lost += 1,
+        }
+    }
+
+    fn stats(&self) -> LatencyStats {
+        let mut sorted: Vec<Duration> = self.window.iter().copied().collect();
+        sorted.sort();
+        let n = sorted.len();
+        LatencyStats {
+            sent: self.sent,
+            lost: self.lost,
+            last: self.last,
+            min: sorted.first().copied(),
+            avg: (n > 0).then(|| sorted.iter().sum::<Duration>() / n as u32),
+            // Nearest-rank percentile.
+            p99: (n > 0).then(|| sorted[(n * 99).div_ceil(100) - 1]),
+        }
+    }
+}
+
+/// Background thread that pings the box at a fixed interval.
+///
+/// Keeps the connection state of the client current, and with
+/// `auto_reconnect` re-handshakes while the program is otherwise idle.
+/// Stops when dropped, without waiting for a ping in flight; call `stop`
+/// to wait for it.
+#[pyclass]
+pub struct Heartbeat {
+    samples: Arc<Mutex<Samples>>,
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    /// Ping through `client` every `interval`, starting now.
+    pub fn start(client: SharedKmBoxNetClient, interval: Duration) -> Self {
+        let samples = Arc::new(Mutex::new(Samples::default()));
+        let samples_clone = samples.clone();
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let handle = thread::spawn(move || loop {
+            let res = client.ping();
+            samples_clone
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .add(&res);
+            // Wakes up early when `stop` is dropped.
+            if stopped.recv_timeout(interval) != Err(RecvTimeoutError::Timeout) {
+                break;
+            }
+        });
+
+        Self {
+            samples,
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Current latency and loss figures.
+    pub fn stats(&self) -> LatencyStats {
+        self.samples
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .stats()
+    }
+
+    /// Stop the heartbeat thread, waiting for a ping in flight.
+    pub fn stop(&mut self) {
+        self.stop = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[pymethods]
+impl Heartbeat {
+    #[pyo3(name = "stats")]
+    fn py_stats(&self) -> LatencyStats {
+        self.stats()
+    }
+
+    #[pyo3(name = "stop")]
+    fn py_stop(&mut self, py: Python<'_>) {
+        py.allow_threads(|| self.stop());
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        // Python may drop the heartbeat while holding the GIL that the ping
+        // thread needs for `enable_logging`, so joining here could deadlock.
+        // Dropping `stop` wakes the detached thread, which exits after the
+        // ping in flight.
+        self.stop = None;
+        self.handle = None;
+    }
+}
+
+impl SharedKmBoxNetClient {
+    /// Start a `Heartbeat` that pings the box every `interval`.
+    pub fn start_heartbeat(&self, interval: Duration) -> Heartbeat {
+        Heartbeat::start(self.clone(), interval)
+    }
+}
+
+#[pymethods]
+impl PySharedKmBoxNetClient {
+    /// Ping every `interval_ms` in the background. See `Heartbeat`.
+    #[pyo3(signature = (interval_ms = 1000))]
+    fn start_heartbeat(&self, interval_ms: u64) -> Heartbeat {
+        self.0.start_heartbeat(Duration::from_millis(interval_ms))
+    }
+}
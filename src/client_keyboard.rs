@@ -1,11 +1,13 @@
-use crate::keys::*;
+use crate::client_core::{ClientCore, Command};
+use crate::error::Error;
+use crate::keys::{Key, Modifier};
+use crate::layout::{Keystroke, Layout, MOD_ALTGR, MOD_SHIFT};
 use crate::protocol::{KmError, CMD_KEYBOARD_ALL};
 use crate::KmBoxNetClient;
 use pyo3::prelude::*;
 use std::thread;
 use std::time::Duration;
 
-#[pymethods]
 impl KmBoxNetClient {
     // --- Keyboard Functions ---
 
@@ -13,189 +15,304 @@ impl KmBoxNetClient {
     /// vk_key: 键值
     /// Press a key (keydown).
     /// Handles modifier keys and normal keys with duplicate checking and queue management.
-    pub fn keydown(&mut self, vkey: i32) -> Result<(), KmError> {
-        let vk_u8 = vkey as u8;
-
-        // Handle modifier keys (0xE0 - 0xE7)
-        if vk_u8 >= KEY_LEFTCONTROL && vk_u8 <= KEY_RIGHT_GUI {
-            match vk_u8 {
-                KEY_LEFTCONTROL => self.soft_keyboard.ctrl |= BIT0,
-                KEY_LEFTSHIFT => self.soft_keyboard.ctrl |= BIT1,
-                KEY_LEFTALT => self.soft_keyboard.ctrl |= BIT2,
-                KEY_LEFT_GUI => self.soft_keyboard.ctrl |= BIT3,
-                KEY_RIGHTCONTROL => self.soft_keyboard.ctrl |= BIT4,
-                KEY_RIGHTSHIFT => self.soft_keyboard.ctrl |= BIT5,
-                KEY_RIGHTALT => self.soft_keyboard.ctrl |= BIT6,
-                KEY_RIGHT_GUI => self.soft_keyboard.ctrl |= BIT7,
-                _ => {}
-            }
-        } else {
-            // Check if key already exists in the queue
-            for i in 0..10 {
-                if self.soft_keyboard.button[i] == vk_u8 {
-                    let keyboard = self.soft_keyboard;
-                    return self.send_command(CMD_KEYBOARD_ALL, &keyboard);
-                }
-            }
-
-            // Find empty slot
-            let mut found_slot = false;
-            for i in 0..10 {
-                if self.soft_keyboard.button[i] == 0 {
-                    self.soft_keyboard.button[i] = vk_u8;
-                    found_slot = true;
-                    break;
-                }
-            }
-
-            // If queue is full, shift everything left and add to the end
-            if !found_slot {
-                self.soft_keyboard.button.copy_within(1..10, 0);
-                self.soft_keyboard.button[9] = vk_u8;
-            }
-        }
-
-        let keyboard = self.soft_keyboard;
-        self.send_command(CMD_KEYBOARD_ALL, &keyboard)
+    pub fn keydown(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.press(&[vkey]);
+        self.send(command)
     }
 
     /// 键盘按键松开
     /// vk_key: 键值
     /// Release a key (keyup).
     /// Removes the key from the report and shifts subsequent keys to maintain a continuous queue.
-    pub fn keyup(&mut self, vkey: i32) -> Result<(), KmError> {
-        let vk_u8 = vkey as u8;
-
-        // Handle modifier keys
-        if vk_u8 >= KEY_LEFTCONTROL && vk_u8 <= KEY_RIGHT_GUI {
-            match vk_u8 {
-                KEY_LEFTCONTROL => self.soft_keyboard.ctrl &= !BIT0,
-                KEY_LEFTSHIFT => self.soft_keyboard.ctrl &= !BIT1,
-                KEY_LEFTALT => self.soft_keyboard.ctrl &= !BIT2,
-                KEY_LEFT_GUI => self.soft_keyboard.ctrl &= !BIT3,
-                KEY_RIGHTCONTROL => self.soft_keyboard.ctrl &= !BIT4,
-                KEY_RIGHTSHIFT => self.soft_keyboard.ctrl &= !BIT5,
-                KEY_RIGHTALT => self.soft_keyboard.ctrl &= !BIT6,
-                KEY_RIGHT_GUI => self.soft_keyboard.ctrl &= !BIT7,
-                _ => {}
-            }
-        } else {
-            // Find key and remove it, shifting others left
-            for i in 0..10 {
-                if self.soft_keyboard.button[i] == vk_u8 {
-                    // Shift remaining keys to fill the gap
-                    if i < 9 {
-                        self.soft_keyboard.button.copy_within((i + 1)..10, i);
-                    }
-                    self.soft_keyboard.button[9] = 0;
-                    break;
-                }
-            }
-        }
-
-        let keyboard = self.soft_keyboard;
-        self.send_command(CMD_KEYBOARD_ALL, &keyboard)
+    pub fn keyup(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.release(&[vkey]);
+        self.send(command)
     }
 
     /// 单击指定按键
     /// ms: 持续时间(毫秒)
     /// Press and release a key with a delay.
-    pub fn keypress(&mut self, vkey: i32, ms: u64) -> Result<(), KmError> {
-        self.keydown(vkey)?;
-        thread::sleep(Duration::from_millis(ms / 2));
-        self.keyup(vkey)?;
-        thread::sleep(Duration::from_millis(ms / 2));
+    pub fn keypress(&mut self, vkey: Key, ms: u64) -> Result<(), Error> {
+        self.run_keys(keypress_steps(vkey, ms), false)
+    }
+
+    /// 同时按下一组按键(组合键)，保持 ms 毫秒后一起松开
+    /// Press every key in `vkeys` in a single report, hold them for `ms`
+    /// milliseconds, then release them all in a single report. Modifiers set
+    /// their `ctrl` bit, so `[KEY_LEFTCONTROL, KEY_LEFTSHIFT, KEY_ESCAPE]`
+    /// reaches the host as one Ctrl+Shift+Esc chord.
+    pub fn chord(&mut self, vkeys: &[Key], ms: u64) -> Result<(), Error> {
+        self.run_keys(chord_steps(vkeys, ms), false)
+    }
+
+    /// 按名称发送组合键，例如 "ctrl+shift+esc"
+    /// Send the chord named by `hotkey`, key names joined with `+` as
+    /// accepted by `Key::from_str`. Fails with `NetCmd` before sending
+    /// anything if a name is unknown.
+    pub fn hotkey(&mut self, hotkey: &str, ms: u64) -> Result<(), Error> {
+        let steps = chord_steps(&hotkey_keys(hotkey)?, ms);
+        self.run_keys(steps, false)
+    }
+
+    /// 按主机键盘布局输入一段文本，需要时自动按住 Shift / AltGr
+    /// per_key_ms: 每个字符的按键时长(毫秒)
+    /// Type `text` on the host layout set in `ClientConfig::layout`, one
+    /// `keypress` per character, holding `KEY_LEFTSHIFT` or `KEY_RIGHTALT`
+    /// (AltGr) where needed. Dead keys are followed by a space.
+    /// Fails with `NetCmd` before sending anything if `text` contains a
+    /// character the layout cannot type.
+    pub fn type_text(&mut self, text: &str, per_key_ms: u64) -> Result<(), Error> {
+        let steps = self.core.type_text_steps(text, per_key_ms)?;
+        self.run_keys(steps, false)
+    }
+
+    /// Send the reports of `steps`, sleeping in between as they say.
+    fn run_keys(&mut self, steps: Vec<KeyStep>, encrypted: bool) -> Result<(), Error> {
+        for step in steps {
+            match step {
+                KeyStep::Report(change) => {
+                    let command = self.core.key_report(change, encrypted);
+                    self.send(command)?;
+                }
+                KeyStep::Wait(delay) => thread::sleep(delay),
+            }
+        }
         Ok(())
     }
 
+    /// 当前按住的普通按键(不含修饰键)，按按下顺序
+    /// Keys the client has pressed and not released, in press order.
+    /// Modifiers are reported by `pressed_modifiers` instead.
+    pub fn pressed_keys(&self) -> Vec<Key> {
+        self.core.soft_keyboard.pressed_keys()
+    }
+
+    /// 当前按住的修饰键
+    /// Modifiers the client has pressed and not released.
+    pub fn pressed_modifiers(&self) -> Vec<Modifier> {
+        self.core.soft_keyboard.pressed_modifiers()
+    }
+
+    /// 指定按键(含修饰键)是否处于按下状态
+    /// Whether the client holds `vkey`, modifier or not.
+    pub fn is_pressed(&self, vkey: Key) -> bool {
+        self.core.soft_keyboard.is_pressed(vkey)
+    }
+
     // --- Encrypted Keyboard Functions ---
 
     /// 键盘按键按下 (加密)
-    pub fn enc_keydown(&mut self, vkey: i32) -> Result<(), KmError> {
-        // Logic is the same as keydown, but uses encrypted command
-        // We reuse keydown's logic by temporarily swapping the send method
-        // or just re-implementing the state change part.
-        self.update_keyboard_state_down(vkey);
-        let keyboard = self.soft_keyboard;
-        self.send_command_encrypted(CMD_KEYBOARD_ALL, &keyboard)
+    pub fn enc_keydown(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.press(&[vkey]).encrypted();
+        self.send(command)
     }
 
     /// 键盘按键松开 (加密)
-    pub fn enc_keyup(&mut self, vkey: i32) -> Result<(), KmError> {
-        self.update_keyboard_state_up(vkey);
-        let keyboard = self.soft_keyboard;
-        self.send_command_encrypted(CMD_KEYBOARD_ALL, &keyboard)
+    pub fn enc_keyup(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.release(&[vkey]).encrypted();
+        self.send(command)
     }
 
     /// 单击指定按键 (加密)
-    pub fn enc_keypress(&mut self, vkey: i32, ms: u64) -> Result<(), KmError> {
-        self.enc_keydown(vkey)?;
-        thread::sleep(Duration::from_millis(ms / 2));
-        self.enc_keyup(vkey)?;
-        thread::sleep(Duration::from_millis(ms / 2));
-        Ok(())
+    pub fn enc_keypress(&mut self, vkey: Key, ms: u64) -> Result<(), Error> {
+        self.run_keys(keypress_steps(vkey, ms), true)
     }
+}
 
-    // Helper methods to avoid duplication between normal and encrypted functions
-    fn update_keyboard_state_down(&mut self, vkey: i32) {
-        let vk_u8 = vkey as u8;
-        if vk_u8 >= KEY_LEFTCONTROL && vk_u8 <= KEY_RIGHT_GUI {
-            match vk_u8 {
-                KEY_LEFTCONTROL => self.soft_keyboard.ctrl |= BIT0,
-                KEY_LEFTSHIFT => self.soft_keyboard.ctrl |= BIT1,
-                KEY_LEFTALT => self.soft_keyboard.ctrl |= BIT2,
-                KEY_LEFT_GUI => self.soft_keyboard.ctrl |= BIT3,
-                KEY_RIGHTCONTROL => self.soft_keyboard.ctrl |= BIT4,
-                KEY_RIGHTSHIFT => self.soft_keyboard.ctrl |= BIT5,
-                KEY_RIGHTALT => self.soft_keyboard.ctrl |= BIT6,
-                KEY_RIGHT_GUI => self.soft_keyboard.ctrl |= BIT7,
-                _ => {}
-            }
+/// A change to the held keys, sent as one report.
+pub(crate) enum KeyChange {
+    Press(Vec<Key>),
+    Release(Vec<Key>),
+}
+
+/// One step of a timed key sequence such as `keypress` or `type_text`.
+pub(crate) enum KeyStep {
+    Report(KeyChange),
+    Wait(Duration),
+}
+
+impl ClientCore {
+    /// Press `vkeys` and build the keyboard report.
+    pub(crate) fn press(&mut self, vkeys: &[Key]) -> Command {
+        self.key_report(KeyChange::Press(vkeys.to_vec()), false)
+    }
+
+    /// Release `vkeys` and build the keyboard report.
+    pub(crate) fn release(&mut self, vkeys: &[Key]) -> Command {
+        self.key_report(KeyChange::Release(vkeys.to_vec()), false)
+    }
+
+    /// Apply `change` to the held keys and build the keyboard report.
+    pub(crate) fn key_report(&mut self, change: KeyChange, encrypted: bool) -> Command {
+        match change {
+            KeyChange::Press(vkeys) => vkeys
+                .into_iter()
+                .for_each(|vkey| self.soft_keyboard.press(vkey.into())),
+            KeyChange::Release(vkeys) => vkeys
+                .into_iter()
+                .for_each(|vkey| self.soft_keyboard.release(vkey.into())),
+        }
+        let command = Command::new(CMD_KEYBOARD_ALL, &self.soft_keyboard);
+        if encrypted {
+            command.encrypted()
         } else {
-            for i in 0..10 {
-                if self.soft_keyboard.button[i] == vk_u8 {
-                    return;
-                }
-            }
-            let mut found = false;
-            for i in 0..10 {
-                if self.soft_keyboard.button[i] == 0 {
-                    self.soft_keyboard.button[i] = vk_u8;
-                    found = true;
-                    break;
-                }
-            }
-            if !found {
-                self.soft_keyboard.button.copy_within(1..10, 0);
-                self.soft_keyboard.button[9] = vk_u8;
-            }
+            command
         }
     }
 
-    fn update_keyboard_state_up(&mut self, vkey: i32) {
-        let vk_u8 = vkey as u8;
-        if vk_u8 >= KEY_LEFTCONTROL && vk_u8 <= KEY_RIGHT_GUI {
-            match vk_u8 {
-                KEY_LEFTCONTROL => self.soft_keyboard.ctrl &= !BIT0,
-                KEY_LEFTSHIFT => self.soft_keyboard.ctrl &= !BIT1,
-                KEY_LEFTALT => self.soft_keyboard.ctrl &= !BIT2,
-                KEY_LEFT_GUI => self.soft_keyboard.ctrl &= !BIT3,
-                KEY_RIGHTCONTROL => self.soft_keyboard.ctrl &= !BIT4,
-                KEY_RIGHTSHIFT => self.soft_keyboard.ctrl &= !BIT5,
-                KEY_RIGHTALT => self.soft_keyboard.ctrl &= !BIT6,
-                KEY_RIGHT_GUI => self.soft_keyboard.ctrl &= !BIT7,
-                _ => {}
-            }
-        } else {
-            for i in 0..10 {
-                if self.soft_keyboard.button[i] == vk_u8 {
-                    if i < 9 {
-                        self.soft_keyboard.button.copy_within((i + 1)..10, i);
-                    }
-                    self.soft_keyboard.button[9] = 0;
-                    break;
-                }
+    /// The steps that type `text` on the configured layout, starting from
+    /// the modifiers held now and restoring them at the end.
+    pub(crate) fn type_text_steps(
+        &self,
+        text: &str,
+        per_key_ms: u64,
+    ) -> Result<Vec<KeyStep>, Error> {
+        let strokes = keystrokes(self.config.layout, text)?;
+        let initial = self.soft_keyboard.ctrl & (MOD_SHIFT | MOD_ALTGR);
+        let mut held = initial;
+        let mut steps = Vec::new();
+        for stroke in strokes {
+            steps.extend(modifier_steps(held, stroke.modifiers));
+            held = stroke.modifiers;
+            steps.extend(keypress_steps(Key::try_from(stroke.key)?, per_key_ms));
+            if stroke.dead {
+                steps.extend(modifier_steps(held, 0));
+                held = 0;
+                steps.extend(keypress_steps(Key::Space, per_key_ms));
             }
         }
+        steps.extend(modifier_steps(held, initial));
+        Ok(steps)
+    }
+}
+
+/// Press, hold for half of `ms`, release, wait for the other half.
+pub(crate) fn keypress_steps(vkey: Key, ms: u64) -> Vec<KeyStep> {
+    vec![
+        KeyStep::Report(KeyChange::Press(vec![vkey])),
+        KeyStep::Wait(Duration::from_millis(ms / 2)),
+        KeyStep::Report(KeyChange::Release(vec![vkey])),
+        KeyStep::Wait(Duration::from_millis(ms / 2)),
+    ]
+}
+
+/// Press `vkeys` in one report, hold for `ms`, release them in one report.
+pub(crate) fn chord_steps(vkeys: &[Key], ms: u64) -> Vec<KeyStep> {
+    vec![
+        KeyStep::Report(KeyChange::Press(vkeys.to_vec())),
+        KeyStep::Wait(Duration::from_millis(ms)),
+        KeyStep::Report(KeyChange::Release(vkeys.to_vec())),
+    ]
+}
+
+/// Modifiers `type_text` presses: Shift and AltGr.
+const TYPING_MODIFIERS: [Modifier; 2] = [Modifier::LeftShift, Modifier::RightAlt];
+
+/// Press or release, one report each, the typing modifiers that differ
+/// between `from` and `to`.
+fn modifier_steps(from: u8, to: u8) -> Vec<KeyStep> {
+    TYPING_MODIFIERS
+        .into_iter()
+        .filter(|modifier| (from ^ to) & modifier.bit() != 0)
+        .map(|modifier| {
+            let vkeys = vec![modifier.into()];
+            if to & modifier.bit() != 0 {
+                KeyStep::Report(KeyChange::Press(vkeys))
+            } else {
+                KeyStep::Report(KeyChange::Release(vkeys))
+            }
+        })
+        .collect()
+}
+
+/// The keystrokes that type `text` on `layout`.
+fn keystrokes(layout: Layout, text: &str) -> Result<Vec<Keystroke>, Error> {
+    text.chars()
+        .map(|c| {
+            layout.keystroke(c).ok_or_else(|| {
+                Error::from(KmError::NetCmd)
+                    .with_cmd(CMD_KEYBOARD_ALL)
+                    .with_detail(format!("cannot type {:?} on the {} layout", c, layout))
+            })
+        })
+        .collect()
+}
+
+/// The usage codes of the keys in a `"ctrl+shift+esc"` style hotkey.
+pub(crate) fn hotkey_keys(hotkey: &str) -> Result<Vec<Key>, Error> {
+    hotkey
+        .split('+')
+        .map(|name| {
+            name.trim().parse::<Key>().map_err(|_| {
+                Error::from(KmError::NetCmd)
+                    .with_cmd(CMD_KEYBOARD_ALL)
+                    .with_detail(format!("unknown key {:?} in {:?}", name.trim(), hotkey))
+            })
+        })
+        .collect()
+}
+
+// Python bindings. The GIL is released while waiting on the network,
+// so monitor callbacks and other Python threads keep running.
+#[pymethods]
+impl KmBoxNetClient {
+    #[pyo3(name = "keydown")]
+    fn py_keydown(slf: &Bound<'_, Self>, vkey: Key) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.keydown(vkey))
+    }
+
+    #[pyo3(name = "keyup")]
+    fn py_keyup(slf: &Bound<'_, Self>, vkey: Key) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.keyup(vkey))
+    }
+
+    #[pyo3(name = "keypress")]
+    fn py_keypress(slf: &Bound<'_, Self>, vkey: Key, ms: u64) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.keypress(vkey, ms))
+    }
+
+    #[pyo3(name = "chord")]
+    fn py_chord(slf: &Bound<'_, Self>, vkeys: Vec<Key>, ms: u64) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.chord(&vkeys, ms))
+    }
+
+    #[pyo3(name = "hotkey")]
+    fn py_hotkey(slf: &Bound<'_, Self>, hotkey: &str, ms: u64) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.hotkey(hotkey, ms))
+    }
+
+    #[pyo3(name = "type_text")]
+    fn py_type_text(slf: &Bound<'_, Self>, text: &str, per_key_ms: u64) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.type_text(text, per_key_ms))
+    }
+
+    #[pyo3(name = "pressed_keys")]
+    fn py_pressed_keys(slf: &Bound<'_, Self>) -> PyResult<Vec<Key>> {
+        Ok(Self::py_borrow(slf)?.pressed_keys())
+    }
+
+    #[pyo3(name = "pressed_modifiers")]
+    fn py_pressed_modifiers(slf: &Bound<'_, Self>) -> PyResult<Vec<Modifier>> {
+        Ok(Self::py_borrow(slf)?.pressed_modifiers())
+    }
+
+    #[pyo3(name = "is_pressed")]
+    fn py_is_pressed(slf: &Bound<'_, Self>, vkey: Key) -> PyResult<bool> {
+        Ok(Self::py_borrow(slf)?.is_pressed(vkey))
+    }
+
+    #[pyo3(name = "enc_keydown")]
+    fn py_enc_keydown(slf: &Bound<'_, Self>, vkey: Key) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_keydown(vkey))
+    }
+
+    #[pyo3(name = "enc_keyup")]
+    fn py_enc_keyup(slf: &Bound<'_, Self>, vkey: Key) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_keyup(vkey))
+    }
+
+    #[pyo3(name = "enc_keypress")]
+    fn py_enc_keypress(slf: &Bound<'_, Self>, vkey: Key, ms: u64) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_keypress(vkey, ms))
     }
 }
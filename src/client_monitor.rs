@@ -1,9 +1,11 @@
+use crate::client::NO_PAYLOAD;
+use crate::client_core::{ClientCore, Command};
+use crate::error::Error;
+use crate::keys::Key;
 use crate::protocol::*;
 use crate::KmBoxNetClient;
 use pyo3::prelude::*;
-use std::mem;
 
-#[pymethods]
 impl KmBoxNetClient {
     // --- Monitor Functions ---
 
@@ -12,182 +14,196 @@ impl KmBoxNetClient {
     /// To receive the data, use `KmBoxMonitor::start(port, callback)`.
     ///
     /// port: UDP port to listen on. If 0, monitoring is disabled on the device.
-    pub fn monitor(&mut self, port: u16) -> Result<(), KmError> {
-        self.indexpts = self.indexpts.wrapping_add(1);
+    pub fn monitor(&mut self, port: u16) -> Result<(), Error> {
+        let command = self.core.monitor(port);
+        self.send(command)
+    }
 
-        let mut tx = ClientTx::default();
-        tx.head.mac = self.mac;
-        tx.head.indexpts = self.indexpts;
-        tx.head.cmd = CMD_MONITOR;
+    // --- Masking Functions ---
 
-        tx.head.rand = if port > 0 {
-            (port as u32) | 0xaa55_0000 // 0xaa55 << 16
-        } else {
-            0
-        };
+    pub fn mask_mouse_left(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x01, enable);
+        self.send(command)
+    }
 
-        let length = mem::size_of::<CmdHead>();
-        let data =
-            unsafe { std::slice::from_raw_parts(&tx as *const ClientTx as *const u8, length) };
+    pub fn mask_mouse_right(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x02, enable);
+        self.send(command)
+    }
 
-        self.socket
-            .send_to(data, self.dest_addr)
-            .map_err(|_| KmError::NetTx)?;
+    pub fn mask_mouse_middle(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x04, enable);
+        self.send(command)
+    }
 
-        let mut buf = [0u8; 1024];
-        self.socket
-            .recv_from(&mut buf)
-            .map_err(|_| KmError::NetRxTimeout)?;
+    pub fn mask_mouse_side1(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x08, enable);
+        self.send(command)
+    }
 
-        Ok(())
+    pub fn mask_mouse_side2(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x10, enable);
+        self.send(command)
     }
 
-    // --- Masking Functions ---
+    pub fn mask_mouse_x(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x20, enable);
+        self.send(command)
+    }
 
-    fn send_mask_command(&mut self, rand_val: u32) -> Result<(), KmError> {
-        self.indexpts = self.indexpts.wrapping_add(1);
-        let mut tx = ClientTx::default();
-        tx.head.mac = self.mac;
-        tx.head.indexpts = self.indexpts;
-        tx.head.cmd = CMD_MASK_MOUSE;
-        tx.head.rand = rand_val;
+    pub fn mask_mouse_y(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x40, enable);
+        self.send(command)
+    }
 
-        let length = mem::size_of::<CmdHead>();
-        let data =
-            unsafe { std::slice::from_raw_parts(&tx as *const ClientTx as *const u8, length) };
+    pub fn mask_mouse_wheel(&mut self, enable: bool) -> Result<(), Error> {
+        let command = self.core.mask_mouse(0x80, enable);
+        self.send(command)
+    }
 
-        self.socket
-            .send_to(data, self.dest_addr)
-            .map_err(|_| KmError::NetTx)?;
+    pub fn mask_keyboard(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.mask_keyboard(vkey);
+        self.send(command)
+    }
 
-        let mut buf = [0u8; 1024];
-        self.socket
-            .recv_from(&mut buf)
-            .map_err(|_| KmError::NetRxTimeout)?;
-        Ok(())
+    pub fn unmask_keyboard(&mut self, vkey: Key) -> Result<(), Error> {
+        let command = self.core.unmask_keyboard(vkey);
+        self.send(command)
     }
 
-    pub fn mask_mouse_left(&mut self, enable: bool) -> Result<(), KmError> {
-        if enable {
-            self.mask_keyboard_mouse_flag |= 0x01;
-        } else {
-            self.mask_keyboard_mouse_flag &= !0x01;
-        }
-        self.send_mask_command(self.mask_keyboard_mouse_flag as u32)
+    pub fn unmask_all(&mut self) -> Result<(), Error> {
+        let command = self.core.unmask_all();
+        self.send(command)
     }
+}
 
-    pub fn mask_mouse_right(&mut self, enable: bool) -> Result<(), KmError> {
-        if enable {
-            self.mask_keyboard_mouse_flag |= 0x02;
+impl ClientCore {
+    pub(crate) fn monitor(&self, port: u16) -> Command {
+        let rand_val = if port > 0 {
+            (port as u32) | 0xaa55_0000 // 0xaa55 << 16
         } else {
-            self.mask_keyboard_mouse_flag &= !0x02;
-        }
-        self.send_mask_command(self.mask_keyboard_mouse_flag as u32)
+            0
+        };
+        #[cfg(feature = "tracing")]
+        tracing::info!(port, "monitor");
+        Command::with_rand(CMD_MONITOR, NO_PAYLOAD, rand_val)
     }
 
-    pub fn mask_mouse_middle(&mut self, enable: bool) -> Result<(), KmError> {
+    /// Set or clear the mouse mask bits in `mask`.
+    pub(crate) fn mask_mouse(&mut self, mask: i32, enable: bool) -> Command {
         if enable {
-            self.mask_keyboard_mouse_flag |= 0x04;
+            self.mask_keyboard_mouse_flag |= mask;
         } else {
-            self.mask_keyboard_mouse_flag &= !0x04;
+            self.mask_keyboard_mouse_flag &= !mask;
         }
-        self.send_mask_command(self.mask_keyboard_mouse_flag as u32)
+        mask_command(self.mask_keyboard_mouse_flag as u32)
     }
 
-    pub fn mask_mouse_side1(&mut self, enable: bool) -> Result<(), KmError> {
-        if enable {
-            self.mask_keyboard_mouse_flag |= 0x08;
-        } else {
-            self.mask_keyboard_mouse_flag &= !0x08;
+    pub(crate) fn mask_keyboard(&mut self, vkey: Key) -> Command {
+        if !self.masked_keys.contains(&vkey) {
+            self.masked_keys.push(vkey);
         }
-        self.send_mask_command(self.mask_keyboard_mouse_flag as u32)
+        mask_command(self.key_mask_value(vkey))
     }
 
-    pub fn mask_mouse_side2(&mut self, enable: bool) -> Result<(), KmError> {
-        if enable {
-            self.mask_keyboard_mouse_flag |= 0x10;
-        } else {
-            self.mask_keyboard_mouse_flag &= !0x10;
-        }
-        self.send_mask_command(self.mask_keyboard_mouse_flag as u32)
+    pub(crate) fn unmask_keyboard(&mut self, vkey: Key) -> Command {
+        self.masked_keys.retain(|&k| k != vkey);
+        Command::with_rand(CMD_UNMASK_ALL, NO_PAYLOAD, self.key_mask_value(vkey))
     }
 
-    pub fn mask_mouse_x(&mut self, enable: bool) -> Result<(), KmError> {
-        if enable {
-            self.mask_keyboard_mouse_flag |= 0x20;
-        } else {
-            self.mask_keyboard_mouse_flag &= !0x20;
-        }
-        self.send_mask_command(self.mask_keyboard_mouse_flag as u32)
+    pub(crate) fn unmask_all(&mut self) -> Command {
+        self.mask_keyboard_mouse_flag = 0;
+        self.masked_keys.clear();
+        Command::with_rand(CMD_UNMASK_ALL, NO_PAYLOAD, 0)
     }
 
-    pub fn mask_mouse_y(&mut self, enable: bool) -> Result<(), KmError> {
-        if enable {
-            self.mask_keyboard_mouse_flag |= 0x40;
-        } else {
-            self.mask_keyboard_mouse_flag &= !0x40;
+    /// The commands that send the mouse mask flags and every masked key
+    /// again, e.g. after the box rebooted.
+    pub(crate) fn restore_masks(&self) -> Vec<Command> {
+        if self.masked_keys.is_empty() {
+            if self.mask_keyboard_mouse_flag != 0 {
+                return vec![mask_command(self.mask_keyboard_mouse_flag as u32)];
+            }
+            return Vec::new();
         }
-        self.send_mask_command(self.mask_keyboard_mouse_flag as u32)
+        // Each key mask command also carries the mouse flags.
+        self.masked_keys
+            .iter()
+            .map(|&vkey| mask_command(self.key_mask_value(vkey)))
+            .collect()
     }
 
-    pub fn mask_mouse_wheel(&mut self, enable: bool) -> Result<(), KmError> {
-        if enable {
-            self.mask_keyboard_mouse_flag |= 0x80;
-        } else {
-            self.mask_keyboard_mouse_flag &= !0x80;
-        }
-        self.send_mask_command(self.mask_keyboard_mouse_flag as u32)
+    /// The mouse mask flags in the low byte and `vkey` in the next one.
+    fn key_mask_value(&self, vkey: Key) -> u32 {
+        ((self.mask_keyboard_mouse_flag & 0xff) | ((u8::from(vkey) as i32) << 8)) as u32
+    }
+}
+
+fn mask_command(rand_val: u32) -> Command {
+    Command::with_rand(CMD_MASK_MOUSE, NO_PAYLOAD, rand_val)
+}
+
+// Python bindings. The GIL is released while waiting on the network,
+// so monitor callbacks and other Python threads keep running.
+#[pymethods]
+impl KmBoxNetClient {
+    #[pyo3(name = "monitor")]
+    fn py_monitor(slf: &Bound<'_, Self>, port: u16) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.monitor(port))
     }
 
-    pub fn mask_keyboard(&mut self, vkey: i32) -> Result<(), KmError> {
-        let val = (self.mask_keyboard_mouse_flag & 0xff) | ((vkey & 0xff) << 8);
-        self.send_mask_command(val as u32)
+    #[pyo3(name = "mask_mouse_left")]
+    fn py_mask_mouse_left(slf: &Bound<'_, Self>, enable: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mask_mouse_left(enable))
     }
 
-    pub fn unmask_keyboard(&mut self, vkey: i32) -> Result<(), KmError> {
-        self.indexpts = self.indexpts.wrapping_add(1);
-        let mut tx = ClientTx::default();
-        tx.head.mac = self.mac;
-        tx.head.indexpts = self.indexpts;
-        tx.head.cmd = CMD_UNMASK_ALL;
-        tx.head.rand = ((self.mask_keyboard_mouse_flag & 0xff) | ((vkey & 0xff) << 8)) as u32;
+    #[pyo3(name = "mask_mouse_right")]
+    fn py_mask_mouse_right(slf: &Bound<'_, Self>, enable: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mask_mouse_right(enable))
+    }
 
-        let length = mem::size_of::<CmdHead>();
-        let data =
-            unsafe { std::slice::from_raw_parts(&tx as *const ClientTx as *const u8, length) };
+    #[pyo3(name = "mask_mouse_middle")]
+    fn py_mask_mouse_middle(slf: &Bound<'_, Self>, enable: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mask_mouse_middle(enable))
+    }
 
-        self.socket
-            .send_to(data, self.dest_addr)
-            .map_err(|_| KmError::NetTx)?;
+    #[pyo3(name = "mask_mouse_side1")]
+    fn py_mask_mouse_side1(slf: &Bound<'_, Self>, enable: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mask_mouse_side1(enable))
+    }
 
-        let mut buf = [0u8; 1024];
-        self.socket
-            .recv_from(&mut buf)
-            .map_err(|_| KmError::NetRxTimeout)?;
-        Ok(())
+    #[pyo3(name = "mask_mouse_side2")]
+    fn py_mask_mouse_side2(slf: &Bound<'_, Self>, enable: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mask_mouse_side2(enable))
     }
 
-    pub fn unmask_all(&mut self) -> Result<(), KmError> {
-        self.indexpts = self.indexpts.wrapping_add(1);
-        let mut tx = ClientTx::default();
-        tx.head.mac = self.mac;
-        tx.head.indexpts = self.indexpts;
-        tx.head.cmd = CMD_UNMASK_ALL;
-        self.mask_keyboard_mouse_flag = 0;
-        tx.head.rand = 0;
+    #[pyo3(name = "mask_mouse_x")]
+    fn py_mask_mouse_x(slf: &Bound<'_, Self>, enable: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mask_mouse_x(enable))
+    }
+
+    #[pyo3(name = "mask_mouse_y")]
+    fn py_mask_mouse_y(slf: &Bound<'_, Self>, enable: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mask_mouse_y(enable))
+    }
 
-        let length = mem::size_of::<CmdHead>();
-        let data =
-            unsafe { std::slice::from_raw_parts(&tx as *const ClientTx as *const u8, length) };
+    #[pyo3(name = "mask_mouse_wheel")]
+    fn py_mask_mouse_wheel(slf: &Bound<'_, Self>, enable: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mask_mouse_wheel(enable))
+    }
 
-        self.socket
-            .send_to(data, self.dest_addr)
-            .map_err(|_| KmError::NetTx)?;
+    #[pyo3(name = "mask_keyboard")]
+    fn py_mask_keyboard(slf: &Bound<'_, Self>, vkey: Key) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mask_keyboard(vkey))
+    }
+
+    #[pyo3(name = "unmask_keyboard")]
+    fn py_unmask_keyboard(slf: &Bound<'_, Self>, vkey: Key) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.unmask_keyboard(vkey))
+    }
 
-        let mut buf = [0u8; 1024];
-        self.socket
-            .recv_from(&mut buf)
-            .map_err(|_| KmError::NetRxTimeout)?;
-        Ok(())
+    #[pyo3(name = "unmask_all")]
+    fn py_unmask_all(slf: &Bound<'_, Self>) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.unmask_all())
     }
 }
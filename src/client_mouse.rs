@@ -1,11 +1,12 @@
+use crate::client_core::{ClientCore, Command};
+use crate::error::Error;
 use crate::protocol::{
-    KmError, CMD_BAZER_MOVE, CMD_MOUSE_AUTOMOVE, CMD_MOUSE_LEFT, CMD_MOUSE_MIDDLE, CMD_MOUSE_MOVE,
+    CMD_BAZER_MOVE, CMD_MOUSE_AUTOMOVE, CMD_MOUSE_LEFT, CMD_MOUSE_MIDDLE, CMD_MOUSE_MOVE,
     CMD_MOUSE_RIGHT, CMD_MOUSE_WHEEL,
 };
 use crate::KmBoxNetClient;
 use pyo3::prelude::*;
 
-#[pymethods]
 impl KmBoxNetClient {
     // --- Mouse Functions ---
 
@@ -14,111 +15,69 @@ impl KmBoxNetClient {
     /// 返回值：0正常执行，其他值异常。
     /// Move the mouse relative to current position.
     /// x, y: relative movement
-    pub fn mouse_move(&mut self, x: i32, y: i32) -> Result<(), KmError> {
-        self.soft_mouse.x = x;
-        self.soft_mouse.y = y;
-        let mouse = self.soft_mouse;
-        let res = self.send_command(CMD_MOUSE_MOVE, &mouse);
-        self.soft_mouse.x = 0;
-        self.soft_mouse.y = 0;
-        res
+    pub fn mouse_move(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        let command = self.core.mouse_move(x, y);
+        self.send(command)
     }
 
     /// 鼠标左键控制
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
     /// Set mouse left button state.
-    pub fn mouse_left(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x01
-        } else {
-            self.soft_mouse.button & !0x01
-        };
-        let mouse = self.soft_mouse;
-        self.send_command(CMD_MOUSE_LEFT, &mouse)
+    pub fn mouse_left(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_left(is_down);
+        self.send(command)
     }
 
     /// 鼠标右键控制
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
     /// Set mouse right button state.
-    pub fn mouse_right(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x02
-        } else {
-            self.soft_mouse.button & !0x02
-        };
-        let mouse = self.soft_mouse;
-        self.send_command(CMD_MOUSE_RIGHT, &mouse)
+    pub fn mouse_right(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_right(is_down);
+        self.send(command)
     }
 
     /// 鼠标中键控制
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
     /// Set mouse middle button state.
-    pub fn mouse_middle(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x04
-        } else {
-            self.soft_mouse.button & !0x04
-        };
-        let mouse = self.soft_mouse;
-        self.send_command(CMD_MOUSE_MIDDLE, &mouse)
+    pub fn mouse_middle(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_middle(is_down);
+        self.send(command)
     }
 
     /// 鼠标侧键1控制
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
     /// Set mouse side button 1 (Back) state.
-    pub fn mouse_side1(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x08
-        } else {
-            self.soft_mouse.button & !0x08
-        };
-        // C++ implementation uses CMD_MOUSE_LEFT for side buttons but updates the button mask
-        let mouse = self.soft_mouse;
-        self.send_command(CMD_MOUSE_LEFT, &mouse)
+    pub fn mouse_side1(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_side1(is_down);
+        self.send(command)
     }
 
     /// 鼠标侧键2控制
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
     /// Set mouse side button 2 (Forward) state.
-    pub fn mouse_side2(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x10
-        } else {
-            self.soft_mouse.button & !0x10
-        };
-        let mouse = self.soft_mouse;
-        self.send_command(CMD_MOUSE_LEFT, &mouse)
+    pub fn mouse_side2(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_side2(is_down);
+        self.send(command)
     }
 
     /// 鼠标滚轮控制
     /// Scroll mouse wheel.
-    pub fn mouse_wheel(&mut self, wheel: i32) -> Result<(), KmError> {
-        self.soft_mouse.wheel = wheel;
-        let mouse = self.soft_mouse;
-        let res = self.send_command(CMD_MOUSE_WHEEL, &mouse);
-        self.soft_mouse.wheel = 0;
-        res
+    pub fn mouse_wheel(&mut self, wheel: i32) -> Result<(), Error> {
+        let command = self.core.mouse_wheel(wheel);
+        self.send(command)
     }
 
     /// 鼠标全报告控制函数
     /// Control all mouse parameters at once.
     /// Note: C++ source uses cmd_mouse_wheel for the 'all' function.
-    pub fn mouse_all(&mut self, button: i32, x: i32, y: i32, wheel: i32) -> Result<(), KmError> {
-        self.soft_mouse.button = button;
-        self.soft_mouse.x = x;
-        self.soft_mouse.y = y;
-        self.soft_mouse.wheel = wheel;
-        let mouse = self.soft_mouse;
-        let res = self.send_command(CMD_MOUSE_WHEEL, &mouse);
-        self.soft_mouse.x = 0;
-        self.soft_mouse.y = 0;
-        self.soft_mouse.wheel = 0;
-        res
+    pub fn mouse_all(&mut self, button: i32, x: i32, y: i32, wheel: i32) -> Result<(), Error> {
+        let command = self.core.mouse_all(button, x, y, wheel);
+        self.send(command)
     }
 
     /// 鼠标移动x,y个单位。模拟人为移动x,y个单位。不会出现键鼠异常的检测.
@@ -127,22 +86,18 @@ impl KmBoxNetClient {
     /// ms是设置移动需要多少毫秒.注意ms给的值不要太小，太小一样会出现键鼠数据异常。
     /// 尽量像人操作。实际用时会比ms小。
     /// Move mouse with automatic duration control (firmware side).
-    pub fn mouse_move_auto(&mut self, x: i32, y: i32, ms: u32) -> Result<(), KmError> {
-        self.soft_mouse.x = x;
-        self.soft_mouse.y = y;
-        let mouse = self.soft_mouse;
-        let res = self.send_command_with_rand(CMD_MOUSE_AUTOMOVE, &mouse, ms);
-        self.soft_mouse.x = 0;
-        self.soft_mouse.y = 0;
-        res
+    pub fn mouse_move_auto(&mut self, x: i32, y: i32, ms: u32) -> Result<(), Error> {
+        let command = self.core.mouse_move_auto(x, y, ms);
+        self.send(command)
     }
 
     /// 二阶贝塞尔曲线控制
-    /// x,y 	:目标点坐标
-    /// ms		:拟合此过程用时（单位ms）
-    /// x1,y1	:控制点p1点坐标
-    /// x2,y2	:控制点p2点坐标
+    /// x,y   :目标点坐标
+    /// ms    :拟合此过程用时（单位ms）
+    /// x1,y1 :控制点p1点坐标
+    /// x2,y2 :控制点p2点坐标
     /// Move mouse using Bezier curve (firmware side).
+    #[allow(clippy::too_many_arguments)]
     pub fn mouse_move_beizer(
         &mut self,
         x: i32,
@@ -152,21 +107,16 @@ impl KmBoxNetClient {
         y1: i32,
         x2: i32,
         y2: i32,
-    ) -> Result<(), KmError> {
-        self.soft_mouse.x = x;
-        self.soft_mouse.y = y;
-        self.soft_mouse.point[0] = x1;
-        self.soft_mouse.point[1] = y1;
-        self.soft_mouse.point[2] = x2;
-        self.soft_mouse.point[3] = y2;
-
-        let mouse = self.soft_mouse;
-        let res = self.send_command_with_rand(CMD_BAZER_MOVE, &mouse, ms);
+    ) -> Result<(), Error> {
+        let command = self.core.mouse_move_beizer(x, y, ms, x1, y1, x2, y2);
+        self.send(command)
+    }
 
-        self.soft_mouse.x = 0;
-        self.soft_mouse.y = 0;
-        self.soft_mouse.point = [0; 10];
-        res
+    /// 当前按住的鼠标按键掩码
+    /// Mouse buttons the client holds, as the bit mask `mouse_all` takes:
+    /// 0x01 left, 0x02 right, 0x04 middle, 0x08 side 1, 0x10 side 2.
+    pub fn pressed_buttons(&self) -> i32 {
+        self.core.soft_mouse.button
     }
 
     // --- Encrypted Mouse Functions ---
@@ -176,132 +126,168 @@ impl KmBoxNetClient {
     /// 自己写轨迹移动时使用此函数。
     /// 返回值：0正常执行，其他值异常。
     /// 此函数是带加密功能的，可以保证同一个移动指令网络数据包内容都不一样。无法通过网络发码抓捕来特征盒子。
-    pub fn enc_mouse_move(&mut self, x: i32, y: i32) -> Result<(), KmError> {
-        self.soft_mouse.x = x;
-        self.soft_mouse.y = y;
-        let mouse = self.soft_mouse;
-        let res = self.send_command_encrypted(CMD_MOUSE_MOVE, &mouse);
-        self.soft_mouse.x = 0;
-        self.soft_mouse.y = 0;
-        res
+    pub fn enc_mouse_move(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        let command = self.core.mouse_move(x, y).encrypted();
+        self.send(command)
     }
 
     /// 鼠标左键控制 (加密)
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
-    pub fn enc_mouse_left(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x01
-        } else {
-            self.soft_mouse.button & !0x01
-        };
-        let mouse = self.soft_mouse;
-        self.send_command_encrypted(CMD_MOUSE_LEFT, &mouse)
+    pub fn enc_mouse_left(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_left(is_down).encrypted();
+        self.send(command)
     }
 
     /// 鼠标右键控制 (加密)
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
-    pub fn enc_mouse_right(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x02
-        } else {
-            self.soft_mouse.button & !0x02
-        };
-        let mouse = self.soft_mouse;
-        self.send_command_encrypted(CMD_MOUSE_RIGHT, &mouse)
+    pub fn enc_mouse_right(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_right(is_down).encrypted();
+        self.send(command)
     }
 
     /// 鼠标中键控制 (加密)
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
-    pub fn enc_mouse_middle(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x04
-        } else {
-            self.soft_mouse.button & !0x04
-        };
-        let mouse = self.soft_mouse;
-        self.send_command_encrypted(CMD_MOUSE_MIDDLE, &mouse)
+    pub fn enc_mouse_middle(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_middle(is_down).encrypted();
+        self.send(command)
     }
 
     /// 鼠标侧键1控制 (加密)
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
-    pub fn enc_mouse_side1(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x08
-        } else {
-            self.soft_mouse.button & !0x08
-        };
-        let mouse = self.soft_mouse;
-        self.send_command_encrypted(CMD_MOUSE_LEFT, &mouse)
+    pub fn enc_mouse_side1(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_side1(is_down).encrypted();
+        self.send(command)
     }
 
     /// 鼠标侧键2控制 (加密)
     /// isdown :0松开 ，1按下
     /// 返回值：0正常执行，其他值异常。
-    pub fn enc_mouse_side2(&mut self, is_down: bool) -> Result<(), KmError> {
-        self.soft_mouse.button = if is_down {
-            self.soft_mouse.button | 0x10
-        } else {
-            self.soft_mouse.button & !0x10
-        };
-        let mouse = self.soft_mouse;
-        self.send_command_encrypted(CMD_MOUSE_LEFT, &mouse)
+    pub fn enc_mouse_side2(&mut self, is_down: bool) -> Result<(), Error> {
+        let command = self.core.mouse_side2(is_down).encrypted();
+        self.send(command)
     }
 
     /// 鼠标滚轮控制 (加密)
-    pub fn enc_mouse_wheel(&mut self, wheel: i32) -> Result<(), KmError> {
-        self.soft_mouse.wheel = wheel;
-        let mouse = self.soft_mouse;
-        let res = self.send_command_encrypted(CMD_MOUSE_WHEEL, &mouse);
-        self.soft_mouse.wheel = 0;
-        res
+    pub fn enc_mouse_wheel(&mut self, wheel: i32) -> Result<(), Error> {
+        let command = self.core.mouse_wheel(wheel).encrypted();
+        self.send(command)
     }
 
     /// 鼠标全报告控制函数 (加密)
-    pub fn enc_mouse_all(
+    pub fn enc_mouse_all(&mut self, button: i32, x: i32, y: i32, wheel: i32) -> Result<(), Error> {
+        let command = self.core.mouse_all(button, x, y, wheel).encrypted();
+        self.send(command)
+    }
+
+    /// 鼠标移动x,y个单位。模拟人为移动x,y个单位。不会出现键鼠异常的检测. (加密)
+    /// 没有写移动曲线的推荐用此函数。此函数不会出现跳跃现象，按照最小步进逼近
+    /// 目标点。耗时比kmNet_mouse_move高。
+    /// ms是设置移动需要多少毫秒.注意ms给的值不要太小，太小一样会出现键鼠数据异常。
+    /// 尽量像人操作。实际用时会比ms小。
+    pub fn enc_mouse_move_auto(&mut self, x: i32, y: i32, ms: u32) -> Result<(), Error> {
+        let command = self.core.mouse_move_auto(x, y, ms).encrypted();
+        self.send(command)
+    }
+
+    /// 二阶贝塞尔曲线控制 (加密)
+    /// x,y   :目标点坐标
+    /// ms    :拟合此过程用时（单位ms）
+    /// x1,y1 :控制点p1点坐标
+    /// x2,y2 :控制点p2点坐标
+    #[allow(clippy::too_many_arguments)]
+    pub fn enc_mouse_move_beizer(
         &mut self,
-        button: i32,
         x: i32,
         y: i32,
-        wheel: i32,
-    ) -> Result<(), KmError> {
+        ms: u32,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+    ) -> Result<(), Error> {
+        let command = self
+            .core
+            .mouse_move_beizer(x, y, ms, x1, y1, x2, y2)
+            .encrypted();
+        self.send(command)
+    }
+}
+
+impl ClientCore {
+    /// The report for the current mouse state. Movement, wheel and curve
+    /// points are relative, so they are cleared once the report is built;
+    /// only the buttons persist.
+    fn mouse_report(&mut self, cmd: u32, rand: Option<u32>) -> Command {
+        let command = match rand {
+            Some(rand) => Command::with_rand(cmd, &self.soft_mouse, rand),
+            None => Command::new(cmd, &self.soft_mouse),
+        };
+        self.soft_mouse.x = 0;
+        self.soft_mouse.y = 0;
+        self.soft_mouse.wheel = 0;
+        self.soft_mouse.point = [0; 10];
+        command
+    }
+
+    pub(crate) fn mouse_move(&mut self, x: i32, y: i32) -> Command {
+        self.soft_mouse.x = x;
+        self.soft_mouse.y = y;
+        self.mouse_report(CMD_MOUSE_MOVE, None)
+    }
+
+    /// Set or clear the button bits in `mask` and build the report for `cmd`.
+    fn mouse_button(&mut self, cmd: u32, mask: i32, is_down: bool) -> Command {
+        self.soft_mouse.set_button(mask, is_down);
+        self.mouse_report(cmd, None)
+    }
+
+    pub(crate) fn mouse_left(&mut self, is_down: bool) -> Command {
+        self.mouse_button(CMD_MOUSE_LEFT, 0x01, is_down)
+    }
+
+    pub(crate) fn mouse_right(&mut self, is_down: bool) -> Command {
+        self.mouse_button(CMD_MOUSE_RIGHT, 0x02, is_down)
+    }
+
+    pub(crate) fn mouse_middle(&mut self, is_down: bool) -> Command {
+        self.mouse_button(CMD_MOUSE_MIDDLE, 0x04, is_down)
+    }
+
+    pub(crate) fn mouse_side1(&mut self, is_down: bool) -> Command {
+        // C++ implementation uses CMD_MOUSE_LEFT for side buttons but updates the button mask
+        self.mouse_button(CMD_MOUSE_LEFT, 0x08, is_down)
+    }
+
+    pub(crate) fn mouse_side2(&mut self, is_down: bool) -> Command {
+        self.mouse_button(CMD_MOUSE_LEFT, 0x10, is_down)
+    }
+
+    pub(crate) fn mouse_wheel(&mut self, wheel: i32) -> Command {
+        self.soft_mouse.wheel = wheel;
+        self.mouse_report(CMD_MOUSE_WHEEL, None)
+    }
+
+    pub(crate) fn mouse_all(&mut self, button: i32, x: i32, y: i32, wheel: i32) -> Command {
         self.soft_mouse.button = button;
         self.soft_mouse.x = x;
         self.soft_mouse.y = y;
         self.soft_mouse.wheel = wheel;
         // C++ source uses cmd_mouse_wheel for the 'all' function
-        let mouse = self.soft_mouse;
-        let res = self.send_command_encrypted(CMD_MOUSE_WHEEL, &mouse);
-        self.soft_mouse.x = 0;
-        self.soft_mouse.y = 0;
-        self.soft_mouse.wheel = 0;
-        res
+        self.mouse_report(CMD_MOUSE_WHEEL, None)
     }
 
-    /// 鼠标移动x,y个单位。模拟人为移动x,y个单位。不会出现键鼠异常的检测. (加密)
-    /// 没有写移动曲线的推荐用此函数。此函数不会出现跳跃现象，按照最小步进逼近
-    /// 目标点。耗时比kmNet_mouse_move高。
-    /// ms是设置移动需要多少毫秒.注意ms给的值不要太小，太小一样会出现键鼠数据异常。
-    /// 尽量像人操作。实际用时会比ms小。
-    pub fn enc_mouse_move_auto(&mut self, x: i32, y: i32, ms: u32) -> Result<(), KmError> {
+    pub(crate) fn mouse_move_auto(&mut self, x: i32, y: i32, ms: u32) -> Command {
         self.soft_mouse.x = x;
         self.soft_mouse.y = y;
-        let mouse = self.soft_mouse;
-        let res = self.send_command_encrypted_with_rand(CMD_MOUSE_AUTOMOVE, &mouse, ms);
-        self.soft_mouse.x = 0;
-        self.soft_mouse.y = 0;
-        res
+        self.mouse_report(CMD_MOUSE_AUTOMOVE, Some(ms))
     }
 
-    /// 二阶贝塞尔曲线控制 (加密)
-    /// x,y 	:目标点坐标
-    /// ms		:拟合此过程用时（单位ms）
-    /// x1,y1	:控制点p1点坐标
-    /// x2,y2	:控制点p2点坐标
-    pub fn enc_mouse_move_beizer(
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn mouse_move_beizer(
         &mut self,
         x: i32,
         y: i32,
@@ -310,20 +296,156 @@ impl KmBoxNetClient {
         y1: i32,
         x2: i32,
         y2: i32,
-    ) -> Result<(), KmError> {
+    ) -> Command {
         self.soft_mouse.x = x;
         self.soft_mouse.y = y;
-        self.soft_mouse.point[0] = x1;
-        self.soft_mouse.point[1] = y1;
-        self.soft_mouse.point[2] = x2;
-        self.soft_mouse.point[3] = y2;
+        self.soft_mouse.point[..4].copy_from_slice(&[x1, y1, x2, y2]);
+        self.mouse_report(CMD_BAZER_MOVE, Some(ms))
+    }
+}
 
-        let mouse = self.soft_mouse;
-        let res = self.send_command_encrypted_with_rand(CMD_BAZER_MOVE, &mouse, ms);
+// Python bindings. The GIL is released while waiting on the network,
+// so monitor callbacks and other Python threads keep running.
+#[pymethods]
+impl KmBoxNetClient {
+    #[pyo3(name = "mouse_move")]
+    fn py_mouse_move(slf: &Bound<'_, Self>, x: i32, y: i32) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mouse_move(x, y))
+    }
 
-        self.soft_mouse.x = 0;
-        self.soft_mouse.y = 0;
-        self.soft_mouse.point = [0; 10];
-        res
+    #[pyo3(name = "mouse_left")]
+    fn py_mouse_left(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mouse_left(is_down))
+    }
+
+    #[pyo3(name = "mouse_right")]
+    fn py_mouse_right(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mouse_right(is_down))
+    }
+
+    #[pyo3(name = "mouse_middle")]
+    fn py_mouse_middle(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mouse_middle(is_down))
+    }
+
+    #[pyo3(name = "mouse_side1")]
+    fn py_mouse_side1(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mouse_side1(is_down))
+    }
+
+    #[pyo3(name = "mouse_side2")]
+    fn py_mouse_side2(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mouse_side2(is_down))
+    }
+
+    #[pyo3(name = "mouse_wheel")]
+    fn py_mouse_wheel(slf: &Bound<'_, Self>, wheel: i32) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mouse_wheel(wheel))
+    }
+
+    #[pyo3(name = "mouse_all")]
+    fn py_mouse_all(
+        slf: &Bound<'_, Self>,
+        button: i32,
+        x: i32,
+        y: i32,
+        wheel: i32,
+    ) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mouse_all(button, x, y, wheel))
+    }
+
+    #[pyo3(name = "mouse_move_auto")]
+    fn py_mouse_move_auto(slf: &Bound<'_, Self>, x: i32, y: i32, ms: u32) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.mouse_move_auto(x, y, ms))
+    }
+
+    #[pyo3(name = "mouse_move_beizer")]
+    #[allow(clippy::too_many_arguments)]
+    fn py_mouse_move_beizer(
+        slf: &Bound<'_, Self>,
+        x: i32,
+        y: i32,
+        ms: u32,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+    ) -> PyResult<()> {
+        Self::py_blocking(slf, |client| {
+            client.mouse_move_beizer(x, y, ms, x1, y1, x2, y2)
+        })
+    }
+
+    #[pyo3(name = "pressed_buttons")]
+    fn py_pressed_buttons(slf: &Bound<'_, Self>) -> PyResult<i32> {
+        Ok(Self::py_borrow(slf)?.pressed_buttons())
+    }
+
+    #[pyo3(name = "enc_mouse_move")]
+    fn py_enc_mouse_move(slf: &Bound<'_, Self>, x: i32, y: i32) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_mouse_move(x, y))
+    }
+
+    #[pyo3(name = "enc_mouse_left")]
+    fn py_enc_mouse_left(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_mouse_left(is_down))
+    }
+
+    #[pyo3(name = "enc_mouse_right")]
+    fn py_enc_mouse_right(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_mouse_right(is_down))
+    }
+
+    #[pyo3(name = "enc_mouse_middle")]
+    fn py_enc_mouse_middle(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_mouse_middle(is_down))
+    }
+
+    #[pyo3(name = "enc_mouse_side1")]
+    fn py_enc_mouse_side1(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_mouse_side1(is_down))
+    }
+
+    #[pyo3(name = "enc_mouse_side2")]
+    fn py_enc_mouse_side2(slf: &Bound<'_, Self>, is_down: bool) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_mouse_side2(is_down))
+    }
+
+    #[pyo3(name = "enc_mouse_wheel")]
+    fn py_enc_mouse_wheel(slf: &Bound<'_, Self>, wheel: i32) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_mouse_wheel(wheel))
+    }
+
+    #[pyo3(name = "enc_mouse_all")]
+    fn py_enc_mouse_all(
+        slf: &Bound<'_, Self>,
+        button: i32,
+        x: i32,
+        y: i32,
+        wheel: i32,
+    ) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_mouse_all(button, x, y, wheel))
+    }
+
+    #[pyo3(name = "enc_mouse_move_auto")]
+    fn py_enc_mouse_move_auto(slf: &Bound<'_, Self>, x: i32, y: i32, ms: u32) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.enc_mouse_move_auto(x, y, ms))
+    }
+
+    #[pyo3(name = "enc_mouse_move_beizer")]
+    #[allow(clippy::too_many_arguments)]
+    fn py_enc_mouse_move_beizer(
+        slf: &Bound<'_, Self>,
+        x: i32,
+        y: i32,
+        ms: u32,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+    ) -> PyResult<()> {
+        Self::py_blocking(slf, |client| {
+            client.enc_mouse_move_beizer(x, y, ms, x1, y1, x2, y2)
+        })
     }
 }
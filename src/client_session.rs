@@ -0,0 +1,139 @@
+use crate::client_asyncio::AsyncioKmBoxNetClient;
+use crate::client_core::{ClientCore, Command};
+use crate::client_shared::PySharedKmBoxNetClient;
+use crate::error::Error;
+use crate::protocol::{SoftKeyboard, SoftMouse, CMD_KEYBOARD_ALL, CMD_MOUSE_LEFT};
+use crate::KmBoxNetClient;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use std::ops::{Deref, DerefMut};
+
+impl KmBoxNetClient {
+    /// 松开所有按键与鼠标按键
+    /// Release every key and mouse button: clears the held keys and buttons
+    /// and sends an empty keyboard report and an empty mouse report. Both are
+    /// sent even if nothing is held here, so input left stuck by an earlier
+    /// process is released as well. Returns the first error.
+    pub fn release_all(&mut self) -> Result<(), Error> {
+        let [keys, buttons] = self.core.release_all();
+        let keys = self.send(keys);
+        let buttons = self.send(buttons);
+        keys.and(buttons)
+    }
+
+    /// 返回一个守卫，离开作用域(包括 panic)时松开所有按键
+    /// Borrow the client for a block of input. The guard derefs to the
+    /// client and calls `release_all` when dropped, including while
+    /// unwinding from a panic, so no key or button stays held.
+    pub fn session(&mut self) -> InputSession<'_> {
+        InputSession { client: self }
+    }
+}
+
+impl ClientCore {
+    /// Clear the held keys and buttons and build the empty keyboard and
+    /// mouse reports.
+    pub(crate) fn release_all(&mut self) -> [Command; 2] {
+        self.soft_keyboard = SoftKeyboard::default();
+        self.soft_mouse.button = 0;
+        [
+            Command::new(CMD_KEYBOARD_ALL, &self.soft_keyboard),
+            Command::new(CMD_MOUSE_LEFT, &SoftMouse::default()),
+        ]
+    }
+}
+
+/// Guard returned by `KmBoxNetClient::session`.
+pub struct InputSession<'a> {
+    client: &'a mut KmBoxNetClient,
+}
+
+impl Deref for InputSession<'_> {
+    type Target = KmBoxNetClient;
+
+    fn deref(&self) -> &KmBoxNetClient {
+        self.client
+    }
+}
+
+impl DerefMut for InputSession<'_> {
+    fn deref_mut(&mut self) -> &mut KmBoxNetClient {
+        self.client
+    }
+}
+
+impl Drop for InputSession<'_> {
+    fn drop(&mut self) {
+        // Nothing to report to; a failed release is only visible on the host.
+        let _ = self.client.release_all();
+    }
+}
+
+/// Python context manager returned by `session()`. Calls the client's
+/// `release_all` when the `with` (or `async with`) block exits, whether or
+/// not it raised. Exceptions from the block are never suppressed.
+#[pyclass(name = "InputSession", frozen)]
+pub struct PyInputSession {
+    client: PyObject,
+}
+
+#[pymethods]
+impl PyInputSession {
+    fn __enter__(&self, py: Python<'_>) -> PyObject {
+        self.client.clone_ref(py)
+    }
+
+    #[pyo3(signature = (*_exc))]
+    fn __exit__(&self, py: Python<'_>, _exc: &Bound<'_, PyTuple>) -> PyResult<bool> {
+        self.client.call_method0(py, "release_all")?;
+        Ok(false)
+    }
+
+    fn __aenter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let future = py
+            .import_bound("asyncio")?
+            .call_method0("get_running_loop")?
+            .call_method0("create_future")?;
+        future.call_method1("set_result", (self.client.clone_ref(py),))?;
+        Ok(future)
+    }
+
+    /// The awaitable of `release_all`, which resolves to `None`.
+    #[pyo3(signature = (*_exc))]
+    fn __aexit__(&self, py: Python<'_>, _exc: &Bound<'_, PyTuple>) -> PyResult<PyObject> {
+        self.client.call_method0(py, "release_all")
+    }
+}
+
+fn py_session(client: Bound<'_, PyAny>) -> PyInputSession {
+    PyInputSession {
+        client: client.unbind(),
+    }
+}
+
+#[pymethods]
+impl KmBoxNetClient {
+    #[pyo3(name = "release_all")]
+    fn py_release_all(slf: &Bound<'_, Self>) -> PyResult<()> {
+        Self::py_blocking(slf, |client| client.release_all())
+    }
+
+    #[pyo3(name = "session")]
+    fn py_session(slf: Bound<'_, Self>) -> PyInputSession {
+        py_session(slf.into_any())
+    }
+}
+
+#[pymethods]
+impl PySharedKmBoxNetClient {
+    fn session(slf: Bound<'_, Self>) -> PyInputSession {
+        py_session(slf.into_any())
+    }
+}
+
+#[pymethods]
+impl AsyncioKmBoxNetClient {
+    fn session(slf: Bound<'_, Self>) -> PyInputSession {
+        py_session(slf.into_any())
+    }
+}
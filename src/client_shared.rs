@@ -0,0 +1,261 @@
+use crate::capture::Capture;
+use crate::client::{py_config, with_client_methods, ClientConfig, ConnectionState};
+use crate::client_stats::ClientStats;
+use crate::error::Error;
+use crate::keys::{Key, Modifier};
+use crate::layout::Layout;
+use crate::KmBoxNetClient;
+use pyo3::prelude::*;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Cloneable, thread-safe handle to one `KmBoxNetClient`.
+///
+/// Every command locks the client for its whole duration, including the ack
+/// wait and `keypress` delays, so commands from different threads never
+/// interleave and `indexpts` and the held mouse buttons and keys stay
+/// consistent. Use `lock()` to send several commands back to back.
+#[derive(Clone)]
+pub struct SharedKmBoxNetClient {
+    inner: Arc<Mutex<KmBoxNetClient>>,
+}
+
+impl SharedKmBoxNetClient {
+    /// Connect to the device and perform the handshake.
+    pub fn new(ip: &str, port: u16, mac_str: &str) -> Result<Self, Error> {
+        KmBoxNetClient::new(ip, port, mac_str).map(Self::from)
+    }
+
+    /// Like `new`, with custom timeouts and retry policy.
+    pub fn with_config(
+        ip: &str,
+        port: u16,
+        mac_str: &str,
+        config: ClientConfig,
+    ) -> Result<Self, Error> {
+        KmBoxNetClient::with_config(ip, port, mac_str, config).map(Self::from)
+    }
+
+    /// Exclusive access to the client until the guard is dropped.
+    pub fn lock(&self) -> MutexGuard<'_, KmBoxNetClient> {
+        // A panic in another thread leaves the client itself usable.
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_config(&self, ip_str: &str, port: u16) -> Result<(), Error> {
+        self.lock().set_config(ip_str, port)
+    }
+
+    pub fn lcd_picture_bottom(&self, buff: &[u8]) -> Result<(), Error> {
+        self.lock().lcd_picture_bottom(buff)
+    }
+
+    pub fn lcd_picture(&self, buff: &[u8]) -> Result<(), Error> {
+        self.lock().lcd_picture(buff)
+    }
+
+    /// See `KmBoxNetClient::chord`.
+    pub fn chord(&self, vkeys: &[Key], ms: u64) -> Result<(), Error> {
+        self.lock().chord(vkeys, ms)
+    }
+
+    /// See `KmBoxNetClient::hotkey`.
+    pub fn hotkey(&self, hotkey: &str, ms: u64) -> Result<(), Error> {
+        self.lock().hotkey(hotkey, ms)
+    }
+
+    /// See `KmBoxNetClient::type_text`. Other threads wait until the whole
+    /// text is typed.
+    pub fn type_text(&self, text: &str, per_key_ms: u64) -> Result<(), Error> {
+        self.lock().type_text(text, per_key_ms)
+    }
+
+    /// See `KmBoxNetClient::ping`.
+    pub fn ping(&self) -> Result<Duration, Error> {
+        self.lock().ping()
+    }
+
+    /// See `KmBoxNetClient::set_capture`.
+    pub fn set_capture(&self, capture: Option<Capture>) {
+        self.lock().set_capture(capture)
+    }
+
+    /// See `KmBoxNetClient::stats`. Waits for a running command.
+    pub fn stats(&self) -> ClientStats {
+        self.lock().stats()
+    }
+
+    /// See `KmBoxNetClient::connection_state`. Waits for a running command.
+    pub fn connection_state(&self) -> ConnectionState {
+        self.lock().connection_state()
+    }
+
+    /// See `KmBoxNetClient::pressed_keys`. Waits for a running command.
+    pub fn pressed_keys(&self) -> Vec<Key> {
+        self.lock().pressed_keys()
+    }
+
+    /// See `KmBoxNetClient::pressed_modifiers`. Waits for a running command.
+    pub fn pressed_modifiers(&self) -> Vec<Modifier> {
+        self.lock().pressed_modifiers()
+    }
+
+    /// See `KmBoxNetClient::pressed_buttons`. Waits for a running command.
+    pub fn pressed_buttons(&self) -> i32 {
+        self.lock().pressed_buttons()
+    }
+
+    /// See `KmBoxNetClient::is_pressed`. Waits for a running command.
+    pub fn is_pressed(&self, vkey: Key) -> bool {
+        self.lock().is_pressed(vkey)
+    }
+}
+
+impl From<KmBoxNetClient> for SharedKmBoxNetClient {
+    fn from(client: KmBoxNetClient) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(client)),
+        }
+    }
+}
+
+macro_rules! shared_methods {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        impl SharedKmBoxNetClient {
+            $(
+                #[doc = concat!("See `KmBoxNetClient::", stringify!($name), "`.")]
+                #[allow(clippy::too_many_arguments)]
+                pub fn $name(&self, $($arg: $ty),*) -> Result<(), Error> {
+                    self.lock().$name($($arg),*)
+                }
+            )*
+        }
+
+        #[pymethods]
+        impl PySharedKmBoxNetClient {
+            $(
+                #[allow(clippy::too_many_arguments)]
+                fn $name(&self, py: Python<'_>, $($arg: $ty),*) -> PyResult<()> {
+                    py.allow_threads(|| self.0.$name($($arg),*))
+                        .map_err(Into::into)
+                }
+            )*
+        }
+    };
+}
+
+with_client_methods!(shared_methods);
+
+/// Python binding of `SharedKmBoxNetClient`. Safe to call from several
+/// Python threads; the GIL is released while a command waits for its turn.
+#[pyclass(name = "SharedKmBoxNetClient", frozen)]
+pub struct PySharedKmBoxNetClient(pub SharedKmBoxNetClient);
+
+#[pymethods]
+impl PySharedKmBoxNetClient {
+    #[new]
+    #[pyo3(signature = (
+        ip,
+        port,
+        mac,
+        *,
+        connect_timeout_ms = 3000,
+        timeout_ms = 3000,
+        retries = 0,
+        backoff_ms = 0,
+        auto_reconnect = false,
+        disconnect_after = 3,
+        layout = Layout::Us
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        py: Python<'_>,
+        ip: &str,
+        port: u16,
+        mac: &str,
+        connect_timeout_ms: u64,
+        timeout_ms: u64,
+        retries: u32,
+        backoff_ms: u64,
+        auto_reconnect: bool,
+        disconnect_after: u32,
+        layout: Layout,
+    ) -> PyResult<Self> {
+        let config = py_config(
+            connect_timeout_ms,
+            timeout_ms,
+            retries,
+            backoff_ms,
+            auto_reconnect,
+            disconnect_after,
+            layout,
+        );
+        py.allow_threads(|| SharedKmBoxNetClient::with_config(ip, port, mac, config))
+            .map(Self)
+            .map_err(Into::into)
+    }
+
+    fn set_config(&self, py: Python<'_>, ip: &str, port: u16) -> PyResult<()> {
+        py.allow_threads(|| self.0.set_config(ip, port))
+            .map_err(Into::into)
+    }
+
+    fn lcd_picture_bottom(&self, py: Python<'_>, buff: &[u8]) -> PyResult<()> {
+        py.allow_threads(|| self.0.lcd_picture_bottom(buff))
+            .map_err(Into::into)
+    }
+
+    fn lcd_picture(&self, py: Python<'_>, buff: &[u8]) -> PyResult<()> {
+        py.allow_threads(|| self.0.lcd_picture(buff))
+            .map_err(Into::into)
+    }
+
+    fn chord(&self, py: Python<'_>, vkeys: Vec<Key>, ms: u64) -> PyResult<()> {
+        py.allow_threads(|| self.0.chord(&vkeys, ms))
+            .map_err(Into::into)
+    }
+
+    fn hotkey(&self, py: Python<'_>, hotkey: &str, ms: u64) -> PyResult<()> {
+        py.allow_threads(|| self.0.hotkey(hotkey, ms))
+            .map_err(Into::into)
+    }
+
+    fn type_text(&self, py: Python<'_>, text: &str, per_key_ms: u64) -> PyResult<()> {
+        py.allow_threads(|| self.0.type_text(text, per_key_ms))
+            .map_err(Into::into)
+    }
+
+    fn ping(&self, py: Python<'_>) -> PyResult<f64> {
+        py.allow_threads(|| self.0.ping())
+            .map(|rtt| rtt.as_secs_f64() * 1000.0)
+            .map_err(Into::into)
+    }
+
+    fn set_capture(&self, py: Python<'_>, capture: Option<Capture>) {
+        py.allow_threads(|| self.0.set_capture(capture))
+    }
+
+    fn stats(&self, py: Python<'_>) -> ClientStats {
+        py.allow_threads(|| self.0.stats())
+    }
+
+    fn connection_state(&self, py: Python<'_>) -> ConnectionState {
+        py.allow_threads(|| self.0.connection_state())
+    }
+
+    fn pressed_keys(&self, py: Python<'_>) -> Vec<Key> {
+        py.allow_threads(|| self.0.pressed_keys())
+    }
+
+    fn pressed_modifiers(&self, py: Python<'_>) -> Vec<Modifier> {
+        py.allow_threads(|| self.0.pressed_modifiers())
+    }
+
+    fn pressed_buttons(&self, py: Python<'_>) -> i32 {
+        py.allow_threads(|| self.0.pressed_buttons())
+    }
+
+    fn is_pressed(&self, py: Python<'_>, vkey: Key) -> bool {
+        py.allow_threads(|| self.0.is_pressed(vkey))
+    }
+}
@@ -0,0 +1,72 @@
+use crate::protocol::cmd_name;
+use pyo3::prelude::*;
+use std::collections::BTreeMap;
+
+/// Counters kept by `KmBoxNetClient` since it was created.
+///
+/// `commands` counts each command once, however often it was resent; the
+/// handshake counts as `CMD_CONNECT`. `bytes_sent` and `timeouts` count
+/// every datagram and every ack wait that ran out, retries included.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    /// Commands sent per `CMD_*` id.
+    pub commands: BTreeMap<u32, u64>,
+    #[pyo3(get)]
+    pub bytes_sent: u64,
+    #[pyo3(get)]
+    pub acks: u64,
+    #[pyo3(get)]
+    pub timeouts: u64,
+    #[pyo3(get)]
+    pub retries: u64,
+    #[pyo3(get)]
+    pub encrypted: u64,
+    #[pyo3(get)]
+    pub plain: u64,
+}
+
+impl ClientStats {
+    pub(crate) fn record_command(&mut self, cmd: u32, encrypted: bool) {
+        *self.commands.entry(cmd).or_default() += 1;
+        if encrypted {
+            self.encrypted += 1;
+        } else {
+            self.plain += 1;
+        }
+    }
+
+    /// Total number of commands sent.
+    pub fn total_commands(&self) -> u64 {
+        self.encrypted + self.plain
+    }
+}
+
+#[pymethods]
+impl ClientStats {
+    /// Commands sent, keyed by `CMD_*` name.
+    #[getter(commands)]
+    fn py_commands(&self) -> BTreeMap<String, u64> {
+        self.commands
+            .iter()
+            .map(|(&cmd, &count)| (cmd_label(cmd), count))
+            .collect()
+    }
+
+    #[pyo3(name = "total_commands")]
+    fn py_total_commands(&self) -> u64 {
+        self.total_commands()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// `cmd_name`, or the hex id for commands it does not know.
+pub(crate) fn cmd_label(cmd: u32) -> String {
+    match cmd_name(cmd) {
+        "UNKNOWN" => format!("{:#010x}", cmd),
+        name => name.to_string(),
+    }
+}
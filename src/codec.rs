@@ -0,0 +1,348 @@
+use crate::protocol::*;
+use std::net::Ipv4Addr;
+
+/// Size of `CmdHead` on the wire.
+pub const HEAD_LEN: usize = 16;
+
+/// Serialize a protocol structure into its little-endian wire format.
+/// The output never depends on host endianness or struct padding.
+pub trait Encode {
+    /// Append the wire bytes of `self` to `buf`.
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf);
+        buf
+    }
+}
+
+/// Parse a protocol structure from its little-endian wire format.
+pub trait Decode: Sized {
+    /// Number of bytes `decode` reads.
+    const SIZE: usize;
+
+    /// Decode from the start of `buf`. Returns `None` if `buf` is too short.
+    fn decode(buf: &[u8]) -> Option<Self>;
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn i32_at(buf: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn i16_at(buf: &[u8], offset: usize) -> i16 {
+    i16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+impl Encode for [u8] {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+}
+
+impl Encode for CmdHead {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.mac.to_le_bytes());
+        buf.extend_from_slice(&self.rand.to_le_bytes());
+        buf.extend_from_slice(&self.indexpts.to_le_bytes());
+        buf.extend_from_slice(&self.cmd.to_le_bytes());
+    }
+}
+
+impl Decode for CmdHead {
+    const SIZE: usize = HEAD_LEN;
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::SIZE {
+            return None;
+        }
+        Some(Self {
+            mac: u32_at(buf, 0),
+            rand: u32_at(buf, 4),
+            indexpts: u32_at(buf, 8),
+            cmd: u32_at(buf, 12),
+        })
+    }
+}
+
+impl Encode for SoftMouse {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.button.to_le_bytes());
+        buf.extend_from_slice(&self.x.to_le_bytes());
+        buf.extend_from_slice(&self.y.to_le_bytes());
+        buf.extend_from_slice(&self.wheel.to_le_bytes());
+        for p in &self.point {
+            buf.extend_from_slice(&p.to_le_bytes());
+        }
+    }
+}
+
+impl Decode for SoftMouse {
+    const SIZE: usize = 56;
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::SIZE {
+            return None;
+        }
+        let mut point = [0i32; 10];
+        for (i, p) in point.iter_mut().enumerate() {
+            *p = i32_at(buf, 16 + i * 4);
+        }
+        Some(Self {
+            button: i32_at(buf, 0),
+            x: i32_at(buf, 4),
+            y: i32_at(buf, 8),
+            wheel: i32_at(buf, 12),
+            point,
+        })
+    }
+}
+
+impl Encode for SoftKeyboard {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(self.ctrl);
+        buf.push(self.resvel);
+        buf.extend_from_slice(&self.button);
+    }
+}
+
+impl Decode for SoftKeyboard {
+    const SIZE: usize = 12;
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::SIZE {
+            return None;
+        }
+        let mut button = [0u8; 10];
+        button.copy_from_slice(&buf[2..12]);
+        Some(Self {
+            ctrl: buf[0],
+            resvel: buf[1],
+            button,
+        })
+    }
+}
+
+// Monitor report, mouse half:
+// struct { u8 report_id, u8 buttons, i16 x, i16 y, i16 wheel }
+impl Encode for HardMouse {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(0);
+        buf.push(self.buttons);
+        buf.extend_from_slice(&self.x.to_le_bytes());
+        buf.extend_from_slice(&self.y.to_le_bytes());
+        buf.extend_from_slice(&self.wheel.to_le_bytes());
+    }
+}
+
+impl Decode for HardMouse {
+    const SIZE: usize = 8;
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::SIZE {
+            return None;
+        }
+        Some(Self {
+            buttons: buf[1],
+            x: i16_at(buf, 2),
+            y: i16_at(buf, 4),
+            wheel: i16_at(buf, 6),
+        })
+    }
+}
+
+// Monitor report, keyboard half:
+// struct { u8 report_id, u8 buttons, u8 data[10] }
+impl Encode for HardKeyboard {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(0);
+        buf.push(self.buttons);
+        let mut data = [0u8; 10];
+        let len = self.data.len().min(10);
+        data[..len].copy_from_slice(&self.data[..len]);
+        buf.extend_from_slice(&data);
+    }
+}
+
+impl Decode for HardKeyboard {
+    const SIZE: usize = 12;
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::SIZE {
+            return None;
+        }
+        Some(Self {
+            buttons: buf[1],
+            data: buf[2..12].to_vec(),
+        })
+    }
+}
+
+/// A full monitor datagram: mouse report followed by keyboard report.
+#[derive(Debug, Clone, Default)]
+pub struct MonitorReport {
+    pub mouse: HardMouse,
+    pub keyboard: HardKeyboard,
+}
+
+impl Encode for MonitorReport {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.mouse.encode(buf);
+        self.keyboard.encode(buf);
+    }
+}
+
+impl Decode for MonitorReport {
+    const SIZE: usize = HardMouse::SIZE + HardKeyboard::SIZE;
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        Some(Self {
+            mouse: HardMouse::decode(buf)?,
+            keyboard: HardKeyboard::decode(buf.get(HardMouse::SIZE..)?)?,
+        })
+    }
+}
+
+/// A command datagram sent by the client, decoded by `cmd` id.
+/// `rand` is interpreted per command where the SDK gives it a meaning.
+#[derive(Debug, Clone)]
+pub enum Packet {
+    Connect {
+        head: CmdHead,
+    },
+    /// Any of the `CMD_MOUSE_*` and `CMD_BAZER_MOVE` commands.
+    Mouse {
+        head: CmdHead,
+        mouse: SoftMouse,
+    },
+    Keyboard {
+        head: CmdHead,
+        keyboard: SoftKeyboard,
+    },
+    Reboot {
+        head: CmdHead,
+    },
+    /// `port` is `None` when monitoring is being disabled.
+    Monitor {
+        head: CmdHead,
+        port: Option<u16>,
+    },
+    MaskMouse {
+        head: CmdHead,
+        flags: u8,
+        vkey: u8,
+    },
+    /// `CMD_UNMASK_ALL`; with a non-zero `vkey` only that key is unmasked.
+    Unmask {
+        head: CmdHead,
+        flags: u8,
+        vkey: u8,
+    },
+    SetConfig {
+        head: CmdHead,
+        ip: Ipv4Addr,
+        port: u16,
+    },
+    SetVidPid {
+        head: CmdHead,
+        vid: u16,
+        pid: u16,
+    },
+    /// Four LCD rows of RGB565 pixels, starting at `row`.
+    ShowPic {
+        head: CmdHead,
+        row: u32,
+        pixels: Vec<u8>,
+    },
+    TraceEnable {
+        head: CmdHead,
+        type_: i32,
+        value: i32,
+    },
+    Unknown {
+        head: CmdHead,
+        payload: Vec<u8>,
+    },
+}
+
+impl Packet {
+    /// Parse a plain (unencrypted) command datagram.
+    /// Returns `None` if it is shorter than its command requires.
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        let head = CmdHead::decode(buf)?;
+        let payload = &buf[HEAD_LEN..];
+        let packet = match head.cmd {
+            CMD_CONNECT => Packet::Connect { head },
+            CMD_MOUSE_MOVE | CMD_MOUSE_LEFT | CMD_MOUSE_MIDDLE | CMD_MOUSE_RIGHT
+            | CMD_MOUSE_WHEEL | CMD_MOUSE_AUTOMOVE | CMD_BAZER_MOVE => Packet::Mouse {
+                head,
+                mouse: SoftMouse::decode(payload)?,
+            },
+            CMD_KEYBOARD_ALL => Packet::Keyboard {
+                head,
+                keyboard: SoftKeyboard::decode(payload)?,
+            },
+            CMD_REBOOT => Packet::Reboot { head },
+            CMD_MONITOR => Packet::Monitor {
+                head,
+                port: (head.rand >> 16 == 0xaa55).then_some(head.rand as u16),
+            },
+            CMD_MASK_MOUSE => Packet::MaskMouse {
+                head,
+                flags: head.rand as u8,
+                vkey: (head.rand >> 8) as u8,
+            },
+            CMD_UNMASK_ALL => Packet::Unmask {
+                head,
+                flags: head.rand as u8,
+                vkey: (head.rand >> 8) as u8,
+            },
+            CMD_SETCONFIG => Packet::SetConfig {
+                head,
+                ip: Ipv4Addr::from(head.rand.to_le_bytes()),
+                port: u16::from_be_bytes([*payload.first()?, *payload.get(1)?]),
+            },
+            CMD_SETVIDPID => Packet::SetVidPid {
+                head,
+                vid: head.rand as u16,
+                pid: (head.rand >> 16) as u16,
+            },
+            CMD_SHOWPIC => Packet::ShowPic {
+                head,
+                row: head.rand,
+                pixels: payload.to_vec(),
+            },
+            CMD_TRACE_ENABLE => Packet::TraceEnable {
+                head,
+                type_: (head.rand >> 24) as i32,
+                value: (head.rand & 0x00ff_ffff) as i32,
+            },
+            _ => Packet::Unknown {
+                head,
+                payload: payload.to_vec(),
+            },
+        };
+        Some(packet)
+    }
+
+    pub fn head(&self) -> &CmdHead {
+        match self {
+            Packet::Connect { head }
+            | Packet::Mouse { head, .. }
+            | Packet::Keyboard { head, .. }
+            | Packet::Reboot { head }
+            | Packet::Monitor { head, .. }
+            | Packet::MaskMouse { head, .. }
+            | Packet::Unmask { head, .. }
+            | Packet::SetConfig { head, .. }
+            | Packet::SetVidPid { head, .. }
+            | Packet::ShowPic { head, .. }
+            | Packet::TraceEnable { head, .. }
+            | Packet::Unknown { head, .. } => head,
+        }
+    }
+}
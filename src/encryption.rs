@@ -1,91 +1,208 @@
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types::PyByteArray;
 
+const DELTA: u32 = 0x9E3779B9;
+
+/// Which XXTEA variant to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XxteaMode {
+    /// The variant used by the box: always 6 rounds over the first 32 words
+    /// (128 bytes). Any data after the first 128 bytes is left untouched.
+    KmBox,
+    /// Standard XXTEA over the whole buffer with `6 + 52 / n` rounds.
+    /// The length must be a multiple of 4 bytes and at least 8 bytes.
+    Standard,
+}
+
+/// The buffer length is not valid for the requested `XxteaMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidBlockLength(pub usize);
+
+impl std::fmt::Display for InvalidBlockLength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid XXTEA block length: {} bytes", self.0)
+    }
+}
+
+impl std::error::Error for InvalidBlockLength {}
+
+impl From<InvalidBlockLength> for PyErr {
+    fn from(err: InvalidBlockLength) -> Self {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+fn py_mode(standard: bool) -> XxteaMode {
+    if standard {
+        XxteaMode::Standard
+    } else {
+        XxteaMode::KmBox
+    }
+}
+
 /// XXTEA encryption implementation.
 /// In Rust, this can be called with slices.
 /// In Python, `data` should be a `bytearray` to allow in-place modification.
+/// Pass `standard=True` for standard XXTEA over the whole buffer.
 #[pyfunction]
-#[pyo3(name = "xxtea_encrypt")]
-pub fn py_xxtea_encrypt(data: &Bound<'_, PyByteArray>, key: &[u8]) -> PyResult<()> {
+#[pyo3(name = "xxtea_encrypt", signature = (data, key, standard = false))]
+pub fn py_xxtea_encrypt(data: &Bound<'_, PyByteArray>, key: &[u8], standard: bool) -> PyResult<()> {
     // Safety: We ensure we don't hold references to the buffer longer than necessary.
     // PyByteArray::as_bytes_mut provides a mutable slice to the underlying Python buffer.
     let data_slice = unsafe { data.as_bytes_mut() };
-    xxtea_encrypt(data_slice, key);
+    if standard {
+        xxtea_encrypt_with(data_slice, key, py_mode(standard))?;
+    } else {
+        // Keep the historical behaviour of ignoring short buffers.
+        xxtea_encrypt(data_slice, key);
+    }
+    Ok(())
+}
+
+/// XXTEA decryption, the inverse of `xxtea_encrypt`.
+#[pyfunction]
+#[pyo3(name = "xxtea_decrypt", signature = (data, key, standard = false))]
+pub fn py_xxtea_decrypt(data: &Bound<'_, PyByteArray>, key: &[u8], standard: bool) -> PyResult<()> {
+    // Safety: see `py_xxtea_encrypt`.
+    let data_slice = unsafe { data.as_bytes_mut() };
+    if standard {
+        xxtea_decrypt_with(data_slice, key, py_mode(standard))?;
+    } else {
+        xxtea_decrypt(data_slice, key);
+    }
     Ok(())
 }
 
-/// Pure Rust implementation of XXTEA encryption.
+/// The key the box uses for encrypted commands, derived from its MAC
+/// (e.g. `"0B50E466"`).
+pub fn key_for_mac(mac_str: &str) -> [u8; 16] {
+    crate::client::key_from_mac(crate::client::str_to_hex(mac_str))
+}
+
+/// Pure Rust implementation of XXTEA encryption, in the box's fixed
+/// 128-byte mode. Buffers shorter than 128 bytes are left unchanged.
 pub fn xxtea_encrypt(data: &mut [u8], key: &[u8]) {
-    const N: usize = 32;
+    let _ = xxtea_encrypt_with(data, key, XxteaMode::KmBox);
+}
+
+/// Inverse of `xxtea_encrypt`. Buffers shorter than 128 bytes are left unchanged.
+pub fn xxtea_decrypt(data: &mut [u8], key: &[u8]) {
+    let _ = xxtea_decrypt_with(data, key, XxteaMode::KmBox);
+}
+
+/// Encrypt `data` in place using the given mode.
+pub fn xxtea_encrypt_with(
+    data: &mut [u8],
+    key: &[u8],
+    mode: XxteaMode,
+) -> Result<(), InvalidBlockLength> {
+    let (block, rounds) = split_block(data, mode)?;
+    let mut v = to_words(block);
+    encrypt_words(&mut v, &key_words(key), rounds);
+    from_words(&v, block);
+    Ok(())
+}
+
+/// Decrypt `data` in place using the given mode.
+pub fn xxtea_decrypt_with(
+    data: &mut [u8],
+    key: &[u8],
+    mode: XxteaMode,
+) -> Result<(), InvalidBlockLength> {
+    let (block, rounds) = split_block(data, mode)?;
+    let mut v = to_words(block);
+    decrypt_words(&mut v, &key_words(key), rounds);
+    from_words(&v, block);
+    Ok(())
+}
 
-    // Ensure we have enough data (32 * 4 = 128 bytes)
-    if data.len() < N * 4 {
-        return;
+/// The part of `data` that is processed, and the number of rounds.
+// `usize::is_multiple_of` needs Rust 1.87, newer than the crate otherwise needs.
+#[allow(clippy::manual_is_multiple_of)]
+fn split_block(data: &mut [u8], mode: XxteaMode) -> Result<(&mut [u8], u32), InvalidBlockLength> {
+    match mode {
+        XxteaMode::KmBox => {
+            const N: usize = 32;
+            // Ensure we have enough data (32 * 4 = 128 bytes)
+            if data.len() < N * 4 {
+                return Err(InvalidBlockLength(data.len()));
+            }
+            Ok((&mut data[..N * 4], 6))
+        }
+        XxteaMode::Standard => {
+            if data.len() < 8 || data.len() % 4 != 0 {
+                return Err(InvalidBlockLength(data.len()));
+            }
+            let rounds = 6 + 52 / (data.len() / 4) as u32;
+            Ok((data, rounds))
+        }
     }
+}
 
-    // Convert data to u32 vector (Little Endian)
-    let mut v: Vec<u32> = data[..N * 4]
+// Convert data to u32 vector (Little Endian)
+fn to_words(block: &[u8]) -> Vec<u32> {
+    block
         .chunks_exact(4)
         .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
-        .collect();
+        .collect()
+}
 
-    // Convert key to u32 vector (Little Endian)
-    // Key is expected to be 16 bytes (4 u32s)
-    let mut k_vec: Vec<u32> = key
-        .chunks_exact(4)
-        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
-        .collect();
+// Write back to data (Little Endian)
+fn from_words(v: &[u32], block: &mut [u8]) {
+    for (chunk, val) in block.chunks_exact_mut(4).zip(v) {
+        chunk.copy_from_slice(&val.to_le_bytes());
+    }
+}
 
-    // If key is shorter than 16 bytes, pad with 0
-    while k_vec.len() < 4 {
-        k_vec.push(0);
+// Key is expected to be 16 bytes (4 u32s). If it is shorter, pad with 0;
+// anything past 16 bytes is ignored.
+fn key_words(key: &[u8]) -> [u32; 4] {
+    let mut k = [0u32; 4];
+    for (word, chunk) in k.iter_mut().zip(key.chunks_exact(4)) {
+        *word = u32::from_le_bytes(chunk.try_into().unwrap());
     }
-    // We only need the first 4 u32s
-    let k = &k_vec[..4];
+    k
+}
 
-    let mut sum: u32 = 0;
-    let mut a3 = v[N - 1];
-    let mut a4;
-
-    let mut a7 = 6;
-    while a7 > 0 {
-        a7 -= 1;
-        sum = sum.wrapping_add(0x9E3779B9);
-        let a5 = (sum >> 2) & 3;
-
-        for p in 0..(N - 1) {
-            a4 = v[p + 1];
-
-            let term1 = (a3 >> 5) ^ (a4 << 2);
-            let term2 = (a4 >> 3) ^ (a3 << 4);
-            let term3 = sum ^ a4;
-            let term4 = k[(p & 3) ^ a5 as usize] ^ a3;
-
-            let val = term1.wrapping_add(term2) ^ term3.wrapping_add(term4);
-            v[p] = v[p].wrapping_add(val);
-            a3 = v[p];
-        }
+fn mx(sum: u32, y: u32, z: u32, p: usize, e: u32, k: &[u32; 4]) -> u32 {
+    let term1 = (z >> 5) ^ (y << 2);
+    let term2 = (y >> 3) ^ (z << 4);
+    let term3 = sum ^ y;
+    let term4 = k[(p & 3) ^ e as usize] ^ z;
+    term1.wrapping_add(term2) ^ term3.wrapping_add(term4)
+}
 
-        // Final step for the round
-        a4 = v[0];
-        let p = N - 1;
+fn encrypt_words(v: &mut [u32], k: &[u32; 4], rounds: u32) {
+    let n = v.len();
+    let mut sum: u32 = 0;
+    let mut z = v[n - 1];
 
-        let term1 = (a3 >> 5) ^ (a4 << 2);
-        let term2 = (a4 >> 3) ^ (a3 << 4);
-        let term3 = sum ^ a4;
-        let term4 = k[(p & 3) ^ a5 as usize] ^ a3;
+    for _ in 0..rounds {
+        sum = sum.wrapping_add(DELTA);
+        let e = (sum >> 2) & 3;
 
-        let val = term1.wrapping_add(term2) ^ term3.wrapping_add(term4);
-        v[p] = v[p].wrapping_add(val);
-        a3 = v[p];
+        for p in 0..n {
+            let y = v[(p + 1) % n];
+            v[p] = v[p].wrapping_add(mx(sum, y, z, p, e, k));
+            z = v[p];
+        }
     }
+}
 
-    // Write back to data (Little Endian)
-    for (i, val) in v.iter().enumerate() {
-        let bytes = val.to_le_bytes();
-        data[i * 4] = bytes[0];
-        data[i * 4 + 1] = bytes[1];
-        data[i * 4 + 2] = bytes[2];
-        data[i * 4 + 3] = bytes[3];
+fn decrypt_words(v: &mut [u32], k: &[u32; 4], rounds: u32) {
+    let n = v.len();
+    let mut sum = DELTA.wrapping_mul(rounds);
+    let mut y = v[0];
+
+    for _ in 0..rounds {
+        let e = (sum >> 2) & 3;
+
+        for p in (0..n).rev() {
+            let z = v[(p + n - 1) % n];
+            v[p] = v[p].wrapping_sub(mx(sum, y, z, p, e, k));
+            y = v[p];
+        }
+        sum = sum.wrapping_sub(DELTA);
     }
 }
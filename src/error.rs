@@ -0,0 +1,198 @@
+use crate::protocol::{cmd_name, CmdHead, KmError, CMD_CONNECT};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use std::io;
+
+create_exception!(
+    kmbox_net,
+    KmBoxError,
+    PyException,
+    "Base class for KmBoxNet errors. `code` holds the C SDK error code."
+);
+create_exception!(
+    kmbox_net,
+    ConnectError,
+    KmBoxError,
+    "Socket setup or the handshake with the box failed."
+);
+create_exception!(
+    kmbox_net,
+    TimeoutError,
+    KmBoxError,
+    "The box did not acknowledge a command in time."
+);
+create_exception!(
+    kmbox_net,
+    TransmitError,
+    KmBoxError,
+    "A command could not be sent."
+);
+create_exception!(
+    kmbox_net,
+    CommandError,
+    KmBoxError,
+    "Invalid arguments, or the box answered with an unexpected ack."
+);
+
+/// Error returned by the client, monitor and simulator.
+///
+/// Carries the `KmError` category (whose `code()` matches the C SDK
+/// numbering), the command that failed if there was one, what was wrong
+/// with the arguments for invalid input, and the underlying socket error
+/// if the failure came from the OS.
+#[derive(Debug)]
+pub struct Error {
+    kind: KmError,
+    cmd: Option<u32>,
+    indexpts: Option<u32>,
+    detail: Option<String>,
+    source: Option<io::Error>,
+}
+
+impl Error {
+    pub(crate) fn io(kind: KmError, source: io::Error) -> Self {
+        Self {
+            source: Some(source),
+            ..Self::from(kind)
+        }
+    }
+
+    /// Attach the command id and `indexpts` of `head`.
+    pub(crate) fn with_head(mut self, head: &CmdHead) -> Self {
+        self.cmd = Some(head.cmd);
+        self.indexpts = Some(head.indexpts);
+        self
+    }
+
+    /// Attach a command id, for failures before a packet was built.
+    pub(crate) fn with_cmd(mut self, cmd: u32) -> Self {
+        self.cmd = Some(cmd);
+        self
+    }
+
+    /// Describe what was wrong with the input.
+    pub(crate) fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn kind(&self) -> KmError {
+        self.kind
+    }
+
+    /// The C SDK error code, e.g. `-8997` for a receive timeout.
+    pub fn code(&self) -> i32 {
+        self.kind.code()
+    }
+
+    /// The `CMD_*` id of the failed command.
+    pub fn cmd(&self) -> Option<u32> {
+        self.cmd
+    }
+
+    /// The `indexpts` of the failed command.
+    pub fn indexpts(&self) -> Option<u32> {
+        self.indexpts
+    }
+
+    /// What was wrong with the input, e.g. `cannot type 'é'`.
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    /// The OS-level socket error, e.g. `ECONNREFUSED`.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.source.as_ref()
+    }
+}
+
+impl From<KmError> for Error {
+    fn from(kind: KmError) -> Self {
+        Self {
+            kind,
+            cmd: None,
+            indexpts: None,
+            detail: None,
+            source: None,
+        }
+    }
+}
+
+impl PartialEq<KmError> for Error {
+    fn eq(&self, other: &KmError) -> bool {
+        self.kind == *other
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        match (self.cmd, self.indexpts) {
+            (Some(cmd), Some(indexpts)) => {
+                write!(f, " ({}, indexpts {})", cmd_name(cmd), indexpts)?
+            }
+            (Some(cmd), None) => write!(f, " ({})", cmd_name(cmd))?,
+            _ => {}
+        }
+        if let Some(detail) = &self.detail {
+            write!(f, ": {}", detail)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl From<Error> for PyErr {
+    fn from(err: Error) -> Self {
+        let message = err.to_string();
+        let py_err = if err.cmd == Some(CMD_CONNECT) {
+            ConnectError::new_err(message)
+        } else {
+            match err.kind {
+                KmError::CreatSocket | KmError::NetVersion => ConnectError::new_err(message),
+                KmError::NetRxTimeout => TimeoutError::new_err(message),
+                KmError::NetTx | KmError::UsbDevTxTimeout => TransmitError::new_err(message),
+                KmError::NetCmd | KmError::NetPts | KmError::NetAckMismatch => {
+                    CommandError::new_err(message)
+                }
+                KmError::Success => KmBoxError::new_err(message),
+            }
+        };
+
+        Python::with_gil(|py| {
+            let value = py_err.value_bound(py);
+            // Setting attributes on a fresh exception instance cannot fail.
+            let _ = value.setattr("code", err.code());
+            let _ = value.setattr("cmd", err.cmd);
+            let _ = value.setattr("indexpts", err.indexpts);
+        });
+        py_err
+    }
+}
+
+/// Raised when a Python thread calls a `KmBoxNetClient` while another thread
+/// is using it. Unlike the SDK errors it has no `code`.
+pub(crate) fn client_in_use() -> PyErr {
+    let py_err = KmBoxError::new_err(
+        "KmBoxNetClient is in use by another thread; \
+         use SharedKmBoxNetClient to share a client between threads",
+    );
+    Python::with_gil(|py| {
+        let value = py_err.value_bound(py);
+        let _ = value.setattr("code", py.None());
+        let _ = value.setattr("cmd", py.None());
+        let _ = value.setattr("indexpts", py.None());
+    });
+    py_err
+}
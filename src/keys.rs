@@ -1,3 +1,10 @@
+use crate::error::Error;
+use crate::protocol::KmError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::fmt;
+use std::str::FromStr;
+
 pub const KEY_NONE: u8 = 0x00;
 pub const KEY_ERRORROLLOVER: u8 = 0x01;
 pub const KEY_POSTFAIL: u8 = 0x02;
@@ -226,3 +233,504 @@ pub const BIT4: u8 = 0x10;
 pub const BIT5: u8 = 0x20;
 pub const BIT6: u8 = 0x40;
 pub const BIT7: u8 = 0x80;
+
+macro_rules! key_enum {
+    ($($name:ident = $code:ident,)*) => {
+        /// A HID keyboard usage: one variant per `KEY_*` constant above,
+        /// leaving out the reserved codes below `KEY_A`.
+        ///
+        /// Converts to and from its usage code with `u8::from` and
+        /// `Key::try_from`, which rejects codes that are not keys, and to and
+        /// from its variant name with `Display` and `FromStr`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[repr(u8)]
+        pub enum Key {
+            $($name = $code,)*
+        }
+
+        impl Key {
+            /// Every key, in usage code order.
+            pub const ALL: &'static [Key] = &[$(Key::$name,)*];
+
+            /// The variant name, e.g. `"LeftControl"`.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(Key::$name => stringify!($name),)*
+                }
+            }
+
+            /// The `KEY_*` constant with this usage code, e.g. `"KEY_LEFTCONTROL"`.
+            pub const fn const_name(self) -> &'static str {
+                match self {
+                    $(Key::$name => stringify!($code),)*
+                }
+            }
+        }
+
+        impl TryFrom<u8> for Key {
+            type Error = Error;
+
+            fn try_from(code: u8) -> Result<Self, Error> {
+                match code {
+                    $($code => Ok(Key::$name),)*
+                    _ => Err(invalid_key(code.into())),
+                }
+            }
+        }
+    };
+}
+
+key_enum! {
+    A = KEY_A,
+    B = KEY_B,
+    C = KEY_C,
+    D = KEY_D,
+    E = KEY_E,
+    F = KEY_F,
+    G = KEY_G,
+    H = KEY_H,
+    I = KEY_I,
+    J = KEY_J,
+    K = KEY_K,
+    L = KEY_L,
+    M = KEY_M,
+    N = KEY_N,
+    O = KEY_O,
+    P = KEY_P,
+    Q = KEY_Q,
+    R = KEY_R,
+    S = KEY_S,
+    T = KEY_T,
+    U = KEY_U,
+    V = KEY_V,
+    W = KEY_W,
+    X = KEY_X,
+    Y = KEY_Y,
+    Z = KEY_Z,
+    Digit1 = KEY_1_EXCLAMATION_MARK,
+    Digit2 = KEY_2_AT,
+    Digit3 = KEY_3_NUMBER_SIGN,
+    Digit4 = KEY_4_DOLLAR,
+    Digit5 = KEY_5_PERCENT,
+    Digit6 = KEY_6_CARET,
+    Digit7 = KEY_7_AMPERSAND,
+    Digit8 = KEY_8_ASTERISK,
+    Digit9 = KEY_9_OPARENTHESIS,
+    Digit0 = KEY_0_CPARENTHESIS,
+    Enter = KEY_ENTER,
+    Escape = KEY_ESCAPE,
+    Backspace = KEY_BACKSPACE,
+    Tab = KEY_TAB,
+    Space = KEY_SPACEBAR,
+    Minus = KEY_MINUS_UNDERSCORE,
+    Equal = KEY_EQUAL_PLUS,
+    LeftBracket = KEY_OBRACKET_AND_OBRACE,
+    RightBracket = KEY_CBRACKET_AND_CBRACE,
+    Backslash = KEY_BACKSLASH_VERTICAL_BAR,
+    NonUsHash = KEY_NONUS_NUMBER_SIGN_TILDE,
+    Semicolon = KEY_SEMICOLON_COLON,
+    Quote = KEY_SINGLE_AND_DOUBLE_QUOTE,
+    Grave = KEY_GRAVE_ACCENT_AND_TILDE,
+    Comma = KEY_COMMA_AND_LESS,
+    Period = KEY_DOT_GREATER,
+    Slash = KEY_SLASH_QUESTION,
+    CapsLock = KEY_CAPS_LOCK,
+    F1 = KEY_F1,
+    F2 = KEY_F2,
+    F3 = KEY_F3,
+    F4 = KEY_F4,
+    F5 = KEY_F5,
+    F6 = KEY_F6,
+    F7 = KEY_F7,
+    F8 = KEY_F8,
+    F9 = KEY_F9,
+    F10 = KEY_F10,
+    F11 = KEY_F11,
+    F12 = KEY_F12,
+    PrintScreen = KEY_PRINTSCREEN,
+    ScrollLock = KEY_SCROLL_LOCK,
+    Pause = KEY_PAUSE,
+    Insert = KEY_INSERT,
+    Home = KEY_HOME,
+    PageUp = KEY_PAGEUP,
+    Delete = KEY_DELETE,
+    End = KEY_END1,
+    PageDown = KEY_PAGEDOWN,
+    RightArrow = KEY_RIGHTARROW,
+    LeftArrow = KEY_LEFTARROW,
+    DownArrow = KEY_DOWNARROW,
+    UpArrow = KEY_UPARROW,
+    NumLock = KEY_KEYPAD_NUM_LOCK_AND_CLEAR,
+    KeypadSlash = KEY_KEYPAD_SLASH,
+    KeypadAsterisk = KEY_KEYPAD_ASTERIKS,
+    KeypadMinus = KEY_KEYPAD_MINUS,
+    KeypadPlus = KEY_KEYPAD_PLUS,
+    KeypadEnter = KEY_KEYPAD_ENTER,
+    Keypad1 = KEY_KEYPAD_1_END,
+    Keypad2 = KEY_KEYPAD_2_DOWN_ARROW,
+    Keypad3 = KEY_KEYPAD_3_PAGEDN,
+    Keypad4 = KEY_KEYPAD_4_LEFT_ARROW,
+    Keypad5 = KEY_KEYPAD_5,
+    Keypad6 = KEY_KEYPAD_6_RIGHT_ARROW,
+    Keypad7 = KEY_KEYPAD_7_HOME,
+    Keypad8 = KEY_KEYPAD_8_UP_ARROW,
+    Keypad9 = KEY_KEYPAD_9_PAGEUP,
+    Keypad0 = KEY_KEYPAD_0_INSERT,
+    KeypadPeriod = KEY_KEYPAD_DECIMAL_SEPARATOR_DELETE,
+    NonUsBackslash = KEY_NONUS_BACK_SLASH_VERTICAL_BAR,
+    Application = KEY_APPLICATION,
+    Power = KEY_POWER,
+    KeypadEqual = KEY_KEYPAD_EQUAL,
+    F13 = KEY_F13,
+    F14 = KEY_F14,
+    F15 = KEY_F15,
+    F16 = KEY_F16,
+    F17 = KEY_F17,
+    F18 = KEY_F18,
+    F19 = KEY_F19,
+    F20 = KEY_F20,
+    F21 = KEY_F21,
+    F22 = KEY_F22,
+    F23 = KEY_F23,
+    F24 = KEY_F24,
+    Execute = KEY_EXECUTE,
+    Help = KEY_HELP,
+    Menu = KEY_MENU,
+    Select = KEY_SELECT,
+    Stop = KEY_STOP,
+    Again = KEY_AGAIN,
+    Undo = KEY_UNDO,
+    Cut = KEY_CUT,
+    Copy = KEY_COPY,
+    Paste = KEY_PASTE,
+    Find = KEY_FIND,
+    Mute = KEY_MUTE,
+    VolumeUp = KEY_VOLUME_UP,
+    VolumeDown = KEY_VOLUME_DOWN,
+    LockingCapsLock = KEY_LOCKING_CAPS_LOCK,
+    LockingNumLock = KEY_LOCKING_NUM_LOCK,
+    LockingScrollLock = KEY_LOCKING_SCROLL_LOCK,
+    KeypadComma = KEY_KEYPAD_COMMA,
+    KeypadEqualSign = KEY_KEYPAD_EQUAL_SIGN,
+    International1 = KEY_INTERNATIONAL1,
+    International2 = KEY_INTERNATIONAL2,
+    International3 = KEY_INTERNATIONAL3,
+    International4 = KEY_INTERNATIONAL4,
+    International5 = KEY_INTERNATIONAL5,
+    International6 = KEY_INTERNATIONAL6,
+    International7 = KEY_INTERNATIONAL7,
+    International8 = KEY_INTERNATIONAL8,
+    International9 = KEY_INTERNATIONAL9,
+    Lang1 = KEY_LANG1,
+    Lang2 = KEY_LANG2,
+    Lang3 = KEY_LANG3,
+    Lang4 = KEY_LANG4,
+    Lang5 = KEY_LANG5,
+    Lang6 = KEY_LANG6,
+    Lang7 = KEY_LANG7,
+    Lang8 = KEY_LANG8,
+    Lang9 = KEY_LANG9,
+    AlternateErase = KEY_ALTERNATE_ERASE,
+    SysReq = KEY_SYSREQ,
+    Cancel = KEY_CANCEL,
+    Clear = KEY_CLEAR,
+    Prior = KEY_PRIOR,
+    Return = KEY_RETURN,
+    Separator = KEY_SEPARATOR,
+    Out = KEY_OUT,
+    Oper = KEY_OPER,
+    ClearAgain = KEY_CLEAR_AGAIN,
+    CrSel = KEY_CRSEL,
+    ExSel = KEY_EXSEL,
+    Keypad00 = KEY_KEYPAD_00,
+    Keypad000 = KEY_KEYPAD_000,
+    ThousandsSeparator = KEY_THOUSANDS_SEPARATOR,
+    DecimalSeparator = KEY_DECIMAL_SEPARATOR,
+    CurrencyUnit = KEY_CURRENCY_UNIT,
+    CurrencySubUnit = KEY_CURRENCY_SUB_UNIT,
+    KeypadLeftParen = KEY_KEYPAD_OPARENTHESIS,
+    KeypadRightParen = KEY_KEYPAD_CPARENTHESIS,
+    KeypadLeftBrace = KEY_KEYPAD_OBRACE,
+    KeypadRightBrace = KEY_KEYPAD_CBRACE,
+    KeypadTab = KEY_KEYPAD_TAB,
+    KeypadBackspace = KEY_KEYPAD_BACKSPACE,
+    KeypadA = KEY_KEYPAD_A,
+    KeypadB = KEY_KEYPAD_B,
+    KeypadC = KEY_KEYPAD_C,
+    KeypadD = KEY_KEYPAD_D,
+    KeypadE = KEY_KEYPAD_E,
+    KeypadF = KEY_KEYPAD_F,
+    KeypadXor = KEY_KEYPAD_XOR,
+    KeypadCaret = KEY_KEYPAD_CARET,
+    KeypadPercent = KEY_KEYPAD_PERCENT,
+    KeypadLess = KEY_KEYPAD_LESS,
+    KeypadGreater = KEY_KEYPAD_GREATER,
+    KeypadAmpersand = KEY_KEYPAD_AMPERSAND,
+    KeypadLogicalAnd = KEY_KEYPAD_LOGICAL_AND,
+    KeypadVerticalBar = KEY_KEYPAD_VERTICAL_BAR,
+    KeypadLogicalOr = KEY_KEYPAD_LOGIACL_OR,
+    KeypadColon = KEY_KEYPAD_COLON,
+    KeypadNumberSign = KEY_KEYPAD_NUMBER_SIGN,
+    KeypadSpace = KEY_KEYPAD_SPACE,
+    KeypadAt = KEY_KEYPAD_AT,
+    KeypadExclamationMark = KEY_KEYPAD_EXCLAMATION_MARK,
+    KeypadMemoryStore = KEY_KEYPAD_MEMORY_STORE,
+    KeypadMemoryRecall = KEY_KEYPAD_MEMORY_RECALL,
+    KeypadMemoryClear = KEY_KEYPAD_MEMORY_CLEAR,
+    KeypadMemoryAdd = KEY_KEYPAD_MEMORY_ADD,
+    KeypadMemorySubtract = KEY_KEYPAD_MEMORY_SUBTRACT,
+    KeypadMemoryMultiply = KEY_KEYPAD_MEMORY_MULTIPLY,
+    KeypadMemoryDivide = KEY_KEYPAD_MEMORY_DIVIDE,
+    KeypadPlusMinus = KEY_KEYPAD_PLUSMINUS,
+    KeypadClear = KEY_KEYPAD_CLEAR,
+    KeypadClearEntry = KEY_KEYPAD_CLEAR_ENTRY,
+    KeypadBinary = KEY_KEYPAD_BINARY,
+    KeypadOctal = KEY_KEYPAD_OCTAL,
+    KeypadDecimal = KEY_KEYPAD_DECIMAL,
+    KeypadHexadecimal = KEY_KEYPAD_HEXADECIMAL,
+    LeftControl = KEY_LEFTCONTROL,
+    LeftShift = KEY_LEFTSHIFT,
+    LeftAlt = KEY_LEFTALT,
+    LeftGui = KEY_LEFT_GUI,
+    RightControl = KEY_RIGHTCONTROL,
+    RightShift = KEY_RIGHTSHIFT,
+    RightAlt = KEY_RIGHTALT,
+    RightGui = KEY_RIGHT_GUI,
+}
+
+fn invalid_key(code: i32) -> Error {
+    Error::from(KmError::NetCmd).with_detail(format!("invalid key code {}", code))
+}
+
+impl Key {
+    /// The modifier this key is, if any.
+    pub fn modifier(self) -> Option<Modifier> {
+        Modifier::ALL.into_iter().find(|&m| Key::from(m) == self)
+    }
+}
+
+impl From<Key> for u8 {
+    fn from(key: Key) -> u8 {
+        key as u8
+    }
+}
+
+impl TryFrom<i32> for Key {
+    type Error = Error;
+
+    fn try_from(code: i32) -> Result<Self, Error> {
+        u8::try_from(code)
+            .map_err(|_| invalid_key(code))?
+            .try_into()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// Parse a key name as written in hotkey strings such as
+    /// `"ctrl+shift+esc"`. Names are case-insensitive: `Key` variant names,
+    /// letters, digits, `f1`-`f24`, short names like `esc`, `pgup` or `left`,
+    /// and modifiers (`ctrl`, `shift`, `alt` and `win` mean the left key;
+    /// `rctrl`, `rshift`, `ralt`/`altgr` and `rwin` the right one).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Key::ALL
+            .iter()
+            .copied()
+            .find(|key| key.name().eq_ignore_ascii_case(s))
+            .or_else(|| key_alias(&s.to_ascii_lowercase()))
+            .ok_or_else(|| format!("unknown key {:?}", s))
+    }
+}
+
+// Python passes keys as ints, e.g. the `KEY_*` module constants.
+impl<'py> FromPyObject<'py> for Key {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        Ok(Key::try_from(ob.extract::<i32>()?)?)
+    }
+}
+
+impl IntoPy<PyObject> for Modifier {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        u8::from(self).into_py(py)
+    }
+}
+
+/// Add the `Key` IntEnum and a `KEY_*` constant for every key to a Python
+/// module, plus `KEY_0` to `KEY_9` for the digit row. `kmbox_net.pyi`
+/// mirrors what this adds; `tests/python_keys.rs` checks it.
+pub fn add_py_keys(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    let members: Vec<(String, u8)> = Key::ALL
+        .iter()
+        .map(|&key| (python_name(key.name()), key.into()))
+        .collect();
+    let kwargs = PyDict::new_bound(py);
+    kwargs.set_item("module", m.name()?)?;
+    let enum_ = py
+        .import_bound("enum")?
+        .getattr("IntEnum")?
+        .call(("Key", members), Some(&kwargs))?;
+    enum_.setattr(
+        "__doc__",
+        "HID keyboard usage codes, one per KEY_* constant.",
+    )?;
+    m.add("Key", enum_)?;
+
+    for &key in Key::ALL {
+        m.add(key.const_name(), u8::from(key))?;
+    }
+    for &key in Key::ALL {
+        if let Some(digit) = key.name().strip_prefix("Digit") {
+            m.add(format!("KEY_{}", digit).as_str(), u8::from(key))?;
+        }
+    }
+    Ok(())
+}
+
+/// `LeftControl` becomes `LEFT_CONTROL`, the Python enum member name.
+fn python_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut prev = ' ';
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && (prev.is_ascii_lowercase() || prev.is_ascii_digit()) {
+            out.push('_');
+        }
+        out.push(c.to_ascii_uppercase());
+        prev = c;
+    }
+    out
+}
+
+impl IntoPy<PyObject> for Key {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        u8::from(self).into_py(py)
+    }
+}
+
+/// The modifier keys. A keyboard report carries them as bits of `ctrl`
+/// instead of in the key slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Modifier {
+    LeftControl = KEY_LEFTCONTROL,
+    LeftShift = KEY_LEFTSHIFT,
+    LeftAlt = KEY_LEFTALT,
+    LeftGui = KEY_LEFT_GUI,
+    RightControl = KEY_RIGHTCONTROL,
+    RightShift = KEY_RIGHTSHIFT,
+    RightAlt = KEY_RIGHTALT,
+    RightGui = KEY_RIGHT_GUI,
+}
+
+impl Modifier {
+    /// Every modifier, in `ctrl` bit order.
+    pub const ALL: [Modifier; 8] = [
+        Modifier::LeftControl,
+        Modifier::LeftShift,
+        Modifier::LeftAlt,
+        Modifier::LeftGui,
+        Modifier::RightControl,
+        Modifier::RightShift,
+        Modifier::RightAlt,
+        Modifier::RightGui,
+    ];
+
+    /// This modifier's bit in the `ctrl` byte of a keyboard report.
+    pub const fn bit(self) -> u8 {
+        1 << (self as u8 - KEY_LEFTCONTROL)
+    }
+}
+
+impl From<Modifier> for Key {
+    fn from(modifier: Modifier) -> Key {
+        match modifier {
+            Modifier::LeftControl => Key::LeftControl,
+            Modifier::LeftShift => Key::LeftShift,
+            Modifier::LeftAlt => Key::LeftAlt,
+            Modifier::LeftGui => Key::LeftGui,
+            Modifier::RightControl => Key::RightControl,
+            Modifier::RightShift => Key::RightShift,
+            Modifier::RightAlt => Key::RightAlt,
+            Modifier::RightGui => Key::RightGui,
+        }
+    }
+}
+
+impl From<Modifier> for u8 {
+    fn from(modifier: Modifier) -> u8 {
+        modifier as u8
+    }
+}
+
+impl TryFrom<u8> for Modifier {
+    type Error = Error;
+
+    fn try_from(code: u8) -> Result<Self, Error> {
+        Key::try_from(code)?.modifier().ok_or_else(|| {
+            Error::from(KmError::NetCmd).with_detail(format!("{} is not a modifier", code))
+        })
+    }
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Key::from(*self).fmt(f)
+    }
+}
+
+impl FromStr for Modifier {
+    type Err = String;
+
+    /// Parse a modifier name as accepted by `Key::from_str`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Key>()?
+            .modifier()
+            .ok_or_else(|| format!("{:?} is not a modifier", s))
+    }
+}
+
+/// Short names for keys whose variant name is long or unusual. `name` is
+/// lowercase.
+fn key_alias(name: &str) -> Option<Key> {
+    match name.as_bytes() {
+        [c @ b'a'..=b'z'] => return Key::try_from(KEY_A + (c - b'a')).ok(),
+        [b'0'] => return Some(Key::Digit0),
+        [c @ b'1'..=b'9'] => return Key::try_from(KEY_1_EXCLAMATION_MARK + (c - b'1')).ok(),
+        _ => {}
+    }
+    let key = match name {
+        "ctrl" | "control" | "lctrl" => Key::LeftControl,
+        "shift" | "lshift" => Key::LeftShift,
+        "alt" | "lalt" => Key::LeftAlt,
+        "win" | "gui" | "meta" | "super" | "cmd" | "lwin" => Key::LeftGui,
+        "rctrl" => Key::RightControl,
+        "rshift" => Key::RightShift,
+        "ralt" | "altgr" => Key::RightAlt,
+        "rwin" => Key::RightGui,
+        "esc" => Key::Escape,
+        "spacebar" => Key::Space,
+        "lbracket" => Key::LeftBracket,
+        "rbracket" => Key::RightBracket,
+        "backtick" => Key::Grave,
+        "dot" => Key::Period,
+        "prtsc" => Key::PrintScreen,
+        "ins" => Key::Insert,
+        "pgup" => Key::PageUp,
+        "del" => Key::Delete,
+        "pgdn" => Key::PageDown,
+        "right" => Key::RightArrow,
+        "left" => Key::LeftArrow,
+        "down" => Key::DownArrow,
+        "up" => Key::UpArrow,
+        "app" => Key::Application,
+        _ => return None,
+    };
+    Some(key)
+}
@@ -0,0 +1,308 @@
+use crate::keys::*;
+use pyo3::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// `ctrl` bit of `KEY_LEFTSHIFT` in a keyboard report.
+pub const MOD_SHIFT: u8 = Modifier::LeftShift.bit();
+/// `ctrl` bit of `KEY_RIGHTALT`, which is AltGr on European layouts.
+pub const MOD_ALTGR: u8 = Modifier::RightAlt.bit();
+
+/// Keyboard layout configured on the host the box is plugged into.
+///
+/// The box sends HID usage codes, which name key positions on a US
+/// keyboard; the host's layout decides which character each position types.
+#[pyclass]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// US QWERTY.
+    #[default]
+    #[pyo3(name = "US")]
+    Us,
+    /// UK QWERTY.
+    #[pyo3(name = "UK")]
+    Uk,
+    /// German QWERTZ.
+    #[pyo3(name = "DE")]
+    De,
+    /// French AZERTY.
+    #[pyo3(name = "FR")]
+    Fr,
+}
+
+/// How to type one character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keystroke {
+    /// HID usage code of the key.
+    pub key: u8,
+    /// `MOD_SHIFT` and `MOD_ALTGR` bits to hold while pressing `key`.
+    pub modifiers: u8,
+    /// `key` is a dead key: the host waits for the next key to combine
+    /// it with, so a space must follow to type the character itself.
+    pub dead: bool,
+}
+
+impl Layout {
+    /// How to type `c` on this layout, or `None` if it has no key for it.
+    ///
+    /// `'\n'` is Enter and `'\t'` is Tab. Accented letters are supported
+    /// where the layout has a key of their own, not through dead keys.
+    pub fn keystroke(self, c: char) -> Option<Keystroke> {
+        let (key, modifiers) = match c {
+            'a'..='z' => (self.letter_key(c), 0),
+            'A'..='Z' => (self.letter_key(c.to_ascii_lowercase()), MOD_SHIFT),
+            '1'..='9' | '0' => {
+                let key = match c {
+                    '0' => KEY_0_CPARENTHESIS,
+                    _ => KEY_1_EXCLAMATION_MARK + (c as u8 - b'1'),
+                };
+                // AZERTY types digits with Shift.
+                (key, if self == Layout::Fr { MOD_SHIFT } else { 0 })
+            }
+            '\n' => (KEY_ENTER, 0),
+            '\t' => (KEY_TAB, 0),
+            ' ' => (KEY_SPACEBAR, 0),
+            _ => {
+                let &(_, key, modifiers) = self.symbols().iter().find(|s| s.0 == c)?;
+                (key, modifiers)
+            }
+        };
+        Some(Keystroke {
+            key,
+            modifiers,
+            dead: self.dead_keys().contains(&c),
+        })
+    }
+
+    fn letter_key(self, c: char) -> u8 {
+        let c = match (self, c) {
+            (Layout::De, 'y') => 'z',
+            (Layout::De, 'z') => 'y',
+            (Layout::Fr, 'a') => 'q',
+            (Layout::Fr, 'q') => 'a',
+            (Layout::Fr, 'z') => 'w',
+            (Layout::Fr, 'w') => 'z',
+            (Layout::Fr, 'm') => return KEY_SEMICOLON_COLON,
+            _ => c,
+        };
+        KEY_A + (c as u8 - b'a')
+    }
+
+    /// Characters other than letters, digits and whitespace.
+    fn symbols(self) -> &'static [(char, u8, u8)] {
+        match self {
+            Layout::Us => US,
+            Layout::Uk => UK,
+            Layout::De => DE,
+            Layout::Fr => FR,
+        }
+    }
+
+    fn dead_keys(self) -> &'static [char] {
+        match self {
+            Layout::Us | Layout::Uk => &[],
+            Layout::De => &['^', '´', '`'],
+            Layout::Fr => &['~', '`', '¨'],
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Layout::Us => "US",
+            Layout::Uk => "UK",
+            Layout::De => "DE",
+            Layout::Fr => "FR",
+        })
+    }
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    /// Parse `us`, `uk`, `de` or `fr`, in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "us" => Ok(Layout::Us),
+            "uk" | "gb" => Ok(Layout::Uk),
+            "de" => Ok(Layout::De),
+            "fr" => Ok(Layout::Fr),
+            _ => Err(format!("unknown layout {:?}", s)),
+        }
+    }
+}
+
+const S: u8 = MOD_SHIFT;
+const ALTGR: u8 = MOD_ALTGR;
+
+const US: &[(char, u8, u8)] = &[
+    ('!', KEY_1_EXCLAMATION_MARK, S),
+    ('@', KEY_2_AT, S),
+    ('#', KEY_3_NUMBER_SIGN, S),
+    ('$', KEY_4_DOLLAR, S),
+    ('%', KEY_5_PERCENT, S),
+    ('^', KEY_6_CARET, S),
+    ('&', KEY_7_AMPERSAND, S),
+    ('*', KEY_8_ASTERISK, S),
+    ('(', KEY_9_OPARENTHESIS, S),
+    (')', KEY_0_CPARENTHESIS, S),
+    ('-', KEY_MINUS_UNDERSCORE, 0),
+    ('_', KEY_MINUS_UNDERSCORE, S),
+    ('=', KEY_EQUAL_PLUS, 0),
+    ('+', KEY_EQUAL_PLUS, S),
+    ('[', KEY_OBRACKET_AND_OBRACE, 0),
+    ('{', KEY_OBRACKET_AND_OBRACE, S),
+    (']', KEY_CBRACKET_AND_CBRACE, 0),
+    ('}', KEY_CBRACKET_AND_CBRACE, S),
+    ('\\', KEY_BACKSLASH_VERTICAL_BAR, 0),
+    ('|', KEY_BACKSLASH_VERTICAL_BAR, S),
+    (';', KEY_SEMICOLON_COLON, 0),
+    (':', KEY_SEMICOLON_COLON, S),
+    ('\'', KEY_SINGLE_AND_DOUBLE_QUOTE, 0),
+    ('"', KEY_SINGLE_AND_DOUBLE_QUOTE, S),
+    ('`', KEY_GRAVE_ACCENT_AND_TILDE, 0),
+    ('~', KEY_GRAVE_ACCENT_AND_TILDE, S),
+    (',', KEY_COMMA_AND_LESS, 0),
+    ('<', KEY_COMMA_AND_LESS, S),
+    ('.', KEY_DOT_GREATER, 0),
+    ('>', KEY_DOT_GREATER, S),
+    ('/', KEY_SLASH_QUESTION, 0),
+    ('?', KEY_SLASH_QUESTION, S),
+];
+
+const UK: &[(char, u8, u8)] = &[
+    ('!', KEY_1_EXCLAMATION_MARK, S),
+    ('"', KEY_2_AT, S),
+    ('£', KEY_3_NUMBER_SIGN, S),
+    ('$', KEY_4_DOLLAR, S),
+    ('€', KEY_4_DOLLAR, ALTGR),
+    ('%', KEY_5_PERCENT, S),
+    ('^', KEY_6_CARET, S),
+    ('&', KEY_7_AMPERSAND, S),
+    ('*', KEY_8_ASTERISK, S),
+    ('(', KEY_9_OPARENTHESIS, S),
+    (')', KEY_0_CPARENTHESIS, S),
+    ('-', KEY_MINUS_UNDERSCORE, 0),
+    ('_', KEY_MINUS_UNDERSCORE, S),
+    ('=', KEY_EQUAL_PLUS, 0),
+    ('+', KEY_EQUAL_PLUS, S),
+    ('[', KEY_OBRACKET_AND_OBRACE, 0),
+    ('{', KEY_OBRACKET_AND_OBRACE, S),
+    (']', KEY_CBRACKET_AND_CBRACE, 0),
+    ('}', KEY_CBRACKET_AND_CBRACE, S),
+    ('#', KEY_NONUS_NUMBER_SIGN_TILDE, 0),
+    ('~', KEY_NONUS_NUMBER_SIGN_TILDE, S),
+    (';', KEY_SEMICOLON_COLON, 0),
+    (':', KEY_SEMICOLON_COLON, S),
+    ('\'', KEY_SINGLE_AND_DOUBLE_QUOTE, 0),
+    ('@', KEY_SINGLE_AND_DOUBLE_QUOTE, S),
+    ('`', KEY_GRAVE_ACCENT_AND_TILDE, 0),
+    ('¬', KEY_GRAVE_ACCENT_AND_TILDE, S),
+    ('¦', KEY_GRAVE_ACCENT_AND_TILDE, ALTGR),
+    ('\\', KEY_NONUS_BACK_SLASH_VERTICAL_BAR, 0),
+    ('|', KEY_NONUS_BACK_SLASH_VERTICAL_BAR, S),
+    (',', KEY_COMMA_AND_LESS, 0),
+    ('<', KEY_COMMA_AND_LESS, S),
+    ('.', KEY_DOT_GREATER, 0),
+    ('>', KEY_DOT_GREATER, S),
+    ('/', KEY_SLASH_QUESTION, 0),
+    ('?', KEY_SLASH_QUESTION, S),
+];
+
+const DE: &[(char, u8, u8)] = &[
+    ('^', KEY_GRAVE_ACCENT_AND_TILDE, 0),
+    ('°', KEY_GRAVE_ACCENT_AND_TILDE, S),
+    ('!', KEY_1_EXCLAMATION_MARK, S),
+    ('"', KEY_2_AT, S),
+    ('²', KEY_2_AT, ALTGR),
+    ('§', KEY_3_NUMBER_SIGN, S),
+    ('³', KEY_3_NUMBER_SIGN, ALTGR),
+    ('$', KEY_4_DOLLAR, S),
+    ('%', KEY_5_PERCENT, S),
+    ('&', KEY_6_CARET, S),
+    ('/', KEY_7_AMPERSAND, S),
+    ('{', KEY_7_AMPERSAND, ALTGR),
+    ('(', KEY_8_ASTERISK, S),
+    ('[', KEY_8_ASTERISK, ALTGR),
+    (')', KEY_9_OPARENTHESIS, S),
+    (']', KEY_9_OPARENTHESIS, ALTGR),
+    ('=', KEY_0_CPARENTHESIS, S),
+    ('}', KEY_0_CPARENTHESIS, ALTGR),
+    ('ß', KEY_MINUS_UNDERSCORE, 0),
+    ('?', KEY_MINUS_UNDERSCORE, S),
+    ('\\', KEY_MINUS_UNDERSCORE, ALTGR),
+    ('´', KEY_EQUAL_PLUS, 0),
+    ('`', KEY_EQUAL_PLUS, S),
+    ('@', KEY_Q, ALTGR),
+    ('€', KEY_E, ALTGR),
+    ('µ', KEY_M, ALTGR),
+    ('ü', KEY_OBRACKET_AND_OBRACE, 0),
+    ('Ü', KEY_OBRACKET_AND_OBRACE, S),
+    ('+', KEY_CBRACKET_AND_CBRACE, 0),
+    ('*', KEY_CBRACKET_AND_CBRACE, S),
+    ('~', KEY_CBRACKET_AND_CBRACE, ALTGR),
+    ('ö', KEY_SEMICOLON_COLON, 0),
+    ('Ö', KEY_SEMICOLON_COLON, S),
+    ('ä', KEY_SINGLE_AND_DOUBLE_QUOTE, 0),
+    ('Ä', KEY_SINGLE_AND_DOUBLE_QUOTE, S),
+    ('#', KEY_NONUS_NUMBER_SIGN_TILDE, 0),
+    ('\'', KEY_NONUS_NUMBER_SIGN_TILDE, S),
+    ('<', KEY_NONUS_BACK_SLASH_VERTICAL_BAR, 0),
+    ('>', KEY_NONUS_BACK_SLASH_VERTICAL_BAR, S),
+    ('|', KEY_NONUS_BACK_SLASH_VERTICAL_BAR, ALTGR),
+    (',', KEY_COMMA_AND_LESS, 0),
+    (';', KEY_COMMA_AND_LESS, S),
+    ('.', KEY_DOT_GREATER, 0),
+    (':', KEY_DOT_GREATER, S),
+    ('-', KEY_SLASH_QUESTION, 0),
+    ('_', KEY_SLASH_QUESTION, S),
+];
+
+const FR: &[(char, u8, u8)] = &[
+    ('²', KEY_GRAVE_ACCENT_AND_TILDE, 0),
+    ('&', KEY_1_EXCLAMATION_MARK, 0),
+    ('é', KEY_2_AT, 0),
+    ('~', KEY_2_AT, ALTGR),
+    ('"', KEY_3_NUMBER_SIGN, 0),
+    ('#', KEY_3_NUMBER_SIGN, ALTGR),
+    ('\'', KEY_4_DOLLAR, 0),
+    ('{', KEY_4_DOLLAR, ALTGR),
+    ('(', KEY_5_PERCENT, 0),
+    ('[', KEY_5_PERCENT, ALTGR),
+    ('-', KEY_6_CARET, 0),
+    ('|', KEY_6_CARET, ALTGR),
+    ('è', KEY_7_AMPERSAND, 0),
+    ('`', KEY_7_AMPERSAND, ALTGR),
+    ('_', KEY_8_ASTERISK, 0),
+    ('\\', KEY_8_ASTERISK, ALTGR),
+    ('ç', KEY_9_OPARENTHESIS, 0),
+    ('^', KEY_9_OPARENTHESIS, ALTGR),
+    ('à', KEY_0_CPARENTHESIS, 0),
+    ('@', KEY_0_CPARENTHESIS, ALTGR),
+    (')', KEY_MINUS_UNDERSCORE, 0),
+    ('°', KEY_MINUS_UNDERSCORE, S),
+    (']', KEY_MINUS_UNDERSCORE, ALTGR),
+    ('=', KEY_EQUAL_PLUS, 0),
+    ('+', KEY_EQUAL_PLUS, S),
+    ('}', KEY_EQUAL_PLUS, ALTGR),
+    ('€', KEY_E, ALTGR),
+    ('¨', KEY_OBRACKET_AND_OBRACE, S),
+    ('$', KEY_CBRACKET_AND_CBRACE, 0),
+    ('£', KEY_CBRACKET_AND_CBRACE, S),
+    ('¤', KEY_CBRACKET_AND_CBRACE, ALTGR),
+    ('ù', KEY_SINGLE_AND_DOUBLE_QUOTE, 0),
+    ('%', KEY_SINGLE_AND_DOUBLE_QUOTE, S),
+    ('*', KEY_NONUS_NUMBER_SIGN_TILDE, 0),
+    ('µ', KEY_NONUS_NUMBER_SIGN_TILDE, S),
+    ('<', KEY_NONUS_BACK_SLASH_VERTICAL_BAR, 0),
+    ('>', KEY_NONUS_BACK_SLASH_VERTICAL_BAR, S),
+    (',', KEY_M, 0),
+    ('?', KEY_M, S),
+    (';', KEY_COMMA_AND_LESS, 0),
+    ('.', KEY_COMMA_AND_LESS, S),
+    (':', KEY_DOT_GREATER, 0),
+    ('/', KEY_DOT_GREATER, S),
+    ('!', KEY_SLASH_QUESTION, 0),
+    ('§', KEY_SLASH_QUESTION, S),
+];
@@ -0,0 +1,179 @@
+use crate::client_shared::{PySharedKmBoxNetClient, SharedKmBoxNetClient};
+use crate::client_stats::{cmd_label, ClientStats};
+use crate::error::Error;
+use crate::protocol::KmError;
+use pyo3::prelude::*;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+impl ClientStats {
+    /// Render the counters in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        counter_header(&mut out, "kmbox_commands_total", "Commands sent.");
+        for (&cmd, count) in &self.commands {
+            let _ = writeln!(
+                out,
+                "kmbox_commands_total{{cmd=\"{}\"}} {}",
+                cmd_label(cmd),
+                count
+            );
+        }
+        for (name, help, value) in [
+            (
+                "kmbox_encrypted_commands_total",
+                "Commands sent encrypted.",
+                self.encrypted,
+            ),
+            (
+                "kmbox_plain_commands_total",
+                "Commands sent in plain text.",
+                self.plain,
+            ),
+            (
+                "kmbox_bytes_sent_total",
+                "Bytes sent, resends included.",
+                self.bytes_sent,
+            ),
+            ("kmbox_acks_total", "Acks received.", self.acks),
+            (
+                "kmbox_timeouts_total",
+                "Ack waits that timed out.",
+                self.timeouts,
+            ),
+            ("kmbox_retries_total", "Commands resent.", self.retries),
+        ] {
+            counter_header(&mut out, name, help);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out
+    }
+}
+
+#[pymethods]
+impl ClientStats {
+    #[pyo3(name = "to_prometheus")]
+    fn py_to_prometheus(&self) -> String {
+        self.to_prometheus()
+    }
+}
+
+fn counter_header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+}
+
+/// Minimal HTTP endpoint that serves the stats of a client to Prometheus.
+///
+/// Every request, whatever its path, gets `ClientStats::to_prometheus`.
+/// Stops when dropped.
+#[pyclass]
+pub struct MetricsServer {
+    addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// Listen on `addr`, e.g. `"0.0.0.0:9464"`.
+    pub fn start(addr: &str, client: SharedKmBoxNetClient) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr).map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        // Polled so that `stop` is noticed without another connection.
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = running.clone();
+        let handle = thread::spawn(move || {
+            while running_clone.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let body = client.stats().to_prometheus();
+                        // A scraper that hangs up early is not our problem.
+                        let _ = respond(stream, &body);
+                    }
+                    Err(_) => thread::sleep(Duration::from_millis(50)),
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stop the server thread.
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn respond(mut stream: TcpStream, body: &str) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_millis(500)))?;
+    // The request itself does not matter; read it so the client sees a clean close.
+    let mut buf = [0u8; 1024];
+    let _ = stream.read(&mut buf);
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+#[pymethods]
+impl MetricsServer {
+    #[getter]
+    fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    #[pyo3(name = "stop")]
+    fn py_stop(&mut self, py: Python<'_>) {
+        py.allow_threads(|| self.stop());
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl SharedKmBoxNetClient {
+    /// Serve `stats()` to Prometheus on `addr`. See `MetricsServer`.
+    pub fn serve_metrics(&self, addr: &str) -> Result<MetricsServer, Error> {
+        MetricsServer::start(addr, self.clone())
+    }
+}
+
+#[pymethods]
+impl PySharedKmBoxNetClient {
+    #[pyo3(signature = (addr = "0.0.0.0:9464"))]
+    fn serve_metrics(&self, addr: &str) -> PyResult<MetricsServer> {
+        self.0.serve_metrics(addr).map_err(Into::into)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::keys::{Key, Modifier, KEY_LEFTCONTROL, KEY_RIGHT_GUI};
 use pyo3::prelude::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -9,10 +10,18 @@ pub enum KmError {
     NetRxTimeout = -8997,
     NetCmd = -8996,
     NetPts = -8995,
+    NetAckMismatch = -8994,
     Success = 0,
     UsbDevTxTimeout = 1,
 }
 
+impl KmError {
+    /// The numeric code used by the C SDK.
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
 impl std::fmt::Display for KmError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -22,6 +31,7 @@ impl std::fmt::Display for KmError {
             KmError::NetRxTimeout => write!(f, "Network receive timeout"),
             KmError::NetCmd => write!(f, "Network command error"),
             KmError::NetPts => write!(f, "Network PTS error"),
+            KmError::NetAckMismatch => write!(f, "Acknowledgement does not match the command"),
             KmError::Success => write!(f, "Success"),
             KmError::UsbDevTxTimeout => write!(f, "USB device transmission timeout"),
         }
@@ -32,14 +42,14 @@ impl std::error::Error for KmError {}
 
 impl From<KmError> for PyErr {
     fn from(err: KmError) -> Self {
-        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(err.to_string())
+        crate::error::Error::from(err).into()
     }
 }
 
 pub const CMD_CONNECT: u32 = 0xaf3c2828;
 pub const CMD_MOUSE_MOVE: u32 = 0xaede7345;
 pub const CMD_MOUSE_LEFT: u32 = 0x9823AE8D;
-pub const CMD_MOUSE_MIDDLE: u32 = 0x97a3AE8D;
+pub const CMD_MOUSE_MIDDLE: u32 = 0x97A3AE8D;
 pub const CMD_MOUSE_RIGHT: u32 = 0x238d8212;
 pub const CMD_MOUSE_WHEEL: u32 = 0xffeead38;
 pub const CMD_MOUSE_AUTOMOVE: u32 = 0xaede7346;
@@ -55,6 +65,31 @@ pub const CMD_SETVIDPID: u32 = 0xffed3232;
 pub const CMD_SHOWPIC: u32 = 0x12334883;
 pub const CMD_TRACE_ENABLE: u32 = 0xbbcdddac;
 
+/// Name of a `CMD_*` constant, or `"UNKNOWN"` for ids the SDK does not define.
+pub fn cmd_name(cmd: u32) -> &'static str {
+    match cmd {
+        CMD_CONNECT => "CMD_CONNECT",
+        CMD_MOUSE_MOVE => "CMD_MOUSE_MOVE",
+        CMD_MOUSE_LEFT => "CMD_MOUSE_LEFT",
+        CMD_MOUSE_MIDDLE => "CMD_MOUSE_MIDDLE",
+        CMD_MOUSE_RIGHT => "CMD_MOUSE_RIGHT",
+        CMD_MOUSE_WHEEL => "CMD_MOUSE_WHEEL",
+        CMD_MOUSE_AUTOMOVE => "CMD_MOUSE_AUTOMOVE",
+        CMD_KEYBOARD_ALL => "CMD_KEYBOARD_ALL",
+        CMD_REBOOT => "CMD_REBOOT",
+        CMD_BAZER_MOVE => "CMD_BAZER_MOVE",
+        CMD_MONITOR => "CMD_MONITOR",
+        CMD_DEBUG => "CMD_DEBUG",
+        CMD_MASK_MOUSE => "CMD_MASK_MOUSE",
+        CMD_UNMASK_ALL => "CMD_UNMASK_ALL",
+        CMD_SETCONFIG => "CMD_SETCONFIG",
+        CMD_SETVIDPID => "CMD_SETVIDPID",
+        CMD_SHOWPIC => "CMD_SHOWPIC",
+        CMD_TRACE_ENABLE => "CMD_TRACE_ENABLE",
+        _ => "UNKNOWN",
+    }
+}
+
 #[derive(Debug, Copy, Clone, Default)]
 #[repr(C)]
 pub struct CmdHead {
@@ -64,7 +99,7 @@ pub struct CmdHead {
     pub cmd: u32,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 #[repr(C)]
 pub struct SoftMouse {
     pub button: i32,
@@ -74,19 +109,7 @@ pub struct SoftMouse {
     pub point: [i32; 10],
 }
 
-impl Default for SoftMouse {
-    fn default() -> Self {
-        Self {
-            button: 0,
-            x: 0,
-            y: 0,
-            wheel: 0,
-            point: [0; 10],
-        }
-    }
-}
-
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 #[repr(C)]
 pub struct SoftKeyboard {
     pub ctrl: u8,
@@ -94,12 +117,67 @@ pub struct SoftKeyboard {
     pub button: [u8; 10],
 }
 
-impl Default for SoftKeyboard {
-    fn default() -> Self {
-        Self {
-            ctrl: 0,
-            resvel: 0,
-            button: [0; 10],
+impl SoftMouse {
+    /// Set or clear the `button` bits in `mask`.
+    pub(crate) fn set_button(&mut self, mask: i32, is_down: bool) {
+        if is_down {
+            self.button |= mask;
+        } else {
+            self.button &= !mask;
+        }
+    }
+}
+
+impl SoftKeyboard {
+    /// Add `vkey` to the report. Modifiers (0xE0 - 0xE7) set their bit in
+    /// `ctrl`; other keys take the first free slot, and when all 10 slots
+    /// are taken the oldest key is dropped.
+    pub(crate) fn press(&mut self, vkey: u8) {
+        if (KEY_LEFTCONTROL..=KEY_RIGHT_GUI).contains(&vkey) {
+            self.ctrl |= 1 << (vkey - KEY_LEFTCONTROL);
+        } else if !self.button.contains(&vkey) {
+            match self.button.iter().position(|&k| k == 0) {
+                Some(i) => self.button[i] = vkey,
+                None => {
+                    self.button.copy_within(1..10, 0);
+                    self.button[9] = vkey;
+                }
+            }
+        }
+    }
+
+    /// Remove `vkey` from the report, shifting later keys left so the
+    /// pressed keys stay contiguous.
+    pub(crate) fn release(&mut self, vkey: u8) {
+        if (KEY_LEFTCONTROL..=KEY_RIGHT_GUI).contains(&vkey) {
+            self.ctrl &= !(1 << (vkey - KEY_LEFTCONTROL));
+        } else if let Some(i) = self.button.iter().position(|&k| k == vkey) {
+            self.button.copy_within((i + 1)..10, i);
+            self.button[9] = 0;
+        }
+    }
+
+    /// The keys in the slots, in the order they were pressed.
+    pub fn pressed_keys(&self) -> Vec<Key> {
+        self.button
+            .iter()
+            .filter_map(|&k| Key::try_from(k).ok())
+            .collect()
+    }
+
+    /// The modifiers whose bit is set in `ctrl`.
+    pub fn pressed_modifiers(&self) -> Vec<Modifier> {
+        Modifier::ALL
+            .into_iter()
+            .filter(|m| self.ctrl & m.bit() != 0)
+            .collect()
+    }
+
+    /// Whether `key` is held: its `ctrl` bit for a modifier, otherwise a slot.
+    pub fn is_pressed(&self, key: Key) -> bool {
+        match key.modifier() {
+            Some(modifier) => self.ctrl & modifier.bit() != 0,
+            None => self.button.contains(&key.into()),
         }
     }
 }
@@ -125,22 +203,3 @@ pub struct HardKeyboard {
     #[pyo3(get)]
     pub data: Vec<u8>,
 }
-
-// Structure to match the union layout for serialization/encryption
-#[repr(C)]
-pub struct ClientTx {
-    pub head: CmdHead,
-    // Buffer large enough to hold the largest union member + padding if necessary
-    // C++ uses a union with char buff[1024].
-    // We use a fixed size array here.
-    pub data: [u8; 1024],
-}
-
-impl Default for ClientTx {
-    fn default() -> Self {
-        Self {
-            head: CmdHead::default(),
-            data: [0; 1024],
-        }
-    }
-}
@@ -0,0 +1,402 @@
+use crate::client::{key_from_mac, str_to_hex};
+use crate::codec::{Decode, Encode, MonitorReport, Packet, HEAD_LEN};
+use crate::encryption::xxtea_decrypt;
+use crate::error::Error;
+use crate::protocol::*;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+pub const LCD_WIDTH: usize = 128;
+pub const LCD_HEIGHT: usize = 160;
+
+/// Size of every encrypted datagram (see `send_command_encrypted_with_rand`).
+const ENCRYPTED_LEN: usize = 128;
+
+/// A command as received by the simulator, after decryption.
+#[derive(Debug, Clone)]
+pub struct SimCommand {
+    pub head: CmdHead,
+    pub encrypted: bool,
+    pub payload: Vec<u8>,
+}
+
+/// Mouse as seen by the virtual host.
+/// `x`, `y` and `wheel` accumulate every relative report since start.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SimMouse {
+    pub buttons: i32,
+    pub x: i64,
+    pub y: i64,
+    pub wheel: i64,
+    /// The last mouse report, including bezier control points.
+    pub last: SoftMouse,
+}
+
+/// Snapshot of everything the simulated device has been told.
+#[derive(Debug, Clone)]
+pub struct SimState {
+    pub connected: bool,
+    pub mouse: SimMouse,
+    pub keyboard: SoftKeyboard,
+    /// 128x160 RGB565 framebuffer, row-major.
+    pub lcd: Vec<u16>,
+    pub monitor_port: u16,
+    pub mask_mouse: u8,
+    pub masked_keys: Vec<u8>,
+    pub trace_type: i32,
+    pub trace_value: i32,
+    pub vid: u16,
+    pub pid: u16,
+    pub config_ip: Option<Ipv4Addr>,
+    pub config_port: u16,
+    pub reboots: u32,
+    pub commands: Vec<SimCommand>,
+    /// Resent commands that were acked but not applied again.
+    pub retransmissions: u32,
+    peer: Option<SocketAddr>,
+    /// Header of the last applied command, kept apart from `commands` so
+    /// resends are detected after `take_commands`.
+    last: Option<CmdHead>,
+    acks_to_drop: u32,
+}
+
+impl Default for SimState {
+    fn default() -> Self {
+        Self {
+            connected: false,
+            mouse: SimMouse::default(),
+            keyboard: SoftKeyboard::default(),
+            lcd: vec![0; LCD_WIDTH * LCD_HEIGHT],
+            monitor_port: 0,
+            mask_mouse: 0,
+            masked_keys: Vec::new(),
+            trace_type: 0,
+            trace_value: 0,
+            vid: 0,
+            pid: 0,
+            config_ip: None,
+            config_port: 0,
+            reboots: 0,
+            commands: Vec::new(),
+            retransmissions: 0,
+            peer: None,
+            last: None,
+            acks_to_drop: 0,
+        }
+    }
+}
+
+impl SimState {
+    /// RGB565 pixel at column `x`, row `y`.
+    pub fn pixel(&self, x: usize, y: usize) -> u16 {
+        self.lcd[y * LCD_WIDTH + x]
+    }
+
+    fn apply(&mut self, packet: &Packet) {
+        match packet {
+            Packet::Connect { .. } => {
+                self.connected = true;
+            }
+            Packet::Mouse { mouse, .. } => {
+                self.mouse.buttons = mouse.button;
+                self.mouse.x += mouse.x as i64;
+                self.mouse.y += mouse.y as i64;
+                self.mouse.wheel += mouse.wheel as i64;
+                self.mouse.last = *mouse;
+            }
+            Packet::Keyboard { keyboard, .. } => {
+                self.keyboard = *keyboard;
+            }
+            Packet::Monitor { port, .. } => {
+                self.monitor_port = port.unwrap_or(0);
+            }
+            Packet::MaskMouse { flags, vkey, .. } => {
+                self.mask_mouse = *flags;
+                if *vkey != 0 && !self.masked_keys.contains(vkey) {
+                    self.masked_keys.push(*vkey);
+                }
+            }
+            Packet::Unmask { head, flags, vkey } => {
+                if head.rand == 0 {
+                    self.mask_mouse = 0;
+                    self.masked_keys.clear();
+                } else {
+                    self.mask_mouse = *flags;
+                    self.masked_keys.retain(|k| k != vkey);
+                }
+            }
+            Packet::SetConfig { ip, port, .. } => {
+                self.config_ip = Some(*ip);
+                self.config_port = *port;
+            }
+            Packet::SetVidPid { vid, pid, .. } => {
+                self.vid = *vid;
+                self.pid = *pid;
+            }
+            Packet::TraceEnable { type_, value, .. } => {
+                self.trace_type = *type_;
+                self.trace_value = *value;
+            }
+            Packet::ShowPic { row, pixels, .. } => {
+                // Each packet carries 4 rows starting at `row`.
+                let start = *row as usize * LCD_WIDTH;
+                for (i, px) in pixels.chunks_exact(2).take(LCD_WIDTH * 4).enumerate() {
+                    if let Some(slot) = self.lcd.get_mut(start + i) {
+                        *slot = u16::from_le_bytes([px[0], px[1]]);
+                    }
+                }
+            }
+            Packet::Reboot { .. } => {
+                self.reboots += 1;
+                self.connected = false;
+            }
+            Packet::Unknown { .. } => {}
+        }
+    }
+}
+
+/// How the simulator answers commands, for exercising client error paths.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum AckBehavior {
+    /// Echo the command head back to the sender, like the box.
+    #[default]
+    Echo,
+    /// Send an ack for the previous `indexpts` before the real one,
+    /// as if a late ack from an earlier command was still in flight.
+    StaleFirst,
+    /// Send the ack from a different socket before the real one.
+    ForeignFirst,
+    /// Ack with the right `indexpts` but the wrong `cmd`.
+    WrongCmd,
+    /// Never ack, like a box that is offline.
+    Silent,
+    /// Echo after a delay, like a slow link.
+    Delayed(Duration),
+}
+
+/// Local stand-in for a KmBoxNet device, for testing without hardware.
+///
+/// Speaks the same UDP protocol as the box: every command is acknowledged by
+/// echoing its `CmdHead`, and encrypted commands are decrypted with the key
+/// derived from the MAC. The resulting virtual device state can be read back
+/// with `state()`.
+pub struct KmBoxNetSimulator {
+    socket: UdpSocket,
+    state: Arc<Mutex<SimState>>,
+    ack: Arc<Mutex<AckBehavior>>,
+    running: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl KmBoxNetSimulator {
+    /// Start the simulator on `addr` (e.g. `"127.0.0.1:0"` for an ephemeral port).
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The local address to bind.
+    /// * `mac_str` - The device MAC; commands carrying another MAC are ignored.
+    pub fn start(addr: &str, mac_str: &str) -> Result<Self, Error> {
+        let socket = UdpSocket::bind(addr).map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        socket
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        let worker = socket
+            .try_clone()
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
+        let local_ip = socket
+            .local_addr()
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?
+            .ip();
+        let foreign = UdpSocket::bind(SocketAddr::new(local_ip, 0))
+            .map_err(|e| Error::io(KmError::CreatSocket, e))?;
+
+        let mac = str_to_hex(mac_str);
+        let key = key_from_mac(mac);
+
+        let state = Arc::new(Mutex::new(SimState::default()));
+        let ack = Arc::new(Mutex::new(AckBehavior::default()));
+        let running = Arc::new(AtomicBool::new(true));
+        let state_clone = state.clone();
+        let ack_clone = ack.clone();
+        let running_clone = running.clone();
+
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            while running_clone.load(Ordering::Relaxed) {
+                let (len, src) = match worker.recv_from(&mut buf) {
+                    Ok(received) => received,
+                    // Timeout allows checking running flag
+                    Err(_) => continue,
+                };
+                if len < HEAD_LEN {
+                    continue;
+                }
+
+                // Encrypted commands are always exactly one 128-byte block;
+                // no plain command has that length.
+                let encrypted = len == ENCRYPTED_LEN;
+                if encrypted {
+                    xxtea_decrypt(&mut buf[..len], &key);
+                }
+
+                let head = match CmdHead::decode(&buf[..len]) {
+                    Some(head) if head.mac == mac => head,
+                    _ => continue,
+                };
+
+                let command = SimCommand {
+                    head,
+                    encrypted,
+                    payload: buf[HEAD_LEN..len].to_vec(),
+                };
+                let drop_ack = {
+                    let mut state = lock(&state_clone);
+                    state.peer = Some(src);
+                    // A resend of the last command (same indexpts) is only re-acked.
+                    if state
+                        .last
+                        .is_some_and(|l| l.indexpts == head.indexpts && l.cmd == head.cmd)
+                    {
+                        state.retransmissions += 1;
+                    } else {
+                        if let Some(packet) = Packet::parse(&buf[..len]) {
+                            state.apply(&packet);
+                        }
+                        state.last = Some(head);
+                        state.commands.push(command);
+                    }
+                    if state.acks_to_drop > 0 {
+                        state.acks_to_drop -= 1;
+                        true
+                    } else {
+                        false
+                    }
+                };
+                if drop_ack {
+                    continue;
+                }
+
+                let behavior = *ack_clone.lock().unwrap_or_else(|e| e.into_inner());
+                let mut reply = head;
+                match behavior {
+                    AckBehavior::Echo => {}
+                    AckBehavior::StaleFirst => {
+                        let stale = CmdHead {
+                            indexpts: head.indexpts.wrapping_sub(1),
+                            ..head
+                        };
+                        let _ = worker.send_to(&stale.to_bytes(), src);
+                    }
+                    AckBehavior::ForeignFirst => {
+                        let _ = foreign.send_to(&head.to_bytes(), src);
+                    }
+                    AckBehavior::WrongCmd => {
+                        reply.cmd = !head.cmd;
+                    }
+                    AckBehavior::Silent => continue,
+                    AckBehavior::Delayed(delay) => thread::sleep(delay),
+                }
+                let _ = worker.send_to(&reply.to_bytes(), src);
+            }
+        });
+
+        Ok(Self {
+            socket,
+            state,
+            ack,
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    /// The address the simulator is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.socket
+            .local_addr()
+            .expect("bound socket has an address")
+    }
+
+    /// The UDP port the simulator is listening on.
+    pub fn port(&self) -> u16 {
+        self.local_addr().port()
+    }
+
+    /// Snapshot of the current device state.
+    pub fn state(&self) -> SimState {
+        lock(&self.state).clone()
+    }
+
+    /// Remove and return the commands recorded so far. A long-running
+    /// simulator should call this regularly, as `commands` otherwise keeps
+    /// growing.
+    pub fn take_commands(&self) -> Vec<SimCommand> {
+        std::mem::take(&mut lock(&self.state).commands)
+    }
+
+    /// Change how subsequent commands are acknowledged.
+    pub fn set_ack_behavior(&self, behavior: AckBehavior) {
+        *self.ack.lock().unwrap_or_else(|e| e.into_inner()) = behavior;
+    }
+
+    /// Silently drop the acks for the next `count` datagrams, as if they
+    /// were lost on the network.
+    pub fn drop_acks(&self, count: u32) {
+        lock(&self.state).acks_to_drop = count;
+    }
+
+    /// Forget every recorded command and reset the virtual device.
+    pub fn reset(&self) {
+        *lock(&self.state) = SimState::default();
+    }
+
+    /// Push a physical input report to the client's monitor port, like the box
+    /// does after `monitor(port)`. Fails with `NetCmd` if monitoring is disabled.
+    pub fn send_monitor_report(
+        &self,
+        mouse: &HardMouse,
+        keyboard: &HardKeyboard,
+    ) -> Result<(), Error> {
+        let (peer, port) = {
+            let state = lock(&self.state);
+            (state.peer, state.monitor_port)
+        };
+        let peer = match peer {
+            Some(peer) if port != 0 => peer,
+            _ => return Err(KmError::NetCmd.into()),
+        };
+
+        let report = MonitorReport {
+            mouse: *mouse,
+            keyboard: keyboard.clone(),
+        };
+
+        self.socket
+            .send_to(&report.to_bytes(), SocketAddr::new(peer.ip(), port))
+            .map_err(|e| Error::io(KmError::NetTx, e))?;
+        Ok(())
+    }
+
+    /// Stop the simulator thread.
+    pub fn stop(&mut self) {
+        if self.running.load(Ordering::Relaxed) {
+            self.running.store(false, Ordering::Relaxed);
+            if let Some(handle) = self.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+impl Drop for KmBoxNetSimulator {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// A panicking test must not poison the state for the rest of the run.
+fn lock(state: &Mutex<SimState>) -> MutexGuard<'_, SimState> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
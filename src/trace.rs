@@ -0,0 +1,130 @@
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::fmt::{self, Write as _};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// How many payload bytes `payload_summary` shows.
+const SUMMARY_BYTES: usize = 16;
+
+/// Length and leading bytes of a payload, e.g. `56 bytes: 01000000 05000000…`.
+pub(crate) fn payload_summary(payload: &[u8]) -> String {
+    let mut out = format!("{} bytes", payload.len());
+    if payload.is_empty() {
+        return out;
+    }
+    out.push(':');
+    for chunk in payload.chunks(4).take(SUMMARY_BYTES / 4) {
+        out.push(' ');
+        for b in chunk {
+            let _ = write!(out, "{:02x}", b);
+        }
+    }
+    if payload.len() > SUMMARY_BYTES {
+        out.push('…');
+    }
+    out
+}
+
+/// Forwards events to the Python logger `kmbox_net`.
+///
+/// The message is the event's `message` followed by its other fields as
+/// `key=value`, prefixed with the names of the enclosing spans, e.g.
+/// `lcd_picture{chunks=40}: send cmd=CMD_SHOWPIC indexpts=7 …`.
+struct PyLoggingLayer;
+
+impl<S> Layer<S> for PyLoggingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        id: &tracing::span::Id,
+        ctx: Context<'_, S>,
+    ) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut message = String::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                message.push_str(span.name());
+                if let Some(fields) = span.extensions().get::<Fields>() {
+                    if !fields.0.is_empty() {
+                        let _ = write!(message, "{{{}}}", fields.0.trim_start());
+                    }
+                }
+                message.push_str(": ");
+            }
+        }
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        message.push_str(fields.0.trim_start());
+
+        let level = match *event.metadata().level() {
+            Level::ERROR => 40,
+            Level::WARN => 30,
+            Level::INFO => 20,
+            Level::DEBUG => 10,
+            Level::TRACE => 5,
+        };
+        Python::with_gil(|py| {
+            let res = py
+                .import_bound("logging")
+                .and_then(|logging| logging.call_method1("getLogger", ("kmbox_net",)))
+                .and_then(|logger| logger.call_method1("log", (level, message)));
+            if let Err(e) = res {
+                e.print(py);
+            }
+        });
+    }
+}
+
+/// Event or span fields rendered as ` key=value` pairs, message first.
+#[derive(Default)]
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!(" {:?}{}", value, self.0);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0 = format!(" {}{}", value, self.0);
+        } else {
+            let _ = write!(self.0, " {}={}", field.name(), value);
+        }
+    }
+}
+
+/// Send protocol traces to Python's `logging` under the `kmbox_net` logger.
+///
+/// `level` is the most verbose level forwarded: "ERROR", "WARN", "INFO",
+/// "DEBUG" (every command and ack) or "TRACE" (also monitor reports).
+/// Installs the process-wide `tracing` subscriber, so it can only be
+/// called once.
+#[pyfunction]
+#[pyo3(signature = (level = "DEBUG"))]
+pub fn enable_logging(level: &str) -> PyResult<()> {
+    let filter: LevelFilter = level
+        .parse()
+        .map_err(|_| PyValueError::new_err(format!("unknown level {:?}", level)))?;
+    let subscriber = tracing_subscriber::registry().with(PyLoggingLayer.with_filter(filter));
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|_| PyRuntimeError::new_err("a tracing subscriber is already installed"))
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::{connect, MAC};
+use kmbox_net::protocol::{KmError, CMD_CONNECT, CMD_MOUSE_MOVE};
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::{Key, KmBoxNetClient, KmBoxNetSimulator};
+
+#[test]
+fn stale_acks_are_skipped() {
+    let (sim, mut client) = connect();
+    sim.set_ack_behavior(AckBehavior::StaleFirst);
+    for i in 0..5 {
+        client.mouse_move(i, 0).unwrap();
+    }
+    assert_eq!(sim.state().mouse.x, 10);
+}
+
+#[test]
+fn foreign_acks_are_skipped() {
+    let (sim, mut client) = connect();
+    sim.set_ack_behavior(AckBehavior::ForeignFirst);
+    client.mouse_move(1, 1).unwrap();
+    client.keydown(Key::A).unwrap();
+}
+
+#[test]
+fn wrong_cmd_is_reported() {
+    let (sim, mut client) = connect();
+    sim.set_ack_behavior(AckBehavior::WrongCmd);
+    let err = client.mouse_move(1, 1).unwrap_err();
+    assert_eq!(err, KmError::NetAckMismatch);
+    assert_eq!(err.cmd(), Some(CMD_MOUSE_MOVE));
+    assert_eq!(err.indexpts(), Some(1));
+    assert_eq!(
+        client.enc_mouse_move(1, 1).unwrap_err(),
+        KmError::NetAckMismatch
+    );
+
+    sim.set_ack_behavior(AckBehavior::Echo);
+    client.mouse_move(1, 1).unwrap();
+}
+
+#[test]
+fn wrong_cmd_fails_handshake() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    sim.set_ack_behavior(AckBehavior::WrongCmd);
+    let err = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC)
+        .err()
+        .unwrap();
+    assert_eq!(err, KmError::NetAckMismatch);
+    assert_eq!(err.cmd(), Some(CMD_CONNECT));
+}
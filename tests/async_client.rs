@@ -0,0 +1,175 @@
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use common::MAC;
+use kmbox_net::keys::*;
+use kmbox_net::protocol::*;
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::{
+    AsyncKmBoxNetClient, ClientConfig, ConnectionState, KmBoxNetClient, KmBoxNetSimulator,
+};
+use std::time::Duration;
+
+async fn connect() -> (KmBoxNetSimulator, AsyncKmBoxNetClient) {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let client = AsyncKmBoxNetClient::new("127.0.0.1", sim.port(), MAC)
+        .await
+        .unwrap();
+    (sim, client)
+}
+
+#[tokio::test]
+async fn mouse_and_keyboard() {
+    let (sim, mut client) = connect().await;
+    client.mouse_move(10, -5).await.unwrap();
+    client.enc_mouse_move(3, 2).await.unwrap();
+    client.mouse_left(true).await.unwrap();
+    client.keydown(Key::LeftShift).await.unwrap();
+    client.keydown(Key::A).await.unwrap();
+    client.ping().await.unwrap();
+
+    let state = sim.state();
+    assert!(state.connected);
+    assert_eq!((state.mouse.x, state.mouse.y), (13, -3));
+    assert_eq!(state.mouse.buttons & 0x01, 0x01);
+    assert_eq!(state.keyboard.ctrl, BIT1);
+    assert_eq!(state.keyboard.button[0], KEY_A);
+    assert!(state.commands[2].encrypted);
+}
+
+#[tokio::test]
+async fn type_text_matches_blocking_client() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut blocking = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    blocking.type_text("Hi!", 0).unwrap();
+    let mut client = AsyncKmBoxNetClient::new("127.0.0.1", sim.port(), MAC)
+        .await
+        .unwrap();
+    client.type_text("Hi!", 0).await.unwrap();
+    assert!(client.type_text("ü", 0).await.is_err());
+
+    let commands = sim.state().commands;
+    let (a, b) = commands.split_at(commands.len() / 2);
+    assert_eq!(a.len(), 11);
+    for (a, b) in a.iter().zip(b) {
+        assert_eq!(a.head.cmd, b.head.cmd);
+        assert_eq!(a.payload, b.payload);
+    }
+}
+
+#[tokio::test]
+async fn same_packets_as_blocking_client() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut blocking = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    blocking.mouse_move_beizer(50, 60, 300, 1, 2, 3, 4).unwrap();
+    blocking.mask_mouse_x(true).unwrap();
+    blocking.trace_enable(2, 40).unwrap();
+
+    let mut client = AsyncKmBoxNetClient::new("127.0.0.1", sim.port(), MAC)
+        .await
+        .unwrap();
+    client
+        .mouse_move_beizer(50, 60, 300, 1, 2, 3, 4)
+        .await
+        .unwrap();
+    client.mask_mouse_x(true).await.unwrap();
+    client.trace_enable(2, 40).await.unwrap();
+
+    let commands = sim.state().commands;
+    assert_eq!(commands.len(), 8);
+    for (a, b) in commands[1..4].iter().zip(&commands[5..8]) {
+        assert_eq!(a.head.cmd, b.head.cmd);
+        assert_eq!(a.head.rand, b.head.rand);
+        assert_eq!(a.head.indexpts, b.head.indexpts);
+        assert_eq!(a.payload, b.payload);
+    }
+}
+
+#[tokio::test]
+async fn system_commands() {
+    let (sim, mut client) = connect().await;
+    client.set_config("192.168.2.188", 8888).await.unwrap();
+    client.lcd_color(0xf800).await.unwrap();
+    client.reboot().await.unwrap();
+
+    let state = sim.state();
+    assert_eq!(state.config_port, 8888);
+    assert_eq!(state.pixel(0, 159), 0xf800);
+    assert_eq!(state.reboots, 1);
+}
+
+#[tokio::test]
+async fn timeout_and_retry() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = ClientConfig::default()
+        .command_timeout(Duration::from_millis(50))
+        .retries(1);
+    let mut client = AsyncKmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config)
+        .await
+        .unwrap();
+
+    sim.drop_acks(1);
+    client.mouse_move(1, 0).await.unwrap();
+    assert_eq!(sim.state().retransmissions, 1);
+
+    sim.set_ack_behavior(AckBehavior::Silent);
+    let err = client.mouse_move(1, 0).await.unwrap_err();
+    assert_eq!(err, KmError::NetRxTimeout);
+    assert_eq!(err.cmd(), Some(CMD_MOUSE_MOVE));
+}
+
+#[tokio::test]
+async fn stats_and_connection_state() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = ClientConfig::default()
+        .command_timeout(Duration::from_millis(50))
+        .retries(1)
+        .disconnect_after(1);
+    let mut client = AsyncKmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config)
+        .await
+        .unwrap();
+    assert_eq!(client.connection_state(), ConnectionState::Connected);
+
+    client.enc_mouse_move(1, 0).await.unwrap();
+    sim.set_ack_behavior(AckBehavior::Silent);
+    assert!(client.mouse_move(1, 0).await.is_err());
+    assert_eq!(client.connection_state(), ConnectionState::Disconnected);
+
+    let stats = client.stats();
+    assert_eq!(stats.total_commands(), 3);
+    assert_eq!(stats.encrypted, 1);
+    assert_eq!(stats.acks, 2);
+    assert_eq!(stats.timeouts, 2);
+    assert_eq!(stats.retries, 1);
+}
+
+#[tokio::test]
+async fn auto_reconnect_restores_state() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = ClientConfig::default()
+        .command_timeout(Duration::from_millis(50))
+        .disconnect_after(1)
+        .auto_reconnect(true);
+    let mut client = AsyncKmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config)
+        .await
+        .unwrap();
+    client.mask_keyboard(Key::A).await.unwrap();
+    client.keydown(Key::B).await.unwrap();
+
+    sim.set_ack_behavior(AckBehavior::Silent);
+    assert!(client.mouse_move(1, 1).await.is_err());
+    assert_eq!(client.connection_state(), ConnectionState::Disconnected);
+
+    // The box rebooted; the next command reconnects and restores it first.
+    sim.reset();
+    sim.set_ack_behavior(AckBehavior::Echo);
+    client.mouse_move(3, 4).await.unwrap();
+    assert_eq!(client.connection_state(), ConnectionState::Connected);
+
+    let state = sim.state();
+    assert_eq!(state.commands[0].head.cmd, CMD_CONNECT);
+    assert_eq!(state.masked_keys, vec![KEY_A]);
+    assert_eq!(state.keyboard.button[0], KEY_B);
+    assert_eq!((state.mouse.x, state.mouse.y), (3, 4));
+}
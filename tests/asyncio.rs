@@ -0,0 +1,90 @@
+mod common;
+
+use common::{py_globals, MAC};
+use kmbox_net::error::{CommandError, ConnectError};
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::KmBoxNetSimulator;
+use pyo3::prelude::*;
+
+/// Run `code` under `asyncio.run`, with the globals from `py_globals`.
+fn run_async(port: u16, code: &str) -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let globals = py_globals(py, port)?;
+        let code = format!(
+            "async def main():\n{}\nasyncio.run(main())",
+            code.lines()
+                .map(|line| format!("    {}\n", line))
+                .collect::<String>()
+        );
+        py.run_bound(&code, Some(&globals), None)
+    })
+}
+
+#[test]
+fn commands_are_awaitable() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    run_async(
+        sim.port(),
+        r#"
+client = await Async.connect("127.0.0.1", port, "0B50E466")
+await client.mouse_move(10, -5)
+await asyncio.gather(client.keypress(4, 20), client.enc_mouse_move(3, 2))
+await client.set_config("192.168.2.188", 8888)
+client.close()
+"#,
+    )
+    .unwrap();
+
+    let state = sim.state();
+    assert!(state.connected);
+    assert_eq!((state.mouse.x, state.mouse.y), (13, -3));
+    assert_eq!(state.config_port, 8888);
+    // keypress sends keydown and keyup before the queued mouse move.
+    assert_eq!(state.commands.len(), 6);
+}
+
+#[test]
+fn event_loop_keeps_running_during_keypress() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    run_async(
+        sim.port(),
+        r#"
+client = await Async.connect("127.0.0.1", port, "0B50E466")
+ticks = []
+async def tick():
+    while True:
+        ticks.append(None)
+        await asyncio.sleep(0.01)
+task = asyncio.create_task(tick())
+await client.keypress(4, 300)
+task.cancel()
+assert len(ticks) > 10, len(ticks)
+"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn errors_raise_on_await() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let err = run_async(
+        sim.port(),
+        r#"
+client = await Async.connect("127.0.0.1", port, "0B50E466")
+await client.lcd_picture(b"too short")
+"#,
+    )
+    .unwrap_err();
+    Python::with_gil(|py| assert!(err.is_instance_of::<CommandError>(py)));
+
+    sim.set_ack_behavior(AckBehavior::Silent);
+    let err = run_async(
+        sim.port(),
+        r#"
+await Async.connect("127.0.0.1", port, "0B50E466", connect_timeout_ms=50)
+"#,
+    )
+    .unwrap_err();
+    Python::with_gil(|py| assert!(err.is_instance_of::<ConnectError>(py)));
+}
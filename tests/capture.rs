@@ -0,0 +1,133 @@
+mod common;
+
+use common::{temp_path, MAC};
+use kmbox_net::capture::{Capture, CaptureSink, CapturedPacket};
+use kmbox_net::keys::*;
+use kmbox_net::protocol::{HardKeyboard, HardMouse};
+use kmbox_net::{KmBoxNetClient, KmBoxNetMonitor, KmBoxNetSimulator};
+use std::io;
+use std::net::UdpSocket;
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[test]
+fn jsonl_records_both_directions() {
+    let path = temp_path("client.jsonl");
+    let capture = Capture::jsonl(&path).unwrap();
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    client.set_capture(Some(capture.clone()));
+    client.mouse_move(5, 6).unwrap();
+    client.enc_mouse_move(1, 1).unwrap();
+    client.set_capture(None);
+    client.mouse_move(1, 1).unwrap();
+    capture.flush().unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 4);
+    let remote = format!("\"remote\":\"127.0.0.1:{}\"", sim.port());
+    assert!(lines.iter().all(|l| l.contains(&remote)));
+    // The address the client sends from, not the 0.0.0.0 it is bound to.
+    assert!(lines.iter().all(|l| l.contains("\"local\":\"127.0.0.1:")));
+
+    assert!(lines[0].contains("\"dir\":\"sent\",\"channel\":\"command\""));
+    assert!(lines[0].contains("\"len\":72,\"encrypted\":false"));
+    assert!(lines[0].contains("\"indexpts\":1,\"cmd\":\"CMD_MOUSE_MOVE\""));
+    assert!(lines[1].contains("\"dir\":\"received\""));
+    assert!(lines[1].contains("\"len\":16"));
+    assert!(lines[1].contains("\"indexpts\":1,\"cmd\":\"CMD_MOUSE_MOVE\""));
+    // The plain header of an encrypted command is still recorded.
+    assert!(lines[2].contains("\"len\":128,\"encrypted\":true"));
+    assert!(lines[2].contains("\"mac\":\"0x0b50e466\""));
+    assert!(lines[2].contains("\"indexpts\":2,\"cmd\":\"CMD_MOUSE_MOVE\""));
+}
+
+#[test]
+fn pcap_frames_are_ipv4_udp() {
+    let path = temp_path("client.pcap");
+    let capture = Capture::pcap(&path).unwrap();
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    client.set_capture(Some(capture.clone()));
+    client.mouse_move(5, 6).unwrap();
+    capture.flush().unwrap();
+
+    let bytes = std::fs::read(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(&bytes[..4], &0xa1b2_c3d4u32.to_le_bytes());
+    assert_eq!(&bytes[20..24], &101u32.to_le_bytes());
+
+    let record = &bytes[24..];
+    let caplen = u32::from_le_bytes(record[8..12].try_into().unwrap()) as usize;
+    assert_eq!(caplen, 20 + 8 + 72);
+    let frame = &record[16..16 + caplen];
+    assert_eq!(frame[0], 0x45);
+    assert_eq!(frame[9], 17);
+    // A valid IPv4 header sums to 0xffff.
+    let mut sum: u32 = frame[..20]
+        .chunks(2)
+        .map(|w| u16::from_be_bytes([w[0], w[1]]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    assert_eq!(sum, 0xffff);
+    assert_eq!(&frame[12..16], &[127, 0, 0, 1]);
+    assert_eq!(&frame[16..20], &[127, 0, 0, 1]);
+    assert_eq!(u16::from_be_bytes([frame[22], frame[23]]), sim.port());
+    assert_eq!(&frame[28..32], &0x0b50_e466u32.to_le_bytes());
+
+    // Followed by the ack.
+    let ack = &record[16 + caplen..];
+    assert_eq!(
+        u32::from_le_bytes(ack[8..12].try_into().unwrap()),
+        20 + 8 + 16
+    );
+}
+
+#[test]
+fn monitor_reports_are_decoded() {
+    struct Lines(mpsc::Sender<String>);
+    impl CaptureSink for Lines {
+        fn record(&mut self, packet: &CapturedPacket<'_>) -> io::Result<()> {
+            let mut sink = kmbox_net::capture::JsonlSink::new(Vec::new());
+            sink.record(packet)?;
+            let _ = self.0.send(String::from_utf8(sink.into_inner()).unwrap());
+            Ok(())
+        }
+    }
+
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    let port = {
+        let probe = UdpSocket::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap().port()
+    };
+    let (tx, rx) = mpsc::channel();
+    let capture = Capture::new(Lines(tx));
+    let _monitor = KmBoxNetMonitor::start_with_capture(port, Some(capture), |_, _| {}).unwrap();
+
+    client.monitor(port).unwrap();
+    let mouse = HardMouse {
+        buttons: 0x02,
+        x: -4,
+        y: 9,
+        wheel: 1,
+    };
+    let keyboard = HardKeyboard {
+        buttons: BIT0,
+        data: vec![KEY_Z],
+    };
+    sim.send_monitor_report(&mouse, &keyboard).unwrap();
+
+    let line = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+    assert!(line.contains("\"dir\":\"received\",\"channel\":\"monitor\""));
+    assert!(!line.contains("\"head\""));
+    assert!(line.contains(&format!("\"local\":\"127.0.0.1:{}\"", port)));
+    assert!(line.contains(&format!(
+        "\"report\":{{\"buttons\":2,\"x\":-4,\"y\":9,\"wheel\":1,\"modifiers\":1,\"keys\":[{}, 0, 0, 0, 0, 0, 0, 0, 0, 0]}}",
+        KEY_Z
+    )));
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::MAC;
+use kmbox_net::codec::Decode;
+use kmbox_net::keys::KEY_Y;
+use kmbox_net::protocol::{SoftKeyboard, CMD_CONNECT, CMD_KEYBOARD_ALL};
+use kmbox_net::KmBoxNetSimulator;
+use std::process::{Command, Output};
+
+fn kmbox(sim: &KmBoxNetSimulator, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_kmbox"))
+        .env_remove("KMBOX_IP")
+        .env_remove("KMBOX_MAC")
+        .env_remove("KMBOX_LAYOUT")
+        .env("KMBOX_PORT", sim.port().to_string())
+        .args(["--ip", "127.0.0.1", "--mac", MAC])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn runs_commands() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+
+    assert!(kmbox(&sim, &["mouse_move", "-5", "7"]).status.success());
+    assert!(kmbox(&sim, &["set-vid-pid", "0x046d", "0xc08b"])
+        .status
+        .success());
+    assert!(kmbox(&sim, &["mask_keyboard", "0x04"]).status.success());
+    assert!(kmbox(&sim, &["mask_mouse_left", "on"]).status.success());
+    assert!(kmbox(&sim, &["trace_enable", "1", "20"]).status.success());
+
+    let state = sim.state();
+    assert_eq!((state.mouse.x, state.mouse.y), (-5, 7));
+    assert_eq!((state.vid, state.pid), (0x046d, 0xc08b));
+    assert_eq!(state.mask_mouse, 0x01);
+    assert_eq!(state.masked_keys, vec![0x04]);
+    assert_eq!((state.trace_type, state.trace_value), (1, 20));
+}
+
+#[test]
+fn reads_connection_from_env() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_kmbox"))
+        .env("KMBOX_IP", "127.0.0.1")
+        .env("KMBOX_PORT", sim.port().to_string())
+        .env("KMBOX_MAC", MAC)
+        .env("KMBOX_LAYOUT", "de")
+        .args(["type_text", "z", "0"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    // QWERTZ puts z where a US keyboard has y.
+    let typed = &sim.state().commands[1];
+    assert_eq!(typed.head.cmd, CMD_KEYBOARD_ALL);
+    assert_eq!(
+        SoftKeyboard::decode(&typed.payload).unwrap().button[0],
+        KEY_Y
+    );
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    assert!(!kmbox(&sim, &["mouse_move", "1"]).status.success());
+    assert!(!kmbox(&sim, &["lcd_color", "0x10000"]).status.success());
+    assert!(!kmbox(&sim, &["mask_mouse_left", "maybe"]).status.success());
+    assert!(sim
+        .state()
+        .commands
+        .iter()
+        .all(|c| c.head.cmd == CMD_CONNECT));
+}
@@ -0,0 +1,169 @@
+use kmbox_net::codec::*;
+use kmbox_net::protocol::*;
+use std::net::Ipv4Addr;
+
+fn packet(cmd: u32, rand: u32, payload: &[u8]) -> Vec<u8> {
+    let head = CmdHead {
+        mac: 0x0B50E466,
+        rand,
+        indexpts: 7,
+        cmd,
+    };
+    let mut buf = head.to_bytes();
+    payload.encode(&mut buf);
+    buf
+}
+
+#[test]
+fn head_is_little_endian() {
+    let head = CmdHead {
+        mac: 0x11223344,
+        rand: 0x55667788,
+        indexpts: 1,
+        cmd: CMD_CONNECT,
+    };
+    assert_eq!(
+        head.to_bytes(),
+        [0x44, 0x33, 0x22, 0x11, 0x88, 0x77, 0x66, 0x55, 1, 0, 0, 0, 0x28, 0x28, 0x3c, 0xaf]
+    );
+}
+
+#[test]
+fn soft_mouse_round_trip() {
+    let mut mouse = SoftMouse {
+        button: 0x05,
+        x: -100,
+        y: 42,
+        wheel: -1,
+        ..Default::default()
+    };
+    mouse.point[3] = 9;
+
+    let bytes = mouse.to_bytes();
+    assert_eq!(bytes.len(), SoftMouse::SIZE);
+    assert_eq!(bytes[4..8], (-100i32).to_le_bytes());
+
+    let decoded = SoftMouse::decode(&bytes).unwrap();
+    assert_eq!((decoded.button, decoded.x, decoded.y), (0x05, -100, 42));
+    assert_eq!(decoded.point[3], 9);
+    assert!(SoftMouse::decode(&bytes[..20]).is_none());
+}
+
+#[test]
+fn soft_keyboard_round_trip() {
+    let keyboard = SoftKeyboard {
+        ctrl: 0x02,
+        resvel: 0,
+        button: [4, 5, 0, 0, 0, 0, 0, 0, 0, 0],
+    };
+    let bytes = keyboard.to_bytes();
+    assert_eq!(bytes, [2, 0, 4, 5, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let decoded = SoftKeyboard::decode(&bytes).unwrap();
+    assert_eq!(decoded.button, keyboard.button);
+}
+
+#[test]
+fn monitor_report_round_trip() {
+    let report = MonitorReport {
+        mouse: HardMouse {
+            buttons: 1,
+            x: -3,
+            y: 300,
+            wheel: 0,
+        },
+        keyboard: HardKeyboard {
+            buttons: 0x10,
+            data: vec![0x1D],
+        },
+    };
+    let bytes = report.to_bytes();
+    assert_eq!(bytes.len(), MonitorReport::SIZE);
+
+    let decoded = MonitorReport::decode(&bytes).unwrap();
+    assert_eq!(decoded.mouse.y, 300);
+    assert_eq!(decoded.keyboard.buttons, 0x10);
+    assert_eq!(decoded.keyboard.data, [0x1D, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn parse_mouse_and_keyboard() {
+    let mouse = SoftMouse {
+        x: 5,
+        ..Default::default()
+    };
+    match Packet::parse(&packet(CMD_MOUSE_MOVE, 0, &mouse.to_bytes())).unwrap() {
+        Packet::Mouse { head, mouse } => {
+            assert_eq!(head.indexpts, 7);
+            assert_eq!(mouse.x, 5);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let keyboard = SoftKeyboard {
+        ctrl: 1,
+        ..Default::default()
+    };
+    assert!(matches!(
+        Packet::parse(&packet(CMD_KEYBOARD_ALL, 0, &keyboard.to_bytes())),
+        Some(Packet::Keyboard { keyboard, .. }) if keyboard.ctrl == 1
+    ));
+
+    // Truncated payload
+    assert!(Packet::parse(&packet(CMD_MOUSE_MOVE, 0, &[0; 8])).is_none());
+}
+
+#[test]
+fn parse_rand_fields() {
+    assert!(matches!(
+        Packet::parse(&packet(CMD_MONITOR, 0xaa55_3039, &[])),
+        Some(Packet::Monitor {
+            port: Some(12345),
+            ..
+        })
+    ));
+    assert!(matches!(
+        Packet::parse(&packet(CMD_MONITOR, 0, &[])),
+        Some(Packet::Monitor { port: None, .. })
+    ));
+    assert!(matches!(
+        Packet::parse(&packet(CMD_SETVIDPID, 0xc077_046d, &[])),
+        Some(Packet::SetVidPid {
+            vid: 0x046d,
+            pid: 0xc077,
+            ..
+        })
+    ));
+    assert!(matches!(
+        Packet::parse(&packet(CMD_TRACE_ENABLE, (2 << 24) | 40, &[])),
+        Some(Packet::TraceEnable {
+            type_: 2,
+            value: 40,
+            ..
+        })
+    ));
+    assert!(matches!(
+        Packet::parse(&packet(CMD_MASK_MOUSE, 0x1A03, &[])),
+        Some(Packet::MaskMouse {
+            flags: 0x03,
+            vkey: 0x1A,
+            ..
+        })
+    ));
+
+    let ip = u32::from_le_bytes([192, 168, 2, 188]);
+    match Packet::parse(&packet(CMD_SETCONFIG, ip, &8888u16.to_be_bytes())).unwrap() {
+        Packet::SetConfig { ip, port, .. } => {
+            assert_eq!(ip, Ipv4Addr::new(192, 168, 2, 188));
+            assert_eq!(port, 8888);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn parse_unknown_keeps_payload() {
+    let parsed = Packet::parse(&packet(0xdeadbeef, 1, &[9, 9])).unwrap();
+    assert_eq!(parsed.head().cmd, 0xdeadbeef);
+    assert!(matches!(parsed, Packet::Unknown { payload, .. } if payload == [9, 9]));
+    assert!(Packet::parse(&[0; 10]).is_none());
+}
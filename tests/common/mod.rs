@@ -0,0 +1,46 @@
+//! Helpers shared by the integration tests. Each test crate uses only some
+//! of them.
+#![allow(dead_code)]
+
+use kmbox_net::layout::Layout;
+use kmbox_net::{
+    AsyncioKmBoxNetClient, ClientConfig, KmBoxNetClient, KmBoxNetSimulator, PySharedKmBoxNetClient,
+};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const MAC: &str = "0B50E466";
+
+/// A simulator and a client connected to it.
+pub fn connect() -> (KmBoxNetSimulator, KmBoxNetClient) {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let client = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    (sim, client)
+}
+
+/// Short timeouts, so tests that lose acks stay fast.
+pub fn config() -> ClientConfig {
+    ClientConfig::default()
+        .connect_timeout(Duration::from_millis(200))
+        .command_timeout(Duration::from_millis(50))
+}
+
+/// A path in the temp dir that no other test process uses.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("kmbox-test-{}-{}", std::process::id(), name))
+}
+
+/// Globals for Python snippets: the `Client`, `Shared` and `Async` client
+/// classes, `Layout`, the `asyncio` module and the simulator's `port`.
+pub fn py_globals(py: Python<'_>, port: u16) -> PyResult<Bound<'_, PyDict>> {
+    let globals = PyDict::new_bound(py);
+    globals.set_item("Client", py.get_type_bound::<KmBoxNetClient>())?;
+    globals.set_item("Shared", py.get_type_bound::<PySharedKmBoxNetClient>())?;
+    globals.set_item("Async", py.get_type_bound::<AsyncioKmBoxNetClient>())?;
+    globals.set_item("Layout", py.get_type_bound::<Layout>())?;
+    globals.set_item("asyncio", py.import_bound("asyncio")?)?;
+    globals.set_item("port", port)?;
+    Ok(globals)
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{temp_path, MAC};
+use kmbox_net::capture::Capture;
+use kmbox_net::keys::{Key, KEY_A};
+use kmbox_net::{KmBoxNetClient, KmBoxNetSimulator};
+use std::path::Path;
+use std::process::Command;
+
+/// Run a short session against the simulator, captured to `capture`.
+fn record_session(capture: &Capture) {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    client.set_capture(Some(capture.clone()));
+    client.mouse_move(5, -6).unwrap();
+    client.keydown(Key::A).unwrap();
+    client.monitor(1234).unwrap();
+    client.trace_enable(1, 2).unwrap();
+    client.enc_mouse_move(7, 8).unwrap();
+    capture.flush().unwrap();
+}
+
+fn dissect(path: &Path, args: &[&str]) -> Vec<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_kmbox-dissect"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_file(path);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn decodes_pcap() {
+    let path = temp_path("session.pcap");
+    record_session(&Capture::pcap(&path).unwrap());
+
+    let lines = dissect(&path, &["--mac", MAC]);
+    assert_eq!(lines.len(), 10);
+    assert!(lines[0].contains("CMD_MOUSE_MOVE"));
+    assert!(lines[0].contains("mac=0x0b50e466 indexpts=1"));
+    assert!(lines[0].contains("buttons=0x00 x=5 y=-6 wheel=0"));
+    assert!(lines[1].contains("ack CMD_MOUSE_MOVE"));
+    assert!(lines[2].contains("CMD_KEYBOARD_ALL"));
+    assert!(lines[2].contains(&format!("keys=[{}]", KEY_A)));
+    assert!(lines[4].contains("CMD_MONITOR"));
+    assert!(lines[4].contains("port=1234"));
+    assert!(lines[6].contains("CMD_TRACE_ENABLE"));
+    assert!(lines[6].contains("type=1 value=2"));
+    assert!(lines[8].contains("x=7 y=8"));
+    assert!(lines[8].ends_with("(encrypted)"));
+}
+
+#[test]
+fn decodes_jsonl_and_needs_mac_for_encrypted() {
+    let path = temp_path("session.jsonl");
+    record_session(&Capture::jsonl(&path).unwrap());
+
+    let lines = dissect(&path, &[]);
+    assert_eq!(lines.len(), 10);
+    assert!(lines[0].contains(" sent CMD_MOUSE_MOVE"));
+    assert!(lines[1].contains(" received ack CMD_MOUSE_MOVE"));
+    assert!(lines[8].contains("pass --mac to decrypt"));
+}
@@ -0,0 +1,83 @@
+use kmbox_net::encryption::*;
+use proptest::prelude::*;
+
+const KEY: [u8; 16] = [0x0B, 0x50, 0xE4, 0x66, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+#[test]
+fn kmbox_mode_known_answer() {
+    let mut data: Vec<u8> = (0..128u32).map(|i| (i * 7 + 3) as u8).collect();
+    xxtea_encrypt(&mut data, &KEY);
+    let expected: [u8; 128] = [
+        0xa0, 0x80, 0x62, 0xdc, 0x28, 0x7f, 0x5a, 0x07, 0x05, 0x12, 0x33, 0x93, 0xe0, 0x5f, 0x7c,
+        0x46, 0x67, 0xa3, 0xf2, 0x9c, 0x83, 0xe4, 0xaf, 0x16, 0x2b, 0xb0, 0x8f, 0xab, 0xed, 0x10,
+        0xcd, 0x42, 0x61, 0xee, 0x24, 0xd3, 0x8b, 0xe3, 0x97, 0x87, 0x07, 0x11, 0x6c, 0x1b, 0x51,
+        0x79, 0xdf, 0xf3, 0xbf, 0x83, 0xe1, 0x49, 0xf0, 0x99, 0xb2, 0xfc, 0xbf, 0xb8, 0x1a, 0x7c,
+        0x15, 0x25, 0x7f, 0x13, 0xa8, 0x7c, 0x99, 0xdf, 0x22, 0x19, 0x6d, 0x30, 0x39, 0x31, 0xc8,
+        0x11, 0x5c, 0x72, 0xa5, 0x25, 0x6d, 0x96, 0xe2, 0x6f, 0x4e, 0xc3, 0xc4, 0x89, 0x41, 0x1f,
+        0x08, 0x1f, 0xca, 0x0c, 0x52, 0xa6, 0x25, 0x08, 0x7f, 0x7e, 0x66, 0xa7, 0xac, 0x79, 0x83,
+        0xd5, 0x91, 0x20, 0xb9, 0x19, 0x04, 0xf4, 0x9e, 0x70, 0xa5, 0x09, 0x8b, 0x0b, 0xa8, 0xab,
+        0x94, 0xcc, 0x2c, 0xde, 0xbb, 0x1c, 0xff, 0xa5,
+    ];
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn standard_mode_known_answer() {
+    let key: Vec<u8> = (0..16).collect();
+
+    let mut two: Vec<u8> = (0..8u32).map(|i| (i * 13 + 1) as u8).collect();
+    xxtea_encrypt_with(&mut two, &key, XxteaMode::Standard).unwrap();
+    assert_eq!(two, [0x0f, 0xa2, 0xbb, 0x63, 0x21, 0x91, 0xca, 0x4d]);
+
+    let mut five: Vec<u8> = (0..20u32).map(|i| (i * 13 + 1) as u8).collect();
+    xxtea_encrypt_with(&mut five, &key, XxteaMode::Standard).unwrap();
+    assert_eq!(
+        five,
+        [
+            0x95, 0x20, 0x39, 0x14, 0x57, 0x9d, 0xd8, 0xc7, 0x8f, 0x9a, 0x90, 0xfb, 0xe4, 0x78,
+            0xde, 0xcc, 0x73, 0x7e, 0x96, 0x06
+        ]
+    );
+}
+
+#[test]
+fn kmbox_mode_ignores_trailing_bytes() {
+    let mut data = vec![0x5Au8; 140];
+    xxtea_encrypt(&mut data, &KEY);
+    assert_eq!(data[128..], [0x5A; 12]);
+}
+
+#[test]
+fn invalid_lengths() {
+    let mut short = [0u8; 64];
+    assert_eq!(
+        xxtea_encrypt_with(&mut short, &KEY, XxteaMode::KmBox),
+        Err(InvalidBlockLength(64))
+    );
+    // The fixed-size helper leaves short buffers untouched.
+    xxtea_encrypt(&mut short, &KEY);
+    assert_eq!(short, [0; 64]);
+
+    assert!(xxtea_encrypt_with(&mut [0u8; 4], &KEY, XxteaMode::Standard).is_err());
+    assert!(xxtea_decrypt_with(&mut [0u8; 10], &KEY, XxteaMode::Standard).is_err());
+}
+
+proptest! {
+    #[test]
+    fn kmbox_round_trip(data in prop::collection::vec(any::<u8>(), 128..200), key in any::<[u8; 16]>()) {
+        let mut buf = data.clone();
+        xxtea_encrypt(&mut buf, &key);
+        xxtea_decrypt(&mut buf, &key);
+        prop_assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn standard_round_trip(words in 2usize..64, seed in any::<u64>(), key in any::<[u8; 16]>()) {
+        let data: Vec<u8> = (0..words * 4).map(|i| (seed >> (i % 8 * 8)) as u8 ^ i as u8).collect();
+        let mut buf = data.clone();
+        xxtea_encrypt_with(&mut buf, &key, XxteaMode::Standard).unwrap();
+        prop_assert_ne!(&buf, &data);
+        xxtea_decrypt_with(&mut buf, &key, XxteaMode::Standard).unwrap();
+        prop_assert_eq!(buf, data);
+    }
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::MAC;
+use kmbox_net::protocol::{KmError, CMD_MOUSE_MOVE, CMD_SETCONFIG};
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::{ClientConfig, KmBoxNetClient, KmBoxNetSimulator};
+use std::error::Error as _;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+#[test]
+fn codes_match_c_sdk() {
+    assert_eq!(KmError::CreatSocket.code(), -9000);
+    assert_eq!(KmError::NetRxTimeout.code(), -8997);
+    assert_eq!(KmError::UsbDevTxTimeout.code(), 1);
+}
+
+#[test]
+fn bind_failure_keeps_os_error() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let err = KmBoxNetSimulator::start(&sim.local_addr().to_string(), MAC)
+        .err()
+        .unwrap();
+    assert_eq!(err, KmError::CreatSocket);
+    assert_eq!(err.code(), -9000);
+    assert_eq!(err.io_error().unwrap().kind(), ErrorKind::AddrInUse);
+    assert!(err.source().is_some());
+}
+
+#[test]
+fn timeout_names_the_command() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = ClientConfig::default().command_timeout(Duration::from_millis(50));
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config).unwrap();
+    client.mouse_move(1, 1).unwrap();
+
+    sim.set_ack_behavior(AckBehavior::Silent);
+    let err = client.mouse_move(1, 1).unwrap_err();
+    assert_eq!(err.kind(), KmError::NetRxTimeout);
+    assert_eq!(err.cmd(), Some(CMD_MOUSE_MOVE));
+    assert_eq!(err.indexpts(), Some(2));
+    let text = err.to_string();
+    assert!(text.starts_with("Network receive timeout (CMD_MOUSE_MOVE, indexpts 2)"));
+}
+
+#[test]
+fn invalid_input_names_the_command() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    let err = client.set_config("not an ip", 80).unwrap_err();
+    assert_eq!(err, KmError::NetCmd);
+    assert_eq!(err.cmd(), Some(CMD_SETCONFIG));
+    assert!(err.io_error().is_none());
+}
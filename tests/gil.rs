@@ -0,0 +1,159 @@
+mod common;
+
+use common::MAC;
+use kmbox_net::error::KmBoxError;
+use kmbox_net::protocol::{HardKeyboard, HardMouse};
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::{KmBoxNetClient, KmBoxNetMonitor, KmBoxNetSimulator};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn free_port() -> u16 {
+    UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[test]
+fn monitor_callback_runs_during_lcd_picture() {
+    let sim = Arc::new(KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap());
+    let port = free_port();
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let events = PyList::empty_bound(py);
+        let callback = py
+            .eval_bound(
+                "lambda events: lambda mouse, keyboard: events.append(mouse)",
+                None,
+                None,
+            )
+            .unwrap()
+            .call1((&events,))
+            .unwrap();
+        let monitor = py
+            .get_type_bound::<KmBoxNetMonitor>()
+            .call1((port, callback))
+            .unwrap();
+        let client = py
+            .get_type_bound::<KmBoxNetClient>()
+            .call1(("127.0.0.1", sim.port(), MAC))
+            .unwrap();
+        client.call_method1("monitor", (port,)).unwrap();
+
+        // 40 packets at 10ms each keep the upload busy for ~400ms.
+        sim.set_ack_behavior(AckBehavior::Delayed(Duration::from_millis(10)));
+        let uploading = Arc::new(AtomicBool::new(true));
+        let reporter = {
+            let sim = sim.clone();
+            let uploading = uploading.clone();
+            thread::spawn(move || {
+                while uploading.load(Ordering::Relaxed) {
+                    let _ =
+                        sim.send_monitor_report(&HardMouse::default(), &HardKeyboard::default());
+                    thread::sleep(Duration::from_millis(5));
+                }
+            })
+        };
+
+        // This thread holds the GIL except inside `lcd_picture`, so any
+        // callback counted here ran during the upload.
+        let before = events.len();
+        let picture = PyBytes::new_bound(py, &[0u8; 128 * 160 * 2]);
+        client.call_method1("lcd_picture", (picture,)).unwrap();
+        let during = events.len() - before;
+
+        uploading.store(false, Ordering::Relaxed);
+        py.allow_threads(|| reporter.join().unwrap());
+        monitor.call_method0("shutdown").unwrap();
+        assert!(during > 0, "monitor callback did not run during the upload");
+    });
+}
+
+#[test]
+fn second_thread_gets_kmbox_error_while_client_is_busy() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let globals = PyDict::new_bound(py);
+        globals
+            .set_item("Client", py.get_type_bound::<KmBoxNetClient>())
+            .unwrap();
+        globals
+            .set_item("KmBoxError", py.get_type_bound::<KmBoxError>())
+            .unwrap();
+        globals.set_item("port", sim.port()).unwrap();
+        globals.set_item("mac", MAC).unwrap();
+        let client = py
+            .eval_bound("Client('127.0.0.1', port, mac)", Some(&globals), None)
+            .unwrap();
+        globals.set_item("client", client).unwrap();
+
+        // Keep the first thread inside `lcd_picture` for ~400ms.
+        sim.set_ack_behavior(AckBehavior::Delayed(Duration::from_millis(10)));
+        py.run_bound(
+            r#"
+import threading, time
+
+upload = threading.Thread(target=lambda: client.lcd_picture(bytes(128 * 160 * 2)))
+upload.start()
+time.sleep(0.1)
+errors = []
+for call in (lambda: client.mouse_move(1, 1), client.pressed_buttons):
+    try:
+        call()
+    except KmBoxError as e:
+        errors.append(e)
+upload.join()
+client.mouse_move(2, 2)
+"#,
+            Some(&globals),
+            None,
+        )
+        .unwrap();
+
+        let errors = globals.get_item("errors").unwrap().unwrap();
+        assert_eq!(errors.len().unwrap(), 2);
+        let error = errors.get_item(0).unwrap();
+        assert!(error
+            .str()
+            .unwrap()
+            .to_string()
+            .contains("SharedKmBoxNetClient"));
+        assert!(error.getattr("code").unwrap().is_none());
+    });
+    assert_eq!((sim.state().mouse.x, sim.state().mouse.y), (2, 2));
+}
+
+#[test]
+fn dropping_monitor_does_not_wait_for_blocked_callback() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let port = free_port();
+    let mut client = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    client.monitor(port).unwrap();
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let callback = py
+            .eval_bound("lambda mouse, keyboard: None", None, None)
+            .unwrap();
+        let monitor = py
+            .get_type_bound::<KmBoxNetMonitor>()
+            .call1((port, callback))
+            .unwrap();
+
+        // The callback thread now waits for the GIL this thread holds.
+        sim.send_monitor_report(&HardMouse::default(), &HardKeyboard::default())
+            .unwrap();
+        thread::sleep(Duration::from_millis(100));
+        drop(monitor);
+    });
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{config, MAC};
+use kmbox_net::protocol::{KmError, CMD_MOUSE_MOVE};
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::{Heartbeat, KmBoxNetClient, KmBoxNetSimulator, LatencyStats, SharedKmBoxNetClient};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Poll `heartbeat` until its stats satisfy `done`, failing after 5 s.
+fn wait_for(heartbeat: &Heartbeat, done: impl Fn(&LatencyStats) -> bool) -> LatencyStats {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let stats = heartbeat.stats();
+        if done(&stats) {
+            return stats;
+        }
+        assert!(Instant::now() < deadline, "heartbeat stuck at {:?}", stats);
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn ping_sends_an_empty_mouse_report() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config()).unwrap();
+    client.mouse_left(true).unwrap();
+
+    let rtt = client.ping().unwrap();
+    assert!(rtt > Duration::ZERO);
+
+    let state = sim.state();
+    let last = state.commands.last().unwrap();
+    assert_eq!(last.head.cmd, CMD_MOUSE_MOVE);
+    assert_eq!((state.mouse.x, state.mouse.y), (0, 0));
+    assert_eq!(state.mouse.buttons, 0x01);
+}
+
+#[test]
+fn ping_is_not_retried() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = config().retries(3);
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config).unwrap();
+
+    sim.set_ack_behavior(AckBehavior::Silent);
+    assert_eq!(client.ping().unwrap_err(), KmError::NetRxTimeout);
+    assert_eq!(sim.state().retransmissions, 0);
+}
+
+#[test]
+fn heartbeat_tracks_latency_and_loss() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let client = SharedKmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config()).unwrap();
+    let mut heartbeat = client.start_heartbeat(Duration::from_millis(5));
+
+    let stats = wait_for(&heartbeat, |stats| stats.sent > 0);
+    assert_eq!(stats.lost, 0);
+    let (min, avg, p99) = (stats.min.unwrap(), stats.avg.unwrap(), stats.p99.unwrap());
+    assert!(min <= avg && avg <= p99);
+
+    // Commands still go through while the heartbeat runs.
+    client.mouse_move(1, 2).unwrap();
+
+    sim.set_ack_behavior(AckBehavior::Silent);
+    wait_for(&heartbeat, |stats| stats.lost > 0);
+    heartbeat.stop();
+    assert_eq!((sim.state().mouse.x, sim.state().mouse.y), (1, 2));
+}
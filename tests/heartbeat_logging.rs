@@ -0,0 +1,48 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use common::{py_globals, MAC};
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::KmBoxNetSimulator;
+use pyo3::prelude::*;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn dropping_heartbeat_with_logging_does_not_deadlock() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let port = sim.port();
+    // Keep a ping in flight when the heartbeat is dropped: its ack is then
+    // logged from the ping thread, which needs the GIL.
+    sim.set_ack_behavior(AckBehavior::Delayed(Duration::from_millis(100)));
+    pyo3::prepare_freethreaded_python();
+
+    // A deadlock would hang the dropping thread, so wait on it with a timeout.
+    let (done, finished) = mpsc::channel();
+    thread::spawn(move || {
+        Python::with_gil(|py| {
+            let enable = wrap_pyfunction_bound!(kmbox_net::trace::enable_logging, py).unwrap();
+            enable.call1(("DEBUG",)).unwrap();
+            let globals = py_globals(py, port).unwrap();
+            py.run_bound(
+                r#"
+import logging, time
+logging.getLogger("kmbox_net").setLevel(logging.DEBUG)
+client = Shared("127.0.0.1", port, "0B50E466")
+hb = client.start_heartbeat(1)
+time.sleep(0.15)
+del hb
+"#,
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+        });
+        done.send(()).unwrap();
+    });
+    finished
+        .recv_timeout(Duration::from_secs(5))
+        .expect("dropping the heartbeat deadlocked");
+}
@@ -0,0 +1,409 @@
+mod common;
+
+use common::{connect, py_globals, MAC};
+use kmbox_net::codec::Decode;
+use kmbox_net::keys::*;
+use kmbox_net::layout::{Layout, MOD_ALTGR, MOD_SHIFT};
+use kmbox_net::protocol::*;
+use kmbox_net::{ClientConfig, KmBoxNetClient, KmBoxNetSimulator};
+use pyo3::prelude::*;
+
+/// `(ctrl, first key)` of every keyboard report the simulator received.
+fn reports(sim: &KmBoxNetSimulator) -> Vec<(u8, u8)> {
+    sim.state()
+        .commands
+        .iter()
+        .filter(|c| c.head.cmd == CMD_KEYBOARD_ALL)
+        .map(|c| SoftKeyboard::decode(&c.payload).unwrap())
+        .map(|k| (k.ctrl, k.button[0]))
+        .collect()
+}
+
+const S: u8 = MOD_SHIFT;
+const ALTGR: u8 = MOD_ALTGR;
+
+/// Check `(char, key, modifiers)` rows against `layout`.
+fn check_layout(layout: Layout, table: &[(char, u8, u8)]) {
+    for &(c, key, modifiers) in table {
+        let stroke = layout.keystroke(c);
+        assert_eq!(
+            stroke.map(|s| (s.key, s.modifiers)),
+            Some((key, modifiers)),
+            "{:?} on {}",
+            c,
+            layout
+        );
+    }
+}
+
+/// No two characters of `layout` share a key and modifier combination.
+fn check_unambiguous(layout: Layout) {
+    let mut seen = std::collections::HashMap::new();
+    for c in (' '..='~').chain("£€¦¬§°²³ßäöüÄÖÜµ´éèçàù¨¤".chars()) {
+        if let Some(stroke) = layout.keystroke(c) {
+            if let Some(other) = seen.insert((stroke.key, stroke.modifiers), c) {
+                panic!("{:?} and {:?} share a key on {}", other, c, layout);
+            }
+        }
+    }
+}
+
+#[test]
+fn us_layout() {
+    check_layout(
+        Layout::Us,
+        &[
+            ('a', KEY_A, 0),
+            ('Z', KEY_Z, S),
+            ('0', KEY_0_CPARENTHESIS, 0),
+            ('@', KEY_2_AT, S),
+            ('?', KEY_SLASH_QUESTION, S),
+            ('\\', KEY_BACKSLASH_VERTICAL_BAR, 0),
+            ('~', KEY_GRAVE_ACCENT_AND_TILDE, S),
+            ('\n', KEY_ENTER, 0),
+            ('\t', KEY_TAB, 0),
+        ],
+    );
+    assert_eq!(Layout::Us.keystroke('é'), None);
+    assert_eq!(Layout::Us.keystroke('€'), None);
+    check_unambiguous(Layout::Us);
+}
+
+#[test]
+fn uk_layout() {
+    check_layout(
+        Layout::Uk,
+        &[
+            ('q', KEY_Q, 0),
+            ('"', KEY_2_AT, S),
+            ('@', KEY_SINGLE_AND_DOUBLE_QUOTE, S),
+            ('£', KEY_3_NUMBER_SIGN, S),
+            ('€', KEY_4_DOLLAR, ALTGR),
+            ('#', KEY_NONUS_NUMBER_SIGN_TILDE, 0),
+            ('~', KEY_NONUS_NUMBER_SIGN_TILDE, S),
+            ('\\', KEY_NONUS_BACK_SLASH_VERTICAL_BAR, 0),
+            ('¬', KEY_GRAVE_ACCENT_AND_TILDE, S),
+        ],
+    );
+    check_unambiguous(Layout::Uk);
+}
+
+#[test]
+fn de_layout() {
+    check_layout(
+        Layout::De,
+        &[
+            ('z', KEY_Y, 0),
+            ('Y', KEY_Z, S),
+            ('1', KEY_1_EXCLAMATION_MARK, 0),
+            ('ß', KEY_MINUS_UNDERSCORE, 0),
+            ('?', KEY_MINUS_UNDERSCORE, S),
+            ('\\', KEY_MINUS_UNDERSCORE, ALTGR),
+            ('@', KEY_Q, ALTGR),
+            ('€', KEY_E, ALTGR),
+            ('{', KEY_7_AMPERSAND, ALTGR),
+            ('ü', KEY_OBRACKET_AND_OBRACE, 0),
+            ('Ö', KEY_SEMICOLON_COLON, S),
+            ('ä', KEY_SINGLE_AND_DOUBLE_QUOTE, 0),
+            ('-', KEY_SLASH_QUESTION, 0),
+            ('|', KEY_NONUS_BACK_SLASH_VERTICAL_BAR, ALTGR),
+            ('^', KEY_GRAVE_ACCENT_AND_TILDE, 0),
+        ],
+    );
+    assert!(Layout::De.keystroke('^').unwrap().dead);
+    assert!(!Layout::De.keystroke('~').unwrap().dead);
+    check_unambiguous(Layout::De);
+}
+
+#[test]
+fn fr_layout() {
+    check_layout(
+        Layout::Fr,
+        &[
+            ('a', KEY_Q, 0),
+            ('Q', KEY_A, S),
+            ('z', KEY_W, 0),
+            ('w', KEY_Z, 0),
+            ('m', KEY_SEMICOLON_COLON, 0),
+            ('1', KEY_1_EXCLAMATION_MARK, S),
+            ('&', KEY_1_EXCLAMATION_MARK, 0),
+            ('é', KEY_2_AT, 0),
+            ('@', KEY_0_CPARENTHESIS, ALTGR),
+            ('€', KEY_E, ALTGR),
+            (',', KEY_M, 0),
+            ('.', KEY_COMMA_AND_LESS, S),
+            ('!', KEY_SLASH_QUESTION, 0),
+            ('ù', KEY_SINGLE_AND_DOUBLE_QUOTE, 0),
+        ],
+    );
+    assert!(Layout::Fr.keystroke('~').unwrap().dead);
+    assert!(!Layout::Fr.keystroke('^').unwrap().dead);
+    check_unambiguous(Layout::Fr);
+}
+
+#[test]
+fn layout_names() {
+    assert_eq!("de".parse::<Layout>(), Ok(Layout::De));
+    assert_eq!("FR".parse::<Layout>(), Ok(Layout::Fr));
+    assert!("dvorak".parse::<Layout>().is_err());
+    assert_eq!(Layout::Uk.to_string(), "UK");
+    assert_eq!(Layout::default(), Layout::Us);
+}
+
+#[test]
+fn type_text_holds_shift_across_characters() {
+    let (sim, mut client) = connect();
+    client.type_text("aB!", 0).unwrap();
+
+    let shift = BIT1;
+    assert_eq!(
+        reports(&sim),
+        [
+            (0, KEY_A),
+            (0, 0),
+            (shift, 0),
+            (shift, KEY_B),
+            (shift, 0),
+            (shift, KEY_1_EXCLAMATION_MARK),
+            (shift, 0),
+            (0, 0),
+        ]
+    );
+    let state = sim.state();
+    assert_eq!((state.keyboard.ctrl, state.keyboard.button), (0, [0; 10]));
+}
+
+#[test]
+fn type_text_keeps_held_shift() {
+    let (sim, mut client) = connect();
+    client.keydown(Key::LeftShift).unwrap();
+    client.type_text("a", 0).unwrap();
+    assert_eq!(sim.state().keyboard.ctrl, BIT1);
+}
+
+#[test]
+fn type_text_rejects_untypeable_text() {
+    let (sim, mut client) = connect();
+    let err = client.type_text("café", 0).unwrap_err();
+    assert_eq!(err, KmError::NetCmd);
+    assert_eq!(err.cmd(), Some(CMD_KEYBOARD_ALL));
+    assert_eq!(err.detail(), Some("cannot type 'é' on the US layout"));
+    assert!(err
+        .to_string()
+        .ends_with(": cannot type 'é' on the US layout"));
+    assert!(reports(&sim).is_empty());
+}
+
+#[test]
+fn type_text_uses_altgr_and_dead_keys() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = ClientConfig::default().layout(Layout::De);
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config).unwrap();
+    client.type_text("@^z", 0).unwrap();
+
+    assert_eq!(
+        reports(&sim),
+        [
+            (ALTGR, 0),
+            (ALTGR, KEY_Q),
+            (ALTGR, 0),
+            (0, 0),
+            (0, KEY_GRAVE_ACCENT_AND_TILDE),
+            (0, 0),
+            (0, KEY_SPACEBAR),
+            (0, 0),
+            (0, KEY_Y),
+            (0, 0),
+        ]
+    );
+}
+
+#[test]
+fn key_names() {
+    assert_eq!("a".parse::<Key>(), Ok(Key::A));
+    assert_eq!("Z".parse::<Key>(), Ok(Key::Z));
+    assert_eq!("0".parse::<Key>(), Ok(Key::Digit0));
+    assert_eq!("7".parse::<Key>(), Ok(Key::Digit7));
+    assert_eq!("F4".parse::<Key>(), Ok(Key::F4));
+    assert_eq!("f13".parse::<Key>(), Ok(Key::F13));
+    assert_eq!("f24".parse::<Key>(), Ok(Key::F24));
+    assert_eq!("Ctrl".parse::<Key>(), Ok(Key::LeftControl));
+    assert_eq!("altgr".parse::<Key>(), Ok(Key::RightAlt));
+    assert_eq!("win".parse::<Key>(), Ok(Key::LeftGui));
+    assert_eq!("esc".parse::<Key>(), Ok(Key::Escape));
+    assert_eq!("PgDn".parse::<Key>(), Ok(Key::PageDown));
+    assert!("f25".parse::<Key>().is_err());
+    assert!("hyper".parse::<Key>().is_err());
+    assert!("".parse::<Key>().is_err());
+}
+
+#[test]
+fn key_conversions() {
+    assert_eq!(Key::try_from(KEY_A).ok(), Some(Key::A));
+    assert_eq!(u8::from(Key::Escape), KEY_ESCAPE);
+    assert_eq!(
+        Key::try_from(KEY_LEFTCONTROL as i32).ok(),
+        Some(Key::LeftControl)
+    );
+    for code in [KEY_NONE, KEY_ERRORUNDEFINED, 0xA5, 0xDE, 0xE8, 0xFF] {
+        assert!(Key::try_from(code).is_err(), "{:#04x}", code);
+    }
+    let err = Key::try_from(0x104_i32).unwrap_err();
+    assert_eq!(err, KmError::NetCmd);
+    assert_eq!(err.detail(), Some("invalid key code 260"));
+
+    for &key in Key::ALL {
+        assert_eq!(Key::try_from(u8::from(key)).ok(), Some(key));
+        assert_eq!(key.to_string().parse::<Key>(), Ok(key));
+    }
+    assert_eq!(Key::ALL.len(), 215);
+    assert_eq!(Key::RightGui.to_string(), "RightGui");
+    assert_eq!("pageup".parse::<Key>(), Ok(Key::PageUp));
+    assert_eq!("PGUP".parse::<Key>(), Ok(Key::PageUp));
+    assert_eq!("Digit7".parse::<Key>(), Ok(Key::Digit7));
+    assert!("hyper".parse::<Key>().is_err());
+}
+
+#[test]
+fn modifiers() {
+    assert_eq!(Modifier::LeftControl.bit(), BIT0);
+    assert_eq!(Modifier::RightGui.bit(), BIT7);
+    assert_eq!(Key::from(Modifier::LeftShift), Key::LeftShift);
+    assert_eq!(u8::from(Modifier::RightAlt), KEY_RIGHTALT);
+    assert_eq!(Key::LeftAlt.modifier(), Some(Modifier::LeftAlt));
+    assert_eq!(Key::A.modifier(), None);
+    assert_eq!(
+        Modifier::try_from(KEY_RIGHTSHIFT).ok(),
+        Some(Modifier::RightShift)
+    );
+    assert!(Modifier::try_from(KEY_A).is_err());
+    assert_eq!("ctrl".parse::<Modifier>(), Ok(Modifier::LeftControl));
+    assert_eq!(Modifier::RightControl.to_string(), "RightControl");
+    assert!("a".parse::<Modifier>().is_err());
+    for modifier in Modifier::ALL {
+        assert_eq!(modifier.to_string().parse(), Ok(modifier));
+    }
+}
+
+#[test]
+fn hotkey_sends_one_report_each_way() {
+    let (sim, mut client) = connect();
+    client.keydown(Key::A).unwrap();
+    client.hotkey("ctrl+shift+esc", 0).unwrap();
+
+    let ctrl_shift = BIT0 | BIT1;
+    assert_eq!(reports(&sim), [(0, KEY_A), (ctrl_shift, KEY_A), (0, KEY_A)]);
+    let state = sim.state();
+    let chord = SoftKeyboard::decode(&state.commands[2].payload).unwrap();
+    assert_eq!(chord.button[..3], [KEY_A, KEY_ESCAPE, 0]);
+    assert_eq!(state.keyboard.button[..2], [KEY_A, 0]);
+}
+
+#[test]
+fn chord_matches_hotkey() {
+    let (sim, mut client) = connect();
+    client.chord(&[Key::LeftAlt, Key::F4], 0).unwrap();
+    client.hotkey("alt + F4", 0).unwrap();
+    let reports = reports(&sim);
+    assert_eq!(reports[..2], reports[2..]);
+    assert_eq!(reports[..2], [(BIT2, KEY_F4), (0, 0)]);
+}
+
+#[test]
+fn hotkey_rejects_unknown_keys() {
+    let (sim, mut client) = connect();
+    let err = client.hotkey("ctrl+hyper", 0).unwrap_err();
+    assert_eq!(err, KmError::NetCmd);
+    assert_eq!(err.detail(), Some(r#"unknown key "hyper" in "ctrl+hyper""#));
+    assert!(client.hotkey("ctrl+", 0).is_err());
+    assert!(reports(&sim).is_empty());
+}
+
+#[test]
+fn pressed_state_follows_commands() {
+    let (_sim, mut client) = connect();
+    assert!(client.pressed_keys().is_empty());
+    assert!(client.pressed_modifiers().is_empty());
+    assert_eq!(client.pressed_buttons(), 0);
+
+    client.keydown(Key::B).unwrap();
+    client.keydown(Key::RightAlt).unwrap();
+    client.keydown(Key::A).unwrap();
+    client.keydown(Key::LeftControl).unwrap();
+    client.mouse_left(true).unwrap();
+    client.mouse_side2(true).unwrap();
+    assert_eq!(client.pressed_keys(), [Key::B, Key::A]);
+    assert_eq!(
+        client.pressed_modifiers(),
+        [Modifier::LeftControl, Modifier::RightAlt]
+    );
+    assert_eq!(client.pressed_buttons(), 0x11);
+    assert!(client.is_pressed(Key::A));
+    assert!(client.is_pressed(Key::RightAlt));
+    assert!(!client.is_pressed(Key::LeftAlt));
+    assert!(!client.is_pressed(Key::C));
+
+    client.keyup(Key::B).unwrap();
+    client.keyup(Key::RightAlt).unwrap();
+    client.mouse_left(false).unwrap();
+    assert_eq!(client.pressed_keys(), [Key::A]);
+    assert_eq!(client.pressed_modifiers(), [Modifier::LeftControl]);
+    assert_eq!(client.pressed_buttons(), 0x10);
+
+    // A chord's keys are only held while it runs.
+    client.hotkey("shift+x", 0).unwrap();
+    assert_eq!(client.pressed_keys(), [Key::A]);
+    assert_eq!(client.pressed_modifiers(), [Modifier::LeftControl]);
+}
+
+#[test]
+fn python_pressed_state() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let globals = py_globals(py, sim.port()).unwrap();
+        py.run_bound(
+            r#"
+client = Client("127.0.0.1", port, "0B50E466")
+client.keydown(0x04)
+client.keydown(0xE1)
+client.mouse_right(True)
+assert client.pressed_keys() == [0x04]
+assert client.pressed_modifiers() == [0xE1]
+assert client.pressed_buttons() == 0x02
+assert client.is_pressed(0xE1) and not client.is_pressed(0x05)
+
+async def main():
+    client = await Async.connect("127.0.0.1", port, "0B50E466")
+    await client.keydown(0x05)
+    assert await client.pressed_keys() == [0x05]
+    assert await client.is_pressed(0x05)
+    client.close()
+
+asyncio.run(main())
+"#,
+            Some(&globals),
+            None,
+        )
+        .unwrap();
+    });
+}
+
+#[test]
+fn python_layout_keyword() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let globals = py_globals(py, sim.port()).unwrap();
+        py.run_bound(
+            r#"
+client = Client("127.0.0.1", port, "0B50E466", layout=Layout.FR)
+client.type_text("a", 0)
+"#,
+            Some(&globals),
+            None,
+        )
+        .unwrap();
+    });
+    assert_eq!(reports(&sim)[0], (0, KEY_Q));
+}
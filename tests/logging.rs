@@ -0,0 +1,73 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use common::MAC;
+use kmbox_net::{KmBoxNetClient, KmBoxNetSimulator};
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+#[test]
+fn traffic_goes_to_python_logging() {
+    pyo3::prepare_freethreaded_python();
+    let records = Python::with_gil(|py| {
+        let globals = PyDict::new_bound(py);
+        py.run_bound(
+            r#"
+import logging
+records = []
+class Collect(logging.Handler):
+    def emit(self, record):
+        records.append((record.levelno, record.getMessage()))
+logger = logging.getLogger("kmbox_net")
+logger.addHandler(Collect())
+logger.setLevel(logging.DEBUG)
+"#,
+            Some(&globals),
+            None,
+        )
+        .unwrap();
+        let enable = wrap_pyfunction_bound!(kmbox_net::trace::enable_logging, py).unwrap();
+        enable.call1(("DEBUG",)).unwrap();
+        let again = enable.call1(("DEBUG",)).unwrap_err();
+        assert!(again.is_instance_of::<PyRuntimeError>(py));
+        globals.get_item("records").unwrap().unwrap().unbind()
+    });
+
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    client.mouse_move(5, 6).unwrap();
+    client.monitor(1234).unwrap();
+    client.lcd_picture_bottom(&[0u8; 128 * 80 * 2]).unwrap();
+    client.lcd_color(0xf800).unwrap();
+
+    let records: Vec<(u32, String)> = Python::with_gil(|py| records.extract(py).unwrap());
+    let messages: Vec<&str> = records.iter().map(|(_, m)| m.as_str()).collect();
+    assert!(messages[0].starts_with("handshake{addr=127.0.0.1:"));
+    assert!(messages
+        .iter()
+        .any(|m| m.starts_with("handshake{") && m.contains(": ack cmd=CMD_CONNECT")));
+    // button, x, y, wheel
+    assert!(messages
+        .iter()
+        .any(|m| m.starts_with("send cmd=CMD_MOUSE_MOVE indexpts=1 ")
+            && m.ends_with(
+                "encrypted=false payload=56 bytes: 00000000 05000000 06000000 00000000…"
+            )));
+    assert!(messages
+        .iter()
+        .any(|m| m.starts_with("ack cmd=CMD_MOUSE_MOVE indexpts=1 elapsed_us=")));
+    assert!(records.contains(&(20, "monitor port=1234".to_string())));
+    let chunks = messages
+        .iter()
+        .filter(|m| m.starts_with("lcd_picture_bottom{chunks=20}: send cmd=CMD_SHOWPIC"))
+        .count();
+    assert_eq!(chunks, 20);
+    let fills = messages
+        .iter()
+        .filter(|m| m.starts_with("lcd_color{chunks=40}: send cmd=CMD_SHOWPIC"))
+        .count();
+    assert_eq!(fills, 40);
+    assert!(!messages.iter().any(|m| m.starts_with("lcd_picture{")));
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "prometheus")]
+
+mod common;
+
+use common::MAC;
+use kmbox_net::{KmBoxNetSimulator, SharedKmBoxNetClient};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+#[test]
+fn text_format() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let client = SharedKmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    client.mouse_move(1, 1).unwrap();
+
+    let text = client.stats().to_prometheus();
+    assert!(text.contains("# TYPE kmbox_commands_total counter\n"));
+    assert!(text.contains("kmbox_commands_total{cmd=\"CMD_CONNECT\"} 1\n"));
+    assert!(text.contains("kmbox_commands_total{cmd=\"CMD_MOUSE_MOVE\"} 1\n"));
+    assert!(text.contains("kmbox_acks_total 2\n"));
+    assert!(text.contains("kmbox_retries_total 0\n"));
+}
+
+#[test]
+fn http_endpoint() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let client = SharedKmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    let mut server = client.serve_metrics("127.0.0.1:0").unwrap();
+    client.mouse_move(1, 1).unwrap();
+
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(&client.stats().to_prometheus()));
+
+    server.stop();
+    assert!(TcpStream::connect(server.local_addr()).is_err());
+}
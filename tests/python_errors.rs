@@ -0,0 +1,109 @@
+mod common;
+
+use common::MAC;
+use kmbox_net::error::{CommandError, ConnectError, KmBoxError, TimeoutError, TransmitError};
+use kmbox_net::protocol::{KmError, CMD_MOUSE_MOVE};
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::{ClientConfig, Error, KmBoxNetClient, KmBoxNetSimulator};
+use pyo3::prelude::*;
+use std::time::Duration;
+
+fn with_py<F: FnOnce(Python<'_>)>(f: F) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(f);
+}
+
+#[test]
+fn kinds_map_to_subclasses() {
+    with_py(|py| {
+        let err: PyErr = Error::from(KmError::NetRxTimeout).into();
+        assert!(err.is_instance_of::<TimeoutError>(py));
+        assert!(err.is_instance_of::<KmBoxError>(py));
+
+        let err: PyErr = Error::from(KmError::NetTx).into();
+        assert!(err.is_instance_of::<TransmitError>(py));
+
+        let err: PyErr = Error::from(KmError::NetAckMismatch).into();
+        assert!(err.is_instance_of::<CommandError>(py));
+
+        let err: PyErr = KmError::CreatSocket.into();
+        assert!(err.is_instance_of::<ConnectError>(py));
+    });
+}
+
+#[test]
+fn exception_carries_code_and_command() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = ClientConfig::default().command_timeout(Duration::from_millis(50));
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config).unwrap();
+    sim.set_ack_behavior(AckBehavior::Silent);
+    let err = client.mouse_move(1, 1).unwrap_err();
+
+    with_py(|py| {
+        let err = PyErr::from(err);
+        assert!(err.is_instance_of::<TimeoutError>(py));
+        let value = err.value_bound(py);
+        assert_eq!(
+            value.getattr("code").unwrap().extract::<i32>().unwrap(),
+            -8997
+        );
+        assert_eq!(
+            value.getattr("cmd").unwrap().extract::<u32>().unwrap(),
+            CMD_MOUSE_MOVE
+        );
+        assert_eq!(
+            value.getattr("indexpts").unwrap().extract::<u32>().unwrap(),
+            1
+        );
+    });
+}
+
+#[test]
+fn wrong_mac_is_a_connect_error() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = ClientConfig::default().connect_timeout(Duration::from_millis(50));
+    let err = KmBoxNetClient::with_config("127.0.0.1", sim.port(), "11223344", config)
+        .err()
+        .unwrap();
+    assert_eq!(err, KmError::NetRxTimeout);
+
+    with_py(|py| {
+        let err = PyErr::from(err);
+        assert!(err.is_instance_of::<ConnectError>(py));
+        assert!(!err.is_instance_of::<TimeoutError>(py));
+    });
+}
+
+#[test]
+fn untypeable_text_is_a_command_error() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+    let err = client.type_text("→", 0).unwrap_err();
+
+    with_py(|py| {
+        let err = PyErr::from(err);
+        assert!(err.is_instance_of::<CommandError>(py));
+        assert!(err.value_bound(py).to_string().contains("cannot type '→'"));
+    });
+}
+
+#[test]
+fn invalid_key_code_is_a_command_error() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    with_py(|py| {
+        let client = py
+            .get_type_bound::<KmBoxNetClient>()
+            .call1(("127.0.0.1", sim.port(), MAC))
+            .unwrap();
+        for code in [0x1ff, 0xa5, 0x00, -1] {
+            let err = client.call_method1("keydown", (code,)).unwrap_err();
+            assert!(err.is_instance_of::<CommandError>(py), "{}", err);
+            assert!(err
+                .value_bound(py)
+                .to_string()
+                .contains(&format!("invalid key code {}", code)));
+        }
+        client.call_method1("keydown", (0x04,)).unwrap();
+    });
+    assert_eq!(sim.state().commands.len(), 2);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use common::MAC;
+use kmbox_net::codec::Decode;
+use kmbox_net::keys::{add_py_keys, KEY_F13};
+use kmbox_net::protocol::{SoftKeyboard, CMD_KEYBOARD_ALL};
+use kmbox_net::{KmBoxNetClient, KmBoxNetSimulator};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyModule};
+use std::fs;
+
+const PYI: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/kmbox_net.pyi");
+/// Everything from this line to the end of `kmbox_net.pyi` is generated.
+const GENERATED: &str = "# 键码常量定义";
+
+fn with_keys<F: FnOnce(Python<'_>, &Bound<'_, PyModule>)>(f: F) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let m = PyModule::new_bound(py, "kmbox_net").unwrap();
+        add_py_keys(&m).unwrap();
+        f(py, &m);
+    });
+}
+
+/// The stub for what `add_py_keys` adds.
+fn render(m: &Bound<'_, PyModule>) -> String {
+    let mut out = format!(
+        "{} (由 src/keys.rs 生成，请勿手动修改)\n\
+         # Generated from src/keys.rs: run `UPDATE_PYI=1 cargo test --test python_keys` after changing it.\n\n\
+         class Key(IntEnum):\n    \"\"\"HID 键码，每个成员对应一个 KEY_* 常量。\"\"\"\n\n",
+        GENERATED
+    );
+    for member in m.getattr("Key").unwrap().iter().unwrap() {
+        let member = member.unwrap();
+        let name: String = member.getattr("name").unwrap().extract().unwrap();
+        let value: u8 = member.extract().unwrap();
+        out += &format!("    {} = {:#04x}\n", name, value);
+    }
+    out += "\n";
+    for (name, _) in m.dict() {
+        let name: String = name.extract().unwrap();
+        if name.starts_with("KEY_") {
+            out += &format!("{}: int\n", name);
+        }
+    }
+    out
+}
+
+#[test]
+fn pyi_lists_every_key() {
+    with_keys(|_, m| {
+        let expected = render(m);
+        let pyi = fs::read_to_string(PYI).unwrap();
+        let start = pyi
+            .find(GENERATED)
+            .expect("generated section in kmbox_net.pyi");
+        if pyi[start..] != expected {
+            if std::env::var_os("UPDATE_PYI").is_some() {
+                fs::write(PYI, pyi[..start].to_string() + &expected).unwrap();
+            } else {
+                panic!("kmbox_net.pyi is out of date, rerun with UPDATE_PYI=1");
+            }
+        }
+    });
+}
+
+#[test]
+fn key_enum_matches_constants() {
+    with_keys(|py, m| {
+        py.run_bound(
+            r#"
+assert Key.A == KEY_A == 4
+assert Key.LEFT_CONTROL == KEY_LEFTCONTROL == 0xE0
+assert Key.SPACE == KEY_SPACEBAR
+assert Key(0x4B) is Key.PAGE_UP
+assert Key["KEYPAD_A"] == KEY_KEYPAD_A
+assert KEY_1 == Key.DIGIT1 and KEY_0 == Key.DIGIT0
+assert isinstance(Key.F24, int)
+assert len(Key) == 215
+assert Key.__module__ == "kmbox_net"
+"#,
+            Some(&m.dict()),
+            None,
+        )
+        .unwrap();
+    });
+}
+
+#[test]
+fn client_accepts_key_members() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    with_keys(|py, m| {
+        let globals = PyDict::new_bound(py);
+        globals.set_item("Key", m.getattr("Key").unwrap()).unwrap();
+        globals
+            .set_item("Client", py.get_type_bound::<KmBoxNetClient>())
+            .unwrap();
+        globals.set_item("port", sim.port()).unwrap();
+        py.run_bound(
+            r#"
+client = Client("127.0.0.1", port, "0B50E466")
+client.keydown(Key.F13)
+"#,
+            Some(&globals),
+            None,
+        )
+        .unwrap();
+    });
+    let state = sim.state();
+    let report = &state.commands[1];
+    assert_eq!(report.head.cmd, CMD_KEYBOARD_ALL);
+    assert_eq!(
+        SoftKeyboard::decode(&report.payload).unwrap().button[0],
+        KEY_F13
+    );
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::MAC;
+use kmbox_net::keys::*;
+use kmbox_net::protocol::{KmError, CMD_CONNECT};
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::{ClientConfig, ConnectionState, KmBoxNetClient, KmBoxNetSimulator};
+
+fn config() -> ClientConfig {
+    common::config().disconnect_after(2)
+}
+
+fn connects(sim: &KmBoxNetSimulator) -> usize {
+    let state = sim.state();
+    state
+        .commands
+        .iter()
+        .filter(|c| c.head.cmd == CMD_CONNECT)
+        .count()
+}
+
+#[test]
+fn timeouts_degrade_then_disconnect() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config()).unwrap();
+    assert_eq!(client.connection_state(), ConnectionState::Connected);
+
+    sim.set_ack_behavior(AckBehavior::Silent);
+    assert_eq!(client.mouse_move(1, 1).unwrap_err(), KmError::NetRxTimeout);
+    assert_eq!(client.connection_state(), ConnectionState::Degraded);
+    assert!(client.mouse_move(1, 1).is_err());
+    assert_eq!(client.connection_state(), ConnectionState::Disconnected);
+
+    // Without auto-reconnect the client just keeps sending.
+    sim.set_ack_behavior(AckBehavior::Echo);
+    client.mouse_move(1, 1).unwrap();
+    assert_eq!(client.connection_state(), ConnectionState::Connected);
+    assert_eq!(connects(&sim), 1);
+}
+
+#[test]
+fn manual_reconnect_restores_state() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config()).unwrap();
+    client.mask_mouse_left(true).unwrap();
+    client.keydown(Key::LeftShift).unwrap();
+    client.keydown(Key::A).unwrap();
+    client.mouse_left(true).unwrap();
+
+    // The box reboots and forgets everything.
+    sim.reset();
+    client.reconnect().unwrap();
+
+    let state = sim.state();
+    assert!(state.connected);
+    assert_eq!(state.mask_mouse, 0x01);
+    assert_eq!(state.mouse.buttons, 0x01);
+    assert_eq!(state.keyboard.ctrl, 0x02);
+    assert_eq!(state.keyboard.button[0], KEY_A);
+}
+
+#[test]
+fn reconnect_restores_masked_keys() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config()).unwrap();
+    client.mask_mouse_side1(true).unwrap();
+    client.mask_keyboard(Key::A).unwrap();
+    client.mask_keyboard(Key::Escape).unwrap();
+    client.mask_keyboard(Key::F5).unwrap();
+    client.unmask_keyboard(Key::Escape).unwrap();
+
+    sim.reset();
+    client.reconnect().unwrap();
+
+    let state = sim.state();
+    assert_eq!(state.mask_mouse, 0x08);
+    assert_eq!(state.masked_keys, vec![KEY_A, KEY_F5]);
+
+    // Nothing is replayed once everything is unmasked.
+    client.unmask_all().unwrap();
+    sim.reset();
+    client.reconnect().unwrap();
+    let state = sim.state();
+    assert_eq!(state.mask_mouse, 0);
+    assert!(state.masked_keys.is_empty());
+    assert_eq!(state.commands.len(), 1);
+}
+
+#[test]
+fn auto_reconnect_after_outage() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = config().auto_reconnect(true);
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config).unwrap();
+    client.mask_mouse_right(true).unwrap();
+    client.keydown(Key::B).unwrap();
+
+    sim.set_ack_behavior(AckBehavior::Silent);
+    assert!(client.mouse_move(1, 1).is_err());
+    assert!(client.mouse_move(1, 1).is_err());
+    assert_eq!(client.connection_state(), ConnectionState::Disconnected);
+
+    // Still down: the handshake itself fails and the command is not sent.
+    let sent = sim.state().commands.len();
+    assert!(client.mouse_move(1, 1).is_err());
+    assert_eq!(sim.state().commands.len(), sent + 1);
+    assert_eq!(sim.state().commands.last().unwrap().head.cmd, CMD_CONNECT);
+
+    sim.reset();
+    sim.set_ack_behavior(AckBehavior::Echo);
+    client.mouse_move(3, 4).unwrap();
+    assert_eq!(client.connection_state(), ConnectionState::Connected);
+
+    let state = sim.state();
+    assert_eq!(state.commands[0].head.cmd, CMD_CONNECT);
+    assert_eq!(state.mask_mouse, 0x02);
+    assert_eq!(state.keyboard.button[0], KEY_B);
+    assert_eq!((state.mouse.x, state.mouse.y), (3, 4));
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::MAC;
+use kmbox_net::protocol::KmError;
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::{ClientConfig, KmBoxNetClient, KmBoxNetSimulator};
+use std::time::{Duration, Instant};
+
+fn config() -> ClientConfig {
+    common::config().command_timeout(Duration::from_millis(100))
+}
+
+#[test]
+fn lost_ack_is_retried_with_same_indexpts() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client =
+        KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config().retries(2)).unwrap();
+
+    sim.drop_acks(2);
+    client.mouse_move(5, 5).unwrap();
+
+    // Applied once, acknowledged on the third attempt.
+    let state = sim.state();
+    assert_eq!(state.mouse.x, 5);
+    assert_eq!(state.retransmissions, 2);
+
+    client.mouse_move(1, 0).unwrap();
+    assert_eq!(sim.state().mouse.x, 6);
+}
+
+#[test]
+fn retries_exhausted() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client =
+        KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config().retries(1)).unwrap();
+
+    sim.drop_acks(2);
+    assert_eq!(client.mouse_move(5, 5).unwrap_err(), KmError::NetRxTimeout);
+    assert_eq!(sim.state().retransmissions, 1);
+}
+
+#[test]
+fn backoff_doubles() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = config()
+        .command_timeout(Duration::from_millis(20))
+        .retries(3)
+        .backoff(Duration::from_millis(50));
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config).unwrap();
+
+    sim.set_ack_behavior(AckBehavior::Silent);
+    let start = Instant::now();
+    assert_eq!(client.mouse_move(1, 1).unwrap_err(), KmError::NetRxTimeout);
+    // 4 timeouts of 20ms plus 50 + 100 + 200ms of backoff
+    assert!(start.elapsed() >= Duration::from_millis(430));
+}
+
+#[test]
+fn connect_timeout() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    sim.set_ack_behavior(AckBehavior::Silent);
+
+    let start = Instant::now();
+    let res = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config());
+    assert_eq!(res.err().unwrap(), KmError::NetRxTimeout);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
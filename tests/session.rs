@@ -0,0 +1,129 @@
+mod common;
+
+use common::{connect, py_globals, MAC};
+use kmbox_net::codec::Decode;
+use kmbox_net::keys::*;
+use kmbox_net::protocol::*;
+use kmbox_net::KmBoxNetSimulator;
+use pyo3::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
+
+fn assert_released(sim: &KmBoxNetSimulator) {
+    let state = sim.state();
+    assert_eq!(state.keyboard.ctrl, 0);
+    assert_eq!(state.keyboard.button, [0; 10]);
+    assert_eq!(state.mouse.buttons, 0);
+}
+
+#[test]
+fn release_all_clears_keys_and_buttons() {
+    let (sim, mut client) = connect();
+    client.keydown(Key::LeftShift).unwrap();
+    client.keydown(Key::A).unwrap();
+    client.mouse_left(true).unwrap();
+    client.mouse_side1(true).unwrap();
+    client.release_all().unwrap();
+    assert_released(&sim);
+
+    // Nothing from before the release comes back with the next report.
+    client.keydown(Key::B).unwrap();
+    let state = sim.state();
+    assert_eq!(state.keyboard.ctrl, 0);
+    assert_eq!(state.keyboard.button[..2], [KEY_B, 0]);
+}
+
+#[test]
+fn release_all_sends_reports_when_nothing_is_held() {
+    let (sim, mut client) = connect();
+    client.release_all().unwrap();
+    let cmds: Vec<u32> = sim.state().commands.iter().map(|c| c.head.cmd).collect();
+    assert_eq!(cmds, [CMD_CONNECT, CMD_KEYBOARD_ALL, CMD_MOUSE_LEFT]);
+    let keyboard = SoftKeyboard::decode(&sim.state().commands[1].payload).unwrap();
+    assert_eq!((keyboard.ctrl, keyboard.button), (0, [0; 10]));
+}
+
+#[test]
+fn session_releases_on_drop_and_panic() {
+    let (sim, mut client) = connect();
+    {
+        let mut session = client.session();
+        session.keydown(Key::Enter).unwrap();
+        session.mouse_right(true).unwrap();
+        assert_eq!(sim.state().mouse.buttons, 0x02);
+    }
+    assert_released(&sim);
+
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut session = client.session();
+        session.keydown(Key::LeftControl).unwrap();
+        session.mouse_left(true).unwrap();
+        panic!("script failed");
+    }));
+    assert!(res.is_err());
+    assert_released(&sim);
+}
+
+/// Run `code` with the globals from `py_globals`.
+fn run_py(sim: &KmBoxNetSimulator, code: &str) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let globals = py_globals(py, sim.port()).unwrap();
+        py.run_bound(code, Some(&globals), None).unwrap();
+    });
+}
+
+#[test]
+fn python_session_releases_on_exception() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    run_py(
+        &sim,
+        r#"
+client = Client("127.0.0.1", port, "0B50E466")
+try:
+    with client.session() as c:
+        assert c is client
+        c.keydown(4)
+        c.mouse_left(True)
+        raise ValueError("script failed")
+except ValueError:
+    pass
+else:
+    raise AssertionError("exception was suppressed")
+"#,
+    );
+    assert_released(&sim);
+
+    run_py(
+        &sim,
+        r#"
+client = Shared("127.0.0.1", port, "0B50E466")
+with client.session():
+    client.keydown(0xE1)
+    client.mouse_middle(True)
+"#,
+    );
+    assert_released(&sim);
+}
+
+#[test]
+fn python_async_session_releases() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    run_py(
+        &sim,
+        r#"
+async def main():
+    client = await Async.connect("127.0.0.1", port, "0B50E466")
+    try:
+        async with client.session() as c:
+            await c.keydown(4)
+            await c.mouse_left(True)
+            raise KeyError
+    except KeyError:
+        pass
+    client.close()
+
+asyncio.run(main())
+"#,
+    );
+    assert_released(&sim);
+}
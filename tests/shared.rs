@@ -0,0 +1,101 @@
+mod common;
+
+use common::MAC;
+use kmbox_net::keys::*;
+use kmbox_net::{KmBoxNetSimulator, PySharedKmBoxNetClient, SharedKmBoxNetClient};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::thread;
+
+fn assert_send_sync<T: Send + Sync + Clone>() {}
+
+#[test]
+fn commands_from_many_threads() {
+    assert_send_sync::<SharedKmBoxNetClient>();
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let client = SharedKmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let client = client.clone();
+            thread::spawn(move || {
+                for _ in 0..25 {
+                    client.mouse_move(1, -1).unwrap();
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let state = sim.state();
+    assert_eq!((state.mouse.x, state.mouse.y), (100, -100));
+    assert_eq!(state.retransmissions, 0);
+    // Every command got the next indexpts, in the order it was sent.
+    for (i, command) in state.commands.iter().enumerate() {
+        assert_eq!(command.head.indexpts, i as u32);
+    }
+}
+
+#[test]
+fn held_keys_stay_consistent() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let client = SharedKmBoxNetClient::new("127.0.0.1", sim.port(), MAC).unwrap();
+
+    let workers: Vec<_> = [Key::A, Key::B, Key::C]
+        .into_iter()
+        .map(|key| {
+            let client = client.clone();
+            thread::spawn(move || client.keydown(key).unwrap())
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    client.mouse_left(true).unwrap();
+
+    let mut held = sim.state().keyboard.button;
+    held.sort_unstable();
+    assert_eq!(held[7..], [KEY_A, KEY_B, KEY_C]);
+
+    // A sequence under one lock is not interleaved with other threads.
+    {
+        let mut locked = client.lock();
+        locked.keyup(Key::A).unwrap();
+        locked.keyup(Key::B).unwrap();
+    }
+    assert_eq!(sim.state().keyboard.button[..2], [KEY_C, 0]);
+    assert_eq!(sim.state().mouse.buttons, 0x01);
+}
+
+#[test]
+fn python_threads_share_one_client() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let globals = PyDict::new_bound(py);
+        globals
+            .set_item("Client", py.get_type_bound::<PySharedKmBoxNetClient>())
+            .unwrap();
+        globals.set_item("port", sim.port()).unwrap();
+        py.run_bound(
+            r#"
+import threading
+client = Client("127.0.0.1", port, "0B50E466")
+threads = [
+    threading.Thread(target=lambda: [client.mouse_move(1, 0) for _ in range(20)])
+    for _ in range(4)
+]
+for t in threads:
+    t.start()
+for t in threads:
+    t.join()
+"#,
+            Some(&globals),
+            None,
+        )
+        .unwrap();
+    });
+    assert_eq!(sim.state().mouse.x, 80);
+}
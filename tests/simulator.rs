@@ -0,0 +1,227 @@
+mod common;
+
+use common::connect;
+use kmbox_net::keys::*;
+use kmbox_net::protocol::*;
+use kmbox_net::KmBoxNetMonitor;
+use std::net::Ipv4Addr;
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[test]
+fn handshake_connects() {
+    let (sim, _client) = connect();
+    let state = sim.state();
+    assert!(state.connected);
+    assert_eq!(state.commands[0].head.cmd, CMD_CONNECT);
+    assert_eq!(state.commands[0].head.mac, 0x0B50E466);
+}
+
+#[test]
+fn take_commands_drains_history() {
+    let (sim, mut client) = connect();
+    client.mouse_move(1, 0).unwrap();
+    let taken = sim.take_commands();
+    assert_eq!(taken.len(), 2);
+    assert_eq!(taken[1].head.cmd, CMD_MOUSE_MOVE);
+    assert!(sim.state().commands.is_empty());
+
+    client.mouse_move(2, 0).unwrap();
+    let state = sim.state();
+    assert_eq!(state.commands.len(), 1);
+    assert_eq!(state.mouse.x, 3);
+}
+
+#[test]
+fn mouse_moves_accumulate() {
+    let (sim, mut client) = connect();
+    client.mouse_move(10, -5).unwrap();
+    client.mouse_move(3, 2).unwrap();
+    client.mouse_wheel(-1).unwrap();
+    client.mouse_move_auto(100, 0, 200).unwrap();
+
+    let state = sim.state();
+    assert_eq!(
+        (state.mouse.x, state.mouse.y, state.mouse.wheel),
+        (113, -3, -1)
+    );
+    let auto = state.commands.last().unwrap();
+    assert_eq!(auto.head.cmd, CMD_MOUSE_AUTOMOVE);
+    assert_eq!(auto.head.rand, 200);
+}
+
+#[test]
+fn mouse_buttons() {
+    let (sim, mut client) = connect();
+    client.mouse_left(true).unwrap();
+    client.mouse_right(true).unwrap();
+    client.mouse_side2(true).unwrap();
+    assert_eq!(sim.state().mouse.buttons, 0x13);
+
+    client.mouse_right(false).unwrap();
+    client.mouse_left(false).unwrap();
+    assert_eq!(sim.state().mouse.buttons, 0x10);
+}
+
+#[test]
+fn mouse_bezier_control_points() {
+    let (sim, mut client) = connect();
+    client.mouse_move_beizer(50, 60, 300, 1, 2, 3, 4).unwrap();
+
+    let state = sim.state();
+    assert_eq!(state.mouse.last.point[..4], [1, 2, 3, 4]);
+    assert_eq!((state.mouse.x, state.mouse.y), (50, 60));
+}
+
+#[test]
+fn encrypted_commands_are_decrypted() {
+    let (sim, mut client) = connect();
+    client.enc_mouse_move(7, 8).unwrap();
+    client.enc_mouse_left(true).unwrap();
+    client.enc_keydown(Key::Q).unwrap();
+
+    let state = sim.state();
+    assert_eq!((state.mouse.x, state.mouse.y), (7, 8));
+    assert_eq!(state.mouse.buttons, 0x01);
+    assert_eq!(state.keyboard.button[0], KEY_Q);
+    assert!(state.commands[1..].iter().all(|c| c.encrypted));
+}
+
+#[test]
+fn keyboard_state_tracks_keys_and_modifiers() {
+    let (sim, mut client) = connect();
+    client.keydown(Key::LeftShift).unwrap();
+    client.keydown(Key::A).unwrap();
+    client.keydown(Key::B).unwrap();
+
+    let state = sim.state();
+    assert_eq!(state.keyboard.ctrl, BIT1);
+    assert_eq!(state.keyboard.button[..3], [KEY_A, KEY_B, 0]);
+
+    client.keyup(Key::A).unwrap();
+    client.keyup(Key::LeftShift).unwrap();
+    let state = sim.state();
+    assert_eq!(state.keyboard.ctrl, 0);
+    assert_eq!(state.keyboard.button[..2], [KEY_B, 0]);
+}
+
+#[test]
+fn keypress_releases_key() {
+    let (sim, mut client) = connect();
+    client.keypress(Key::Enter, 10).unwrap();
+
+    let state = sim.state();
+    assert_eq!(state.keyboard.button, [0; 10]);
+    let presses: Vec<_> = state
+        .commands
+        .iter()
+        .filter(|c| c.head.cmd == CMD_KEYBOARD_ALL)
+        .collect();
+    assert_eq!(presses.len(), 2);
+}
+
+#[test]
+fn monitor_and_masks() {
+    let (sim, mut client) = connect();
+    client.monitor(12000).unwrap();
+    assert_eq!(sim.state().monitor_port, 12000);
+
+    client.mask_mouse_left(true).unwrap();
+    client.mask_mouse_wheel(true).unwrap();
+    client.mask_keyboard(Key::W).unwrap();
+    let state = sim.state();
+    assert_eq!(state.mask_mouse, 0x81);
+    assert_eq!(state.masked_keys, vec![KEY_W]);
+
+    client.unmask_keyboard(Key::W).unwrap();
+    assert!(sim.state().masked_keys.is_empty());
+
+    client.unmask_all().unwrap();
+    client.monitor(0).unwrap();
+    let state = sim.state();
+    assert_eq!(state.mask_mouse, 0);
+    assert_eq!(state.monitor_port, 0);
+}
+
+#[test]
+fn monitor_reports_reach_callback() {
+    let (sim, mut client) = connect();
+    let port = {
+        let probe = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap().port()
+    };
+    let (tx, rx) = mpsc::channel();
+    let _monitor = KmBoxNetMonitor::start(port, move |mouse, keyboard| {
+        let _ = tx.send((mouse, keyboard));
+    })
+    .unwrap();
+
+    client.monitor(port).unwrap();
+    let mouse = HardMouse {
+        buttons: 0x02,
+        x: -4,
+        y: 9,
+        wheel: 1,
+    };
+    let keyboard = HardKeyboard {
+        buttons: BIT0,
+        data: vec![KEY_Z],
+    };
+    sim.send_monitor_report(&mouse, &keyboard).unwrap();
+
+    let (got_mouse, got_keyboard) = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+    assert_eq!((got_mouse.buttons, got_mouse.x, got_mouse.y), (0x02, -4, 9));
+    assert_eq!(got_keyboard.buttons, BIT0);
+    assert_eq!(got_keyboard.data[0], KEY_Z);
+}
+
+#[test]
+fn system_configuration() {
+    let (sim, mut client) = connect();
+    client.set_config("192.168.2.10", 9999).unwrap();
+    client.set_vid_pid(0x046d, 0xc077).unwrap();
+    client.trace_enable(2, 40).unwrap();
+    client.reboot().unwrap();
+
+    let state = sim.state();
+    assert_eq!(state.config_ip, Some(Ipv4Addr::new(192, 168, 2, 10)));
+    assert_eq!(state.config_port, 9999);
+    assert_eq!((state.vid, state.pid), (0x046d, 0xc077));
+    assert_eq!((state.trace_type, state.trace_value), (2, 40));
+    assert_eq!(state.reboots, 1);
+    assert!(!state.connected);
+}
+
+#[test]
+fn lcd_color_fills_screen() {
+    let (sim, mut client) = connect();
+    client.lcd_color(0xF800).unwrap();
+
+    let state = sim.state();
+    assert!(state.lcd.iter().all(|&px| px == 0xF800));
+}
+
+#[test]
+fn lcd_pictures() {
+    let (sim, mut client) = connect();
+    let mut full = vec![0u8; 128 * 160 * 2];
+    // Row y is filled with colour y.
+    for (i, px) in full.chunks_exact_mut(2).enumerate() {
+        px.copy_from_slice(&((i / 128) as u16).to_le_bytes());
+    }
+    client.lcd_picture(&full).unwrap();
+    let state = sim.state();
+    assert_eq!(state.pixel(0, 0), 0);
+    assert_eq!(state.pixel(127, 159), 159);
+
+    let bottom = vec![0xFFu8; 128 * 80 * 2];
+    client.lcd_picture_bottom(&bottom).unwrap();
+    let state = sim.state();
+    assert_eq!(state.pixel(5, 79), 79);
+    assert_eq!(state.pixel(5, 80), 0xFFFF);
+
+    assert_eq!(
+        client.lcd_picture(&full[..100]).unwrap_err(),
+        KmError::NetCmd
+    );
+}
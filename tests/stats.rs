@@ -0,0 +1,50 @@
+mod common;
+
+use common::{config, MAC};
+use kmbox_net::protocol::{CMD_CONNECT, CMD_MOUSE_LEFT, CMD_MOUSE_MOVE};
+use kmbox_net::simulator::AckBehavior;
+use kmbox_net::{KmBoxNetClient, KmBoxNetSimulator};
+
+#[test]
+fn counts_commands_and_acks() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config()).unwrap();
+    client.mouse_move(1, 1).unwrap();
+    client.mouse_move(2, 2).unwrap();
+    client.enc_mouse_left(true).unwrap();
+
+    let stats = client.stats();
+    assert_eq!(stats.commands[&CMD_CONNECT], 1);
+    assert_eq!(stats.commands[&CMD_MOUSE_MOVE], 2);
+    assert_eq!(stats.commands[&CMD_MOUSE_LEFT], 1);
+    assert_eq!((stats.plain, stats.encrypted), (3, 1));
+    assert_eq!(stats.total_commands(), 4);
+    assert_eq!(stats.acks, 4);
+    assert_eq!((stats.timeouts, stats.retries), (0, 0));
+    let sent: usize = sim
+        .state()
+        .commands
+        .iter()
+        .map(|c| 16 + c.payload.len())
+        .sum();
+    assert_eq!(stats.bytes_sent, sent as u64);
+}
+
+#[test]
+fn counts_timeouts_and_retries() {
+    let sim = KmBoxNetSimulator::start("127.0.0.1:0", MAC).unwrap();
+    let config = config().retries(2);
+    let mut client = KmBoxNetClient::with_config("127.0.0.1", sim.port(), MAC, config).unwrap();
+
+    sim.drop_acks(1);
+    client.mouse_move(1, 1).unwrap();
+    sim.set_ack_behavior(AckBehavior::Silent);
+    assert!(client.mouse_move(1, 1).is_err());
+
+    let stats = client.stats();
+    assert_eq!(stats.commands[&CMD_MOUSE_MOVE], 2);
+    // One lost ack, then three for the command that never got through.
+    assert_eq!(stats.timeouts, 4);
+    assert_eq!(stats.retries, 3);
+    assert_eq!(stats.acks, 2);
+}